@@ -0,0 +1,8 @@
+extern crate earley_codegen;
+
+fn main() {
+    for name in &["wiki", "loup", "ambiguous", "statements"] {
+        earley_codegen::compile(format!("grammars/{}.bnf", name), &format!("{}.rs", name))
+            .unwrap_or_else(|e| panic!("Couldn't generate a parser for {}: {}", name, e));
+    }
+}
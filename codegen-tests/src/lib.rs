@@ -0,0 +1,20 @@
+//! Parsers generated by `earley-codegen` from the grammars in `grammars/`,
+//! tested against the interpreter in `tests/`.
+
+extern crate earley;
+
+pub mod wiki {
+    include!(concat!(env!("OUT_DIR"), "/wiki.rs"));
+}
+
+pub mod loup {
+    include!(concat!(env!("OUT_DIR"), "/loup.rs"));
+}
+
+pub mod ambiguous {
+    include!(concat!(env!("OUT_DIR"), "/ambiguous.rs"));
+}
+
+pub mod statements {
+    include!(concat!(env!("OUT_DIR"), "/statements.rs"));
+}
@@ -0,0 +1,130 @@
+extern crate bnf;
+extern crate codegen_tests;
+extern crate earley;
+extern crate linked_hash_set;
+
+use bnf::Grammar;
+use codegen_tests::{ambiguous, loup, statements, wiki};
+use earley::chart::EarleyChart;
+use earley::istate::IState;
+use earley::outcome::EarleyOutcome;
+use linked_hash_set::LinkedHashSet;
+use std::collections::HashSet;
+use std::fs;
+
+/// The generated parser has to agree with the interpreter on whether the
+/// input is accepted, on the states of the chart (though not their order)
+/// and on the trees.
+fn assert_same(generated: EarleyOutcome, grammar_file: &str, input: &str, split_on: Option<char>) {
+    let grammar = fs::read_to_string(grammar_file).unwrap();
+    let interpreted = EarleyChart::eval(&grammar, input, split_on).unwrap();
+
+    match (generated, interpreted) {
+        (EarleyOutcome::Accepted(generated), EarleyOutcome::Accepted(interpreted)) => {
+            assert_eq!(states(&generated.chart), states(&interpreted.chart));
+            assert_eq!(generated.accepted_states, interpreted.accepted_states);
+            assert_eq!(generated.input, interpreted.input);
+            assert_eq!(generated.tokenizer_info, interpreted.tokenizer_info);
+
+            let mut generated_trees = generated.parse_forest().unwrap();
+            let mut interpreted_trees = interpreted.parse_forest().unwrap();
+            generated_trees.sort_by_key(|t| format!("{:?}", t));
+            interpreted_trees.sort_by_key(|t| format!("{:?}", t));
+            assert_eq!(generated_trees, interpreted_trees);
+        }
+        (EarleyOutcome::Rejected, EarleyOutcome::Rejected) => {}
+        (generated, interpreted) => panic!(
+            "{:?} was {} by the generated parser but {} by the interpreter",
+            input,
+            describe(&generated),
+            describe(&interpreted)
+        ),
+    }
+}
+
+fn states(chart: &[LinkedHashSet<IState>]) -> Vec<HashSet<IState>> {
+    chart
+        .iter()
+        .map(|set| set.iter().cloned().collect())
+        .collect()
+}
+
+fn describe(outcome: &EarleyOutcome) -> &'static str {
+    match outcome {
+        EarleyOutcome::Accepted(_) => "accepted",
+        EarleyOutcome::Rejected => "rejected",
+    }
+}
+
+#[test]
+fn wiki_matches_interpreter() {
+    for input in &["2+3*4", "1", "4*4*4+1", "2+", "+2", "5", ""] {
+        assert_same(wiki::parse(input, None), "grammars/wiki.bnf", input, None);
+    }
+}
+
+#[test]
+fn loup_matches_interpreter() {
+    for input in &["1+(2*3-4)", "42", "(1)/(20-3)", "(1", "1++2"] {
+        assert_same(loup::parse(input, None), "grammars/loup.bnf", input, None);
+    }
+}
+
+#[test]
+fn ambiguous_matches_interpreter() {
+    for input in &["1+2*3", "1+2+3+1", "1*"] {
+        assert_same(
+            ambiguous::parse(input, None),
+            "grammars/ambiguous.bnf",
+            input,
+            None,
+        );
+    }
+
+    if let EarleyOutcome::Accepted(accepted) = ambiguous::parse("1+2+3+1", None) {
+        assert_eq!(accepted.parse_forest().unwrap().len(), 5);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn statements_matches_interpreter() {
+    for input in &[
+        "let x = y ;",
+        "let x = y + self ; print x + x ;",
+        "print let ;",
+        "let z = x ;",
+    ] {
+        assert_same(
+            statements::parse(input, Some(' ')),
+            "grammars/statements.bnf",
+            input,
+            Some(' '),
+        );
+    }
+}
+
+#[test]
+fn generated_symbols() {
+    assert_eq!(
+        statements::Nonterminal::StatementList.name(),
+        "statement list"
+    );
+    assert_eq!(
+        statements::Terminal::classify("let"),
+        Some(statements::Terminal::Let)
+    );
+    assert_eq!(
+        statements::Terminal::classify("self").map(|t| t.text()),
+        Some("self")
+    );
+    assert_eq!(statements::Terminal::classify("z"), None);
+    assert_eq!(wiki::Nonterminal::M as usize, 2);
+
+    let grammar: Grammar = fs::read_to_string("grammars/wiki.bnf")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(wiki::GRAMMAR.to_grammar(), grammar);
+}
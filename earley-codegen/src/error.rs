@@ -0,0 +1,38 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+    BnfError(String),
+    GrammarError(String),
+    IoError(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BnfError(ref s) => write!(f, "{}", s),
+            Error::GrammarError(ref s) => write!(f, "{}", s),
+            Error::IoError(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "Earley codegen error"
+    }
+}
+
+impl From<bnf::Error> for Error {
+    fn from(err: bnf::Error) -> Self {
+        Error::BnfError(format!("{:?}", err))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IoError(err.to_string())
+    }
+}
@@ -0,0 +1,283 @@
+extern crate bnf;
+
+mod error;
+
+pub use crate::error::Error;
+
+use bnf::{Grammar, Term};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Turns a BNF grammar into the source of a Rust module holding an
+/// `earley::compiled::CompiledGrammar` for it, meant to be run from a build
+/// script and `include!`d:
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     earley_codegen::compile("grammars/arith.bnf", "arith.rs").unwrap();
+/// }
+///
+/// // src/lib.rs
+/// pub mod arith {
+///     include!(concat!(env!("OUT_DIR"), "/arith.rs"));
+/// }
+/// ```
+///
+/// The module has a `Nonterminal` and a `Terminal` enum numbering the
+/// grammar's symbols, the `GRAMMAR` tables, and a `parse` function taking
+/// the same arguments as `EarleyChart::eval`.
+pub struct Codegen {
+    source: String,
+    grammar: Grammar,
+}
+
+/// Generates the module for the grammar in `grammar` (relative to the crate
+/// being built) into `$OUT_DIR/out`, and has cargo rerun the build script
+/// when the grammar changes.
+pub fn compile<P: AsRef<Path>>(grammar: P, out: &str) -> Result<(), Error> {
+    let out_dir = std::env::var("OUT_DIR")
+        .map_err(|_| Error::IoError("OUT_DIR isn't set, is this a build script?".to_string()))?;
+    println!("cargo:rerun-if-changed={}", grammar.as_ref().display());
+    Codegen::from_file(grammar)?.write(Path::new(&out_dir).join(out))
+}
+
+/// The grammar's symbols and rules, numbered.
+struct Tables {
+    nonterminals: Vec<String>,
+    terminals: Vec<String>,
+    rules: Vec<(usize, Vec<Term>)>,
+    start: Vec<usize>,
+}
+
+impl Codegen {
+    pub fn new(grammar: &str) -> Result<Codegen, Error> {
+        let parsed: Grammar = grammar.parse()?;
+        if parsed.productions_iter().next().is_none() {
+            return Err(Error::GrammarError(format!(
+                "No start state candidate found in grammar: {}",
+                grammar
+            )));
+        }
+        for production in parsed.productions_iter() {
+            if let Term::Terminal(_) = production.lhs {
+                return Err(Error::GrammarError(format!(
+                    "Production for terminal {} can't be compiled",
+                    production.lhs
+                )));
+            }
+        }
+
+        Ok(Codegen {
+            source: grammar.to_string(),
+            grammar: parsed,
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Codegen, Error> {
+        Codegen::new(&fs::read_to_string(path)?)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.generate())?;
+        Ok(())
+    }
+
+    fn tables(&self) -> Tables {
+        let mut tables = Tables {
+            nonterminals: vec![],
+            terminals: vec![],
+            rules: vec![],
+            start: vec![],
+        };
+        for (i, production) in self.grammar.productions_iter().enumerate() {
+            let lhs = tables.nonterminal(&production.lhs);
+            for expr in production.rhs_iter() {
+                if i == 0 {
+                    tables.start.push(tables.rules.len());
+                }
+                tables
+                    .rules
+                    .push((lhs, expr.terms_iter().cloned().collect()));
+            }
+        }
+        let terms: Vec<Term> = tables.rules.iter().flat_map(|r| r.1.clone()).collect();
+        for term in &terms {
+            match term {
+                Term::Nonterminal(_) => tables.nonterminal(term),
+                Term::Terminal(_) => tables.terminal(term),
+            };
+        }
+        tables
+    }
+
+    pub fn generate(&self) -> String {
+        let tables = self.tables();
+        let nonterminal_idents = idents(&tables.nonterminals, "N");
+        let terminal_idents = idents(&tables.terminals, "T");
+
+        let mut out = String::new();
+        out +=
+            "// Generated by earley-codegen, don't edit. The grammar it was generated from:\n//\n";
+        for line in self.source.trim().lines() {
+            writeln!(out, "//     {}", line.trim()).unwrap();
+        }
+        out += "\n";
+
+        out += "/// The nonterminals of the grammar, numbered as in `GRAMMAR`.\n";
+        out += "#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]\npub enum Nonterminal {\n";
+        for (i, name) in tables.nonterminals.iter().enumerate() {
+            writeln!(out, "    /// `<{}>`", name).unwrap();
+            writeln!(out, "    {} = {},", nonterminal_idents[i], i).unwrap();
+        }
+        out += "}\n\n";
+        out += "impl Nonterminal {\n    pub fn name(self) -> &'static str {\n        GRAMMAR.nonterminals[self as usize]\n    }\n}\n\n";
+
+        out += "/// The terminals of the grammar, numbered as in `GRAMMAR`.\n";
+        out += "#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]\npub enum Terminal {\n";
+        for (i, text) in tables.terminals.iter().enumerate() {
+            writeln!(out, "    /// `{:?}`", text).unwrap();
+            writeln!(out, "    {} = {},", terminal_idents[i], i).unwrap();
+        }
+        out += "}\n\n";
+        out += "impl Terminal {\n    pub fn text(self) -> &'static str {\n        GRAMMAR.terminals[self as usize]\n    }\n\n";
+        out += "    /// The terminal an input token is, if any.\n";
+        out += "    pub fn classify(token: &str) -> Option<Terminal> {\n        match token {\n";
+        for (i, text) in tables.terminals.iter().enumerate() {
+            writeln!(
+                out,
+                "            {:?} => Some(Terminal::{}),",
+                text, terminal_idents[i]
+            )
+            .unwrap();
+        }
+        out += "            _ => None,\n        }\n    }\n}\n\n";
+
+        out += "pub static GRAMMAR: ::earley::compiled::CompiledGrammar = ::earley::compiled::CompiledGrammar {\n";
+        writeln!(out, "    nonterminals: &{:?},", tables.nonterminals).unwrap();
+        writeln!(out, "    terminals: &{:?},", tables.terminals).unwrap();
+        out += "    rules: &[\n";
+        for (lhs, rhs) in &tables.rules {
+            let symbols: Vec<String> = rhs.iter().map(|t| tables.symbol(t)).collect();
+            writeln!(
+                out,
+                "        ::earley::compiled::Rule {{ lhs: {}, rhs: &[{}] }},",
+                lhs,
+                symbols.join(", ")
+            )
+            .unwrap();
+        }
+        out += "    ],\n";
+        writeln!(out, "    start: &{:?},", tables.start).unwrap();
+        out += "    predict: &[\n";
+        for n in 0..tables.nonterminals.len() {
+            writeln!(out, "        &{:?},", tables.predict(n)).unwrap();
+        }
+        out += "    ],\n";
+        out += "    classify: |token| Terminal::classify(token).map(|t| t as u32),\n};\n\n";
+
+        out += "pub fn parse(input: &str, split_on: Option<char>) -> ::earley::outcome::EarleyOutcome {\n    GRAMMAR.parse(input, split_on)\n}\n";
+        out
+    }
+}
+
+impl Tables {
+    fn nonterminal(&mut self, term: &Term) -> usize {
+        index_of(&mut self.nonterminals, term)
+    }
+
+    fn terminal(&mut self, term: &Term) -> usize {
+        index_of(&mut self.terminals, term)
+    }
+
+    fn symbol(&self, term: &Term) -> String {
+        match term {
+            Term::Nonterminal(s) => format!(
+                "::earley::compiled::Symbol::Nonterminal({})",
+                self.nonterminals.iter().position(|n| n == s).unwrap_or(0)
+            ),
+            Term::Terminal(s) => format!(
+                "::earley::compiled::Symbol::Terminal({})",
+                self.terminals.iter().position(|t| t == s).unwrap_or(0)
+            ),
+        }
+    }
+
+    /// The rules `earley_predict` adds, over as many rounds as it takes, for
+    /// an item waiting on nonterminal `n`: those of `n` and, for every rule
+    /// starting with a nonterminal, those of that nonterminal too.
+    fn predict(&self, n: usize) -> Vec<usize> {
+        let mut rules = vec![];
+        let mut seen: HashSet<usize> = HashSet::new();
+        let mut queue = vec![n];
+        seen.insert(n);
+        let mut i = 0;
+        while i < queue.len() {
+            for (r, (lhs, rhs)) in self.rules.iter().enumerate() {
+                if *lhs != queue[i] {
+                    continue;
+                }
+                rules.push(r);
+                if let Some(Term::Nonterminal(s)) = rhs.first() {
+                    let first = self.nonterminals.iter().position(|n| n == s).unwrap_or(0);
+                    if seen.insert(first) {
+                        queue.push(first);
+                    }
+                }
+            }
+            i += 1;
+        }
+        rules.sort_unstable();
+        rules
+    }
+}
+
+fn index_of(names: &mut Vec<String>, term: &Term) -> usize {
+    let name = match term {
+        Term::Nonterminal(s) | Term::Terminal(s) => s,
+    };
+    match names.iter().position(|n| n == name) {
+        Some(i) => i,
+        None => {
+            names.push(name.to_string());
+            names.len() - 1
+        }
+    }
+}
+
+/// An enum variant name for each symbol: the symbol in CamelCase when it's
+/// made of letters and digits, `prefix` and its index when it isn't (or when
+/// that name is taken).
+fn idents(names: &[String], prefix: &str) -> Vec<String> {
+    let mut taken: HashSet<String> = HashSet::new();
+    taken.insert("Self".to_string());
+
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let camel: String = name
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|part| !part.is_empty())
+                .map(|part| {
+                    let mut chars = part.chars();
+                    match chars.next() {
+                        Some(c) => c.to_ascii_uppercase().to_string() + chars.as_str(),
+                        None => String::new(),
+                    }
+                })
+                .collect();
+            let mut ident = match camel.chars().next() {
+                Some(c) if c.is_ascii_alphabetic() && !taken.contains(&camel) => camel,
+                _ => format!("{}{}", prefix, i),
+            };
+            while taken.contains(&ident) {
+                ident.push('_');
+            }
+            taken.insert(ident.clone());
+            ident
+        })
+        .collect()
+}
@@ -0,0 +1,182 @@
+use bnf::{Production, Term};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr};
+
+/// A `#[earley(rule = "...")]` attribute, checked to be valid BNF.
+struct Rule {
+    text: LitStr,
+    production: Production,
+}
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let type_name = name.to_string();
+
+    let mut arms = vec![];
+    let mut rules = vec![];
+    match &input.data {
+        Data::Struct(data) => {
+            let rule = rule(&input.attrs, name)?;
+            let build = build(quote!(#name), &data.fields, &rule)?;
+            arms.push(arm(&rule, build));
+            rules.push(rule.text);
+        }
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                let rule = rule(&variant.attrs, &variant.ident)?;
+                let ident = &variant.ident;
+                let build = build(quote!(#name::#ident), &variant.fields, &rule)?;
+                arms.push(arm(&rule, build));
+                rules.push(rule.text);
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                name,
+                "FromTree can't be derived for a union",
+            ))
+        }
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::earley::from_tree::FromTree for #name #ty_generics #where_clause {
+            fn from_tree(
+                tree: &::earley::tree::Tree,
+            ) -> ::std::result::Result<Self, ::earley::error::Error> {
+                #(#arms)*
+                Err(::earley::from_tree::mismatch(tree, #type_name, &[#(#rules),*]))
+            }
+        }
+    })
+}
+
+fn arm(rule: &Rule, build: TokenStream) -> TokenStream {
+    // `rule` has already checked the lhs is a nonterminal.
+    let lhs = match &rule.production.lhs {
+        Term::Nonterminal(lhs) | Term::Terminal(lhs) => lhs,
+    };
+    let alternatives = rule.production.rhs_iter().map(|expr| {
+        let terms = expr.terms_iter().map(|term| match term {
+            Term::Terminal(s) => quote!(::earley::grammar::StaticTerm::Terminal(#s)),
+            Term::Nonterminal(s) => quote!(::earley::grammar::StaticTerm::Nonterminal(#s)),
+        });
+        quote!(&[#(#terms),*])
+    });
+    quote! {
+        if ::earley::from_tree::matches(
+            tree,
+            &::earley::grammar::StaticProduction {
+                lhs: #lhs,
+                alternatives: &[#(#alternatives),*],
+            },
+        ) {
+            #build
+        }
+    }
+}
+
+/// The code filling `fields` from the branches of a tree matching `rule`.
+fn build(path: TokenStream, fields: &Fields, rule: &Rule) -> Result<TokenStream, Error> {
+    let mut nonterminals = 0;
+    let mut terminals = 0;
+    let mut values = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let field_name = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        if is_terminal(&field.attrs)? {
+            terminals += 1;
+            values.push(quote!(branches.terminal(#field_name)?));
+        } else {
+            nonterminals += 1;
+            values.push(quote!(branches.nonterminal(#field_name)?));
+        }
+    }
+
+    for expr in rule.production.rhs_iter() {
+        let count = |terminal: bool| {
+            expr.terms_iter()
+                .filter(|t| matches!(t, Term::Terminal(_)) == terminal)
+                .count()
+        };
+        if count(false) < nonterminals || count(true) < terminals {
+            return Err(Error::new_spanned(
+                &rule.text,
+                format!(
+                    "`{}` has {} nonterminal and {} terminal branches, too few for {} nonterminal and {} terminal fields",
+                    expr,
+                    count(false),
+                    count(true),
+                    nonterminals,
+                    terminals
+                ),
+            ));
+        }
+    }
+
+    let type_name = path.to_string().replace(' ', "");
+    let value = match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|f| &f.ident);
+            quote!(#path { #(#idents: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+        Fields::Unit => quote!(#path),
+    };
+
+    Ok(quote! {
+        #[allow(unused_mut, unused_variables)]
+        let mut branches = ::earley::from_tree::Branches::new(tree, #type_name);
+        return Ok(#value);
+    })
+}
+
+fn rule(attrs: &[Attribute], ident: &Ident) -> Result<Rule, Error> {
+    let mut text: Option<LitStr> = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("earley")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rule") {
+                text = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `rule = \"...\"`"))
+            }
+        })?;
+    }
+
+    let text = text.ok_or_else(|| {
+        Error::new_spanned(
+            ident,
+            format!("`{}` needs an `#[earley(rule = \"...\")]` attribute", ident),
+        )
+    })?;
+    let production: Production = text.value().parse().map_err(|e| {
+        Error::new_spanned(&text, format!("invalid rule `{}`: {}", text.value(), e))
+    })?;
+    if let Term::Terminal(_) = production.lhs {
+        return Err(Error::new_spanned(
+            &text,
+            "a rule's left hand side must be a nonterminal",
+        ));
+    }
+
+    Ok(Rule { text, production })
+}
+
+fn is_terminal(attrs: &[Attribute]) -> Result<bool, Error> {
+    let mut terminal = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("earley")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("terminal") {
+                terminal = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `terminal`"))
+            }
+        })?;
+    }
+    Ok(terminal)
+}
@@ -0,0 +1,230 @@
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use quote::quote;
+use std::collections::HashSet;
+use syn::{Error, Lit};
+
+enum Term {
+    Terminal(String),
+    Nonterminal(String, Span),
+}
+
+struct Production {
+    lhs: String,
+    alternatives: Vec<Vec<Term>>,
+}
+
+/// Parses the BNF given to `grammar!` straight from its tokens, so every
+/// error can point at the token that caused it, and lays it out as an
+/// `earley::grammar::StaticGrammar`.
+pub fn expand(input: TokenStream) -> Result<TokenStream, Error> {
+    let tokens: Vec<TokenTree> = flatten(input);
+    let productions = Parser { tokens, i: 0 }.productions()?;
+
+    let defined: HashSet<&str> = productions.iter().map(|p| p.lhs.as_str()).collect();
+    for production in &productions {
+        for term in production.alternatives.iter().flatten() {
+            if let Term::Nonterminal(name, span) = term {
+                if !defined.contains(name.as_str()) {
+                    return Err(Error::new(
+                        *span,
+                        format!("<{}> is used but never defined", name),
+                    ));
+                }
+            }
+        }
+    }
+
+    let productions = productions.iter().map(|p| {
+        let lhs = &p.lhs;
+        let alternatives = p.alternatives.iter().map(|terms| {
+            let terms = terms.iter().map(|t| match t {
+                Term::Terminal(s) => quote!(::earley::grammar::StaticTerm::Terminal(#s)),
+                Term::Nonterminal(s, _) => {
+                    quote!(::earley::grammar::StaticTerm::Nonterminal(#s))
+                }
+            });
+            quote!(&[#(#terms),*])
+        });
+        quote! {
+            ::earley::grammar::StaticProduction {
+                lhs: #lhs,
+                alternatives: &[#(#alternatives),*],
+            }
+        }
+    });
+
+    Ok(quote! {
+        ::earley::grammar::StaticGrammar {
+            productions: &[#(#productions),*],
+        }
+    })
+}
+
+/// Invisible groups (from tokens passed through `macro_rules!`) don't mean
+/// anything to BNF, so their contents are spliced in place.
+fn flatten(input: TokenStream) -> Vec<TokenTree> {
+    let mut tokens = vec![];
+    for token in input {
+        match token {
+            TokenTree::Group(ref g) if g.delimiter() == Delimiter::None => {
+                tokens.extend(flatten(g.stream()))
+            }
+            token => tokens.push(token),
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<TokenTree>,
+    i: usize,
+}
+
+impl Parser {
+    fn productions(&mut self) -> Result<Vec<Production>, Error> {
+        let mut productions = vec![];
+        while self.i < self.tokens.len() {
+            if self.is_punct(self.i, ';') {
+                self.i += 1;
+                continue;
+            }
+            productions.push(self.production()?);
+        }
+
+        if productions.is_empty() {
+            return Err(Error::new(Span::call_site(), "grammar has no productions"));
+        }
+        Ok(productions)
+    }
+
+    fn production(&mut self) -> Result<Production, Error> {
+        let (lhs, _) = match self.nonterminal()? {
+            Some(nonterminal) => nonterminal,
+            None => return Err(self.error("expected a nonterminal like `<name>`")),
+        };
+        self.defines()?;
+
+        let mut alternatives = vec![vec![]];
+        loop {
+            if self.i >= self.tokens.len() || self.is_punct(self.i, ';') || self.at_production() {
+                break;
+            }
+
+            if self.is_punct(self.i, '|') {
+                if alternatives.last().is_none_or(|a| a.is_empty()) {
+                    return Err(self.error("empty alternative"));
+                }
+                alternatives.push(vec![]);
+                self.i += 1;
+                continue;
+            }
+
+            let term = match self.nonterminal()? {
+                Some((name, span)) => Term::Nonterminal(name, span),
+                None => Term::Terminal(self.terminal()?),
+            };
+            if let Some(alternative) = alternatives.last_mut() {
+                alternative.push(term);
+            }
+        }
+
+        if alternatives.last().is_none_or(|a| a.is_empty()) {
+            let span = self.tokens[self.i.min(self.tokens.len()) - 1].span();
+            return Err(Error::new(
+                span,
+                format!("<{}> has an empty alternative", lhs),
+            ));
+        }
+
+        Ok(Production { lhs, alternatives })
+    }
+
+    /// Whether a new production (`<name> ::=`) starts at the current token.
+    fn at_production(&self) -> bool {
+        let mut j = self.i;
+        if !self.is_punct(j, '<') {
+            return false;
+        }
+        while j < self.tokens.len() && !self.is_punct(j, '>') {
+            j += 1;
+        }
+        self.is_punct(j + 1, ':') && self.is_punct(j + 2, ':') && self.is_punct(j + 3, '=')
+    }
+
+    fn defines(&mut self) -> Result<(), Error> {
+        for c in &[':', ':', '='] {
+            if !self.is_punct(self.i, *c) {
+                return Err(self.error("expected `::=`"));
+            }
+            self.i += 1;
+        }
+        Ok(())
+    }
+
+    fn nonterminal(&mut self) -> Result<Option<(String, Span)>, Error> {
+        if !self.is_punct(self.i, '<') {
+            return Ok(None);
+        }
+        let span = self.tokens[self.i].span();
+        self.i += 1;
+
+        let mut name = String::new();
+        let mut last_ident = false;
+        loop {
+            match self.tokens.get(self.i) {
+                Some(TokenTree::Punct(p)) if p.as_char() == '>' => break,
+                Some(TokenTree::Ident(ident)) => {
+                    if last_ident {
+                        name.push(' ');
+                    }
+                    name += &ident.to_string();
+                    last_ident = true;
+                }
+                Some(TokenTree::Punct(p)) if p.as_char() != '<' => {
+                    name.push(p.as_char());
+                    last_ident = false;
+                }
+                Some(TokenTree::Literal(l)) => {
+                    name += &l.to_string();
+                    last_ident = false;
+                }
+                Some(_) => return Err(self.error("unexpected token in a nonterminal name")),
+                None => return Err(Error::new(span, "unclosed `<`")),
+            }
+            self.i += 1;
+        }
+        self.i += 1;
+
+        if name.is_empty() {
+            return Err(Error::new(span, "empty nonterminal name"));
+        }
+        Ok(Some((name, span)))
+    }
+
+    fn terminal(&mut self) -> Result<String, Error> {
+        let value = match self.tokens.get(self.i) {
+            Some(TokenTree::Literal(literal)) => match Lit::new(literal.clone()) {
+                Lit::Str(s) => s.value(),
+                Lit::Char(c) => c.value().to_string(),
+                _ => return Err(self.error("expected a string literal terminal")),
+            },
+            _ => return Err(self.error("expected a terminal string or a `<nonterminal>`")),
+        };
+        self.i += 1;
+        Ok(value)
+    }
+
+    fn is_punct(&self, i: usize, c: char) -> bool {
+        match self.tokens.get(i) {
+            Some(TokenTree::Punct(p)) => p.as_char() == c,
+            _ => false,
+        }
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        match self.tokens.get(self.i) {
+            Some(token) => Error::new(token.span(), msg),
+            None => Error::new(Span::call_site(), format!("{} at end of grammar", msg)),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+extern crate bnf;
+extern crate proc_macro;
+
+mod from_tree;
+mod grammar;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Derives `earley::from_tree::FromTree`, see that trait for the attributes.
+#[proc_macro_derive(FromTree, attributes(earley))]
+pub fn derive_from_tree(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_tree::derive(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Checks a BNF grammar at compile time and expands to an
+/// `earley::grammar::StaticGrammar`, see that type for the syntax.
+#[proc_macro]
+pub fn grammar(input: TokenStream) -> TokenStream {
+    grammar::expand(input.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
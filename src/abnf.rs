@@ -0,0 +1,634 @@
+use crate::earley::EarleyParser;
+use crate::error::Error;
+use crate::lower::{self, Alternatives, Lower};
+use bnf::{Grammar, Term};
+use std::collections::HashMap;
+
+/// The core rules of RFC 5234, appendix B.1, added to a grammar that uses
+/// them without defining them.
+const CORE: &str = r#"
+ALPHA  = %x41-5A / %x61-7A
+BIT    = "0" / "1"
+CHAR   = %x01-7F
+CR     = %x0D
+CRLF   = CR LF
+CTL    = %x00-1F / %x7F
+DIGIT  = %x30-39
+DQUOTE = %x22
+HEXDIG = DIGIT / "A" / "B" / "C" / "D" / "E" / "F"
+HTAB   = %x09
+LF     = %x0A
+LWSP   = *(WSP / CRLF WSP)
+OCTET  = %x00-FF
+SP     = %x20
+VCHAR  = %x21-7E
+WSP    = SP / HTAB
+"#;
+
+/// The most chars a `%x..-..` range may span in a grammar built for no
+/// input in particular, since every one of them becomes an alternative.
+const MAX_RANGE: u32 = 1024;
+
+/// A grammar in ABNF (RFC 5234), the notation most protocol specs use,
+/// compiled into a `bnf::Grammar` for the Earley engine to parse with
+/// character by character, i.e. with `split_on` set to `None`:
+///
+/// ```ignore
+/// let abnf = Abnf::new(&fs::read_to_string("uri.abnf")?)?;
+/// let parser = EarleyParser::from_grammar(abnf.to_grammar_from("URI")?, input);
+/// let outcome = parser.earley_parse(None)?;
+/// ```
+///
+/// Rule names are case-insensitive and `=/` adds alternatives to a rule
+/// defined earlier. Repetitions (`*x`, `n*mx`, `nx`), options, groups,
+/// quoted strings (case-insensitive, or case-sensitive written `%s"..."`,
+/// RFC 7405) and `%b`, `%d`, `%x` values, series and ranges all work, and
+/// the core rules like `ALPHA` and `DIGIT` are there unless the grammar
+/// defines its own. Prose values (`<...>`) can't be parsed, except repeated
+/// zero times.
+///
+/// The engine's productions can't be empty, so the grammar's empty
+/// alternatives are taken out, along with the empty input when the start
+/// rule matches it. Groups, repetitions, ranges and case-insensitive
+/// letters get helper nonterminals named after their ABNF text, like
+/// `1*DIGIT` or `"a"`, which show up in the parse trees.
+///
+/// A range like `%x5D-10FFFF` can span most of Unicode, so, as with `Ebnf`,
+/// `parser` and `to_grammar_for` build the grammar for an input, the range
+/// only getting alternatives for the chars of the input it contains.
+/// `to_grammar` takes ranges of up to 1024 chars.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Abnf {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rule {
+    name: String,
+    line: usize,
+    alternation: Alternation,
+}
+
+type Alternation = Vec<Concatenation>;
+type Concatenation = Vec<Repetition>;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Repetition {
+    min: usize,
+    max: Option<usize>,
+    element: Element,
+    /// The element as written, with runs of whitespace made single spaces.
+    text: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Element {
+    Rule(String),
+    /// A quoted string and whether it's case-sensitive.
+    Text(String, bool),
+    Chars(Vec<char>),
+    Range(char, char),
+    Group(Alternation),
+    Option(Alternation),
+    Prose(String),
+}
+
+impl Abnf {
+    pub fn new(source: &str) -> Result<Abnf, Error> {
+        let mut rules: Vec<Rule> = vec![];
+        for (line, text) in rule_texts(source)? {
+            let mut parser = Parser {
+                chars: text.chars().collect(),
+                pos: 0,
+                line,
+            };
+            let (name, incremental, alternation) = parser.rule()?;
+            let existing = rules
+                .iter_mut()
+                .find(|r| r.name.eq_ignore_ascii_case(&name));
+            match (existing, incremental) {
+                (Some(rule), true) => rule.alternation.extend(alternation),
+                (None, false) => rules.push(Rule {
+                    name,
+                    line,
+                    alternation,
+                }),
+                (Some(rule), false) => {
+                    return Err(parser.error(&format!(
+                        "{} is already defined on line {}, add alternatives with `=/`",
+                        name, rule.line
+                    )))
+                }
+                (None, true) => {
+                    return Err(parser.error(&format!(
+                        "`=/` adds alternatives to {}, which isn't defined yet",
+                        name
+                    )))
+                }
+            }
+        }
+
+        if rules.is_empty() {
+            return Err(Error::AbnfError("No rules found in ABNF".to_string()));
+        }
+        Ok(Abnf { rules })
+    }
+
+    /// The grammar starting from the first rule.
+    pub fn to_grammar(&self) -> Result<Grammar, Error> {
+        self.to_grammar_from(&self.rules[0].name)
+    }
+
+    /// The grammar starting from rule `start`, with the rules it doesn't
+    /// use left out, so a fragment of a spec can leave those undefined.
+    pub fn to_grammar_from(&self, start: &str) -> Result<Grammar, Error> {
+        self.lower(start, None)
+    }
+
+    /// `to_grammar_from` with ranges matching the chars of `alphabet`, so
+    /// ranges of any size work.
+    pub fn to_grammar_for(&self, start: &str, alphabet: &str) -> Result<Grammar, Error> {
+        self.lower(start, Some(alphabet))
+    }
+
+    /// A parser for `input` starting from the first rule, with the grammar
+    /// built for the chars of `input`.
+    pub fn parser(&self, input: &str) -> Result<EarleyParser, Error> {
+        self.parser_from(&self.rules[0].name, input)
+    }
+
+    /// `parser` starting from rule `start`.
+    pub fn parser_from(&self, start: &str, input: &str) -> Result<EarleyParser, Error> {
+        Ok(EarleyParser::from_grammar(
+            self.to_grammar_for(start, input)?,
+            input,
+        ))
+    }
+
+    fn lower(&self, start: &str, alphabet: Option<&str>) -> Result<Grammar, Error> {
+        let core = Abnf::new(CORE)?;
+        let reachable = lower::reachable(
+            start,
+            |name| self.rule(name).or_else(|| core.rule(name)),
+            Error::AbnfError,
+            "ABNF",
+        )?;
+        let mut lowering = Lowering {
+            rules: reachable
+                .iter()
+                .map(|rule| (rule.name.clone(), vec![]))
+                .collect(),
+            names: reachable
+                .iter()
+                .map(|rule| (rule.name.to_ascii_lowercase(), rule.name.clone()))
+                .collect(),
+            alphabet: alphabet.map(lower::alphabet),
+        };
+        for (i, rule) in reachable.iter().enumerate() {
+            lowering.rules[i].1 = lowering.alternation(&rule.alternation)?;
+        }
+
+        // Built for an input, the start rule has no alternatives left when
+        // the input has none of the chars its ranges need, and matches
+        // nothing.
+        let rules = lower::remove_empty(lowering.rules);
+        if rules[0].1.is_empty() && lowering.alphabet.is_none() {
+            return Err(Error::AbnfError(format!(
+                "Rule {} only matches the empty string",
+                rules[0].0
+            )));
+        }
+        Ok(Grammar::from_parts(lower::productions(rules)))
+    }
+
+    fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .find(|r| r.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// The rules of `source`, each joined up with its continuation lines and
+/// without comments, along with the line it starts on. Rules start as far
+/// left as the first one does, as in a spec quoted with its indentation,
+/// and continuation lines further right.
+fn rule_texts(source: &str) -> Result<Vec<(usize, String)>, Error> {
+    let mut rules: Vec<(usize, String)> = vec![];
+    let mut margin = None;
+    for (i, line) in source.lines().enumerate() {
+        let line = strip_comment(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let margin = *margin.get_or_insert(indent);
+        if indent > margin {
+            match rules.last_mut() {
+                Some((_, text)) => {
+                    text.push(' ');
+                    text.push_str(line.trim());
+                }
+                None => unreachable!("the first line sets the margin"),
+            }
+        } else if indent < margin {
+            return Err(Error::AbnfError(format!(
+                "line {}: rule starts left of the first one",
+                i + 1
+            )));
+        } else {
+            rules.push((i + 1, line.trim().to_string()));
+        }
+    }
+    Ok(rules)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut close = None;
+    for (i, c) in line.char_indices() {
+        match (close, c) {
+            (None, ';') => return &line[..i],
+            (None, '"') => close = Some('"'),
+            (None, '<') => close = Some('>'),
+            (Some(end), c) if c == end => close = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+impl lower::Rule for Rule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn uses(&self) -> Vec<String> {
+        let mut used = vec![];
+        uses(&self.alternation, &mut used);
+        used
+    }
+}
+
+fn uses(alternation: &Alternation, used: &mut Vec<String>) {
+    for repetition in alternation.iter().flatten() {
+        match &repetition.element {
+            Element::Rule(name) if repetition.max != Some(0) => used.push(name.to_string()),
+            Element::Group(inner) | Element::Option(inner) => uses(inner, used),
+            _ => {}
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error(&self, msg: &str) -> Error {
+        Error::AbnfError(format!("line {}: {}", self.line, msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    /// `rulename ("=" / "=/") alternation`
+    fn rule(&mut self) -> Result<(String, bool, Alternation), Error> {
+        let name = self
+            .rulename()
+            .ok_or_else(|| self.error("expected a rule name"))?;
+        self.expect('=')?;
+        let incremental = self.peek() == Some('/');
+        if incremental {
+            self.pos += 1;
+        }
+        let alternation = self.alternation()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok((name, incremental, alternation)),
+            Some(c) => Err(self.error(&format!("unexpected `{}` in rule {}", c, name))),
+        }
+    }
+
+    fn rulename(&mut self) -> Option<String> {
+        if !self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            self.pos += 1;
+        }
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn alternation(&mut self) -> Result<Alternation, Error> {
+        let mut alternation = vec![self.concatenation()?];
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('/') {
+                return Ok(alternation);
+            }
+            self.pos += 1;
+            alternation.push(self.concatenation()?);
+        }
+    }
+
+    fn concatenation(&mut self) -> Result<Concatenation, Error> {
+        let mut concatenation = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some('/') | Some(')') | Some(']') => break,
+                Some(_) => concatenation.push(self.repetition()?),
+            }
+        }
+        if concatenation.is_empty() {
+            return Err(self.error("empty alternative"));
+        }
+        Ok(concatenation)
+    }
+
+    /// `[repeat] element`, where `repeat` is `n`, `n*`, `*m` or `n*m`.
+    fn repetition(&mut self) -> Result<Repetition, Error> {
+        let min = self.number(10);
+        let (min, max) = if self.peek() == Some('*') {
+            self.pos += 1;
+            (min.unwrap_or(0), self.number(10))
+        } else {
+            (min.unwrap_or(1), min.or(Some(1)))
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error(&format!("repetition {}*{:?} can't match", min, max)));
+        }
+
+        let start = self.pos;
+        let element = self.element()?;
+        let text = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        Ok(Repetition {
+            min,
+            max,
+            element,
+            text,
+        })
+    }
+
+    fn number(&mut self, radix: u32) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_digit(radix)) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        usize::from_str_radix(&digits, radix).ok()
+    }
+
+    fn element(&mut self) -> Result<Element, Error> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let alternation = self.alternation()?;
+                self.expect(')')?;
+                Ok(Element::Group(alternation))
+            }
+            Some('[') => {
+                self.pos += 1;
+                let alternation = self.alternation()?;
+                self.expect(']')?;
+                Ok(Element::Option(alternation))
+            }
+            Some('"') => Ok(Element::Text(self.quoted('"')?, false)),
+            Some('<') => Ok(Element::Prose(self.quoted('>')?)),
+            Some('%') => {
+                self.pos += 1;
+                match self.peek().map(|c| c.to_ascii_lowercase()) {
+                    Some('s') | Some('i') => {
+                        let sensitive = self.peek() == Some('s') || self.peek() == Some('S');
+                        self.pos += 1;
+                        if self.peek() != Some('"') {
+                            return Err(self.error("expected a quoted string after `%s` or `%i`"));
+                        }
+                        Ok(Element::Text(self.quoted('"')?, sensitive))
+                    }
+                    Some('b') => self.num_val(2),
+                    Some('d') => self.num_val(10),
+                    Some('x') => self.num_val(16),
+                    _ => Err(self.error("expected `%b`, `%d`, `%x`, `%s` or `%i`")),
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() => Ok(Element::Rule(self.rulename().unwrap())),
+            Some(c) => Err(self.error(&format!("unexpected `{}`", c))),
+            None => Err(self.error("expected an element")),
+        }
+    }
+
+    /// The text up to `end`, right after the opening quote or bracket.
+    fn quoted(&mut self, end: char) -> Result<String, Error> {
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != end) {
+            self.pos += 1;
+        }
+        if self.peek().is_none() {
+            return Err(self.error(&format!("missing closing `{}`", end)));
+        }
+        self.pos += 1;
+        Ok(self.chars[start..self.pos - 1].iter().collect())
+    }
+
+    /// A numeric value after its `%b`, `%d` or `%x`: a char, a series of
+    /// them separated by `.` or a range.
+    fn num_val(&mut self, radix: u32) -> Result<Element, Error> {
+        self.pos += 1;
+        let mut chars = vec![self.num_char(radix)?];
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            let to = self.num_char(radix)?;
+            if to < chars[0] {
+                return Err(self.error("range ends before it starts"));
+            }
+            return Ok(Element::Range(chars[0], to));
+        }
+        while self.peek() == Some('.') {
+            self.pos += 1;
+            chars.push(self.num_char(radix)?);
+        }
+        Ok(Element::Chars(chars))
+    }
+
+    fn num_char(&mut self, radix: u32) -> Result<char, Error> {
+        self.number(radix)
+            .and_then(|n| char::from_u32(n as u32))
+            .ok_or_else(|| self.error("expected a char value"))
+    }
+}
+
+/// The productions of the grammar being built.
+struct Lowering {
+    rules: Vec<(String, Alternatives)>,
+    /// The name each rule is defined with, by lowercase name.
+    names: HashMap<String, String>,
+    /// The chars ranges are matched against, when the grammar is built for
+    /// an input.
+    alphabet: Option<Vec<char>>,
+}
+
+impl Lower for Lowering {
+    type Item = Repetition;
+
+    fn rules(&mut self) -> &mut Vec<(String, Alternatives)> {
+        &mut self.rules
+    }
+
+    /// The sequences of terms `repetition` can be. Repeating more than once
+    /// goes through a helper `n*mx` (or `n*x`) matching the element at least
+    /// once, and the repetition can be nothing at all when `n` is 0.
+    fn item(&mut self, repetition: &Repetition) -> Result<Alternatives, Error> {
+        let (min, max) = (repetition.min, repetition.max);
+        if max == Some(0) {
+            return Ok(vec![vec![]]);
+        }
+        if min == 1 && max == Some(1) {
+            return self.element(&repetition.element, &repetition.text);
+        }
+
+        let term = self.term(&repetition.element, &repetition.text)?;
+        if Some(min) == max {
+            return Ok(vec![vec![term; min]]);
+        }
+
+        let least = min.max(1);
+        let helper = match max {
+            Some(max) => self.helper(
+                format!("{}*{}{}", least, max, repetition.text),
+                (least..=max).map(|n| vec![term.clone(); n]).collect(),
+            ),
+            None => {
+                let name = format!("1*{}", repetition.text);
+                let more = vec![term.clone(), Term::Nonterminal(name.clone())];
+                let once_or_more = self.helper(name, vec![vec![term.clone()], more]);
+                if least == 1 {
+                    once_or_more
+                } else {
+                    let mut more = vec![term.clone(); least - 1];
+                    more.push(once_or_more);
+                    self.helper(
+                        format!("{}*{}", least, repetition.text),
+                        vec![vec![term; least], more],
+                    )
+                }
+            }
+        };
+
+        let mut options = vec![vec![helper]];
+        if min == 0 {
+            options.push(vec![]);
+        }
+        Ok(options)
+    }
+}
+
+impl Lowering {
+    /// `element` as a single term, a helper named `text` when it isn't one.
+    fn term(&mut self, element: &Element, text: &str) -> Result<Term, Error> {
+        let mut alternatives = self.element(element, text)?;
+        if alternatives.len() == 1 && alternatives[0].len() == 1 {
+            return Ok(alternatives.remove(0).remove(0));
+        }
+        Ok(self.helper(text.to_string(), alternatives))
+    }
+
+    fn element(&mut self, element: &Element, text: &str) -> Result<Alternatives, Error> {
+        Ok(match element {
+            Element::Rule(name) => {
+                let name = &self.names[&name.to_ascii_lowercase()];
+                vec![vec![Term::Nonterminal(name.to_string())]]
+            }
+            Element::Text(s, sensitive) => vec![s
+                .chars()
+                .map(|c| {
+                    if *sensitive || c.to_ascii_lowercase() == c.to_ascii_uppercase() {
+                        Term::Terminal(c.to_string())
+                    } else {
+                        let lower = c.to_ascii_lowercase().to_string();
+                        let upper = c.to_ascii_uppercase().to_string();
+                        self.helper(
+                            format!("\"{}\"", lower),
+                            vec![vec![Term::Terminal(lower)], vec![Term::Terminal(upper)]],
+                        )
+                    }
+                })
+                .collect()],
+            Element::Chars(chars) => vec![chars
+                .iter()
+                .map(|c| Term::Terminal(c.to_string()))
+                .collect()],
+            Element::Range(from, to) => {
+                let chars: Vec<char> = match &self.alphabet {
+                    Some(alphabet) => alphabet
+                        .iter()
+                        .filter(|&c| from <= c && c <= to)
+                        .copied()
+                        .collect(),
+                    None if (*to as u32 - *from as u32) < MAX_RANGE => (*from..=*to).collect(),
+                    None => {
+                        return Err(Error::AbnfError(format!(
+                            "Range {} spans more than {} chars, build the grammar for an input",
+                            text, MAX_RANGE
+                        )))
+                    }
+                };
+                let alternatives = chars
+                    .into_iter()
+                    .map(|c| vec![Term::Terminal(c.to_string())])
+                    .collect();
+                vec![vec![self.helper(text.to_string(), alternatives)]]
+            }
+            Element::Group(alternation) if alternation.len() == 1 => {
+                self.alternation(alternation)?
+            }
+            Element::Group(alternation) => {
+                let alternatives = self.alternation(alternation)?;
+                vec![vec![self.helper(text.to_string(), alternatives)]]
+            }
+            Element::Option(alternation) => {
+                let mut alternatives = self.alternation(alternation)?;
+                alternatives.push(vec![]);
+                alternatives
+            }
+            Element::Prose(prose) => {
+                return Err(Error::AbnfError(format!(
+                    "Prose value <{}> can't be parsed",
+                    prose
+                )))
+            }
+        })
+    }
+}
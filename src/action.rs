@@ -0,0 +1,98 @@
+use crate::error::Error;
+use crate::forest::{Forest, ForestChild};
+use bnf::{Production, Term};
+use std::collections::HashMap;
+
+type Action<'a, T> = Box<dyn Fn(&[T]) -> T + 'a>;
+
+/// Semantic actions to run while building trees, keyed by production: each
+/// one gets the values of a node's branches, in order, and builds the
+/// node's value. Terminal leaves get their value from `terminal`.
+///
+/// A production without an action passes the value of its only branch
+/// through, which keeps unit productions like `<P> ::= <S>` free; any other
+/// production without one is an error.
+pub struct Actions<'a, T> {
+    terminal: Box<dyn Fn(&str) -> T + 'a>,
+    actions: HashMap<(Term, Vec<Term>), Action<'a, T>>,
+}
+
+impl<'a, T: Clone> Actions<'a, T> {
+    pub fn new<F: Fn(&str) -> T + 'a>(terminal: F) -> Actions<'a, T> {
+        Actions {
+            terminal: Box::new(terminal),
+            actions: HashMap::new(),
+        }
+    }
+
+    /// Runs `action` for every alternative of `production`, given in BNF,
+    /// e.g. `"<S> ::= <S> '+' <M>"`.
+    pub fn on<F>(mut self, production: &str, action: F) -> Result<Actions<'a, T>, Error>
+    where
+        F: Fn(&[T]) -> T + Clone + 'a,
+    {
+        let production: Production = production.parse()?;
+        for expr in production.rhs_iter() {
+            let key = (production.lhs.clone(), expr.terms_iter().cloned().collect());
+            self.actions.insert(key, Box::new(action.clone()));
+        }
+        Ok(self)
+    }
+
+    /// The value of every derivation in the forest. Each node's values are
+    /// computed once and shared by every derivation that uses the node, so
+    /// the action of a shared subtree runs once per derivation of that
+    /// subtree rather than once per derivation of the whole input.
+    pub fn evaluate(&self, forest: &Forest) -> Result<Vec<T>, Error> {
+        if forest.is_cyclic() {
+            return Err(Error::ParseForestError(
+                "Parse forest has cycles, it has infinitely many values".to_string(),
+            ));
+        }
+
+        let mut values: Vec<Vec<T>> = vec![vec![]; forest.nodes.len()];
+        for v in forest.postorder() {
+            let node = &forest.nodes[v];
+            let key = (node.prod.lhs.clone(), node.prod.rhs.clone());
+            let action = self.actions.get(&key);
+            if action.is_none() && node.prod.rhs.len() != 1 {
+                return Err(Error::ActionError(format!("No action for {}", node.prod)));
+            }
+
+            let mut node_values = vec![];
+            for family in &node.families {
+                let mut partials: Vec<Vec<T>> = vec![vec![]];
+                for child in family {
+                    let options = match child {
+                        ForestChild::Nonterminal(c) => values[*c].clone(),
+                        ForestChild::Terminal(_, s) => vec![(self.terminal)(s)],
+                    };
+                    partials = partials
+                        .iter()
+                        .flat_map(|partial| {
+                            options.iter().map(move |option| {
+                                let mut args = partial.clone();
+                                args.push(option.clone());
+                                args
+                            })
+                        })
+                        .collect();
+                }
+
+                for args in partials {
+                    node_values.push(match action {
+                        Some(action) => action(&args),
+                        None => args[0].clone(),
+                    });
+                }
+            }
+            values[v] = node_values;
+        }
+
+        Ok(forest
+            .roots
+            .iter()
+            .flat_map(|r| values[*r].iter().cloned())
+            .collect())
+    }
+}
@@ -0,0 +1,252 @@
+use crate::error::Error;
+use bnf::{Expression, Grammar, Production, Term};
+
+/// A symbol of an alternative: a term as written, or a group standing for
+/// a nonterminal named when the grammar is built.
+enum Symbol {
+    Term(Term),
+    Group(usize),
+}
+
+/// The alternatives of one rule, written term by term, with `or` starting
+/// the next alternative.
+pub struct RuleBuilder {
+    alternatives: Vec<Vec<Symbol>>,
+    groups: Vec<RuleBuilder>,
+    error: Option<String>,
+}
+
+impl RuleBuilder {
+    fn new() -> RuleBuilder {
+        RuleBuilder {
+            alternatives: vec![vec![]],
+            groups: vec![],
+            error: None,
+        }
+    }
+
+    fn fail(&mut self, message: String) {
+        if self.error.is_none() {
+            self.error = Some(message);
+        }
+    }
+
+    fn push(&mut self, symbol: Symbol) {
+        self.alternatives.last_mut().unwrap().push(symbol);
+    }
+
+    /// Appends nonterminal `<name>` to the current alternative.
+    pub fn nt(mut self, name: &str) -> RuleBuilder {
+        if name.is_empty() {
+            self.fail("empty nonterminal name".to_string());
+        }
+        self.push(Symbol::Term(Term::Nonterminal(name.to_string())));
+        self
+    }
+
+    /// Appends terminal `terminal` to the current alternative.
+    pub fn t(mut self, terminal: &str) -> RuleBuilder {
+        if terminal.is_empty() {
+            self.fail("empty terminal".to_string());
+        }
+        self.push(Symbol::Term(Term::Terminal(terminal.to_string())));
+        self
+    }
+
+    /// Starts the next alternative.
+    pub fn or(mut self) -> RuleBuilder {
+        self.alternatives.push(vec![]);
+        self
+    }
+
+    /// Appends a nonterminal of its own with the alternatives `group`
+    /// writes, e.g. `r.nt("E").group(|g| g.t("+").or().t("-")).nt("E")`.
+    /// It's named after the rule, `<E.1>`, `<E.2>` and so on, unless a rule
+    /// already has that name.
+    pub fn group<F>(mut self, group: F) -> RuleBuilder
+    where
+        F: FnOnce(RuleBuilder) -> RuleBuilder,
+    {
+        let group = group(RuleBuilder::new());
+        if let Some(message) = &group.error {
+            self.fail(message.to_string());
+        }
+        self.groups.push(group);
+        self.push(Symbol::Group(self.groups.len() - 1));
+        self
+    }
+
+    /// Any error in the alternatives written, including empty ones, which
+    /// the parser has no way to match.
+    fn check(&self) -> Result<(), String> {
+        if let Some(message) = &self.error {
+            return Err(message.to_string());
+        }
+        if self.alternatives.iter().any(Vec::is_empty) {
+            return Err("empty alternative".to_string());
+        }
+        self.groups.iter().try_for_each(RuleBuilder::check)
+    }
+
+    /// Adds the alternatives of `other`, which is for the same rule.
+    fn extend(&mut self, other: RuleBuilder) {
+        let offset = self.groups.len();
+        self.groups.extend(other.groups);
+        self.alternatives
+            .extend(other.alternatives.into_iter().map(|symbols| {
+                symbols
+                    .into_iter()
+                    .map(|symbol| match symbol {
+                        Symbol::Group(i) => Symbol::Group(i + offset),
+                        term => term,
+                    })
+                    .collect()
+            }));
+    }
+
+    /// Pushes the production for `<name>` and those of its groups, naming
+    /// each group after `name` with a name no other rule has.
+    fn productions(self, name: &str, taken: &mut Vec<String>, productions: &mut Vec<Production>) {
+        let names: Vec<String> = (1..=self.groups.len())
+            .map(|i| {
+                let mut group = format!("{}.{}", name, i);
+                while taken.contains(&group) {
+                    group.push('\'');
+                }
+                taken.push(group.to_string());
+                group
+            })
+            .collect();
+
+        let alternatives = self
+            .alternatives
+            .into_iter()
+            .map(|symbols| {
+                let terms = symbols
+                    .into_iter()
+                    .map(|symbol| match symbol {
+                        Symbol::Term(term) => term,
+                        Symbol::Group(i) => Term::Nonterminal(names[i].to_string()),
+                    })
+                    .collect();
+                Expression::from_parts(terms)
+            })
+            .collect();
+        productions.push(Production::from_parts(
+            Term::Nonterminal(name.to_string()),
+            alternatives,
+        ));
+
+        for (group, name) in self.groups.into_iter().zip(names) {
+            group.productions(&name, taken, productions);
+        }
+    }
+}
+
+/// Builds a grammar from Rust code, e.g. from configuration read at run
+/// time, without going through BNF:
+///
+/// ```ignore
+/// let grammar = GrammarBuilder::new()
+///     .rule("P", |r| r.nt("S"))
+///     .rule("S", |r| r.nt("S").t("+").nt("M").or().nt("M"))
+///     .rule("M", |r| r.nt("M").t("*").nt("T").or().nt("T"))
+///     .rule("T", |r| r.t("1").or().t("2").or().t("3").or().t("4"))
+///     .build()?;
+/// ```
+///
+/// Each rule is checked as it's added, and `build` reports the first
+/// error. Rules given twice have their alternatives merged, and the first
+/// rule is the start rule unless `start` says otherwise.
+#[derive(Default)]
+pub struct GrammarBuilder {
+    rules: Vec<(String, RuleBuilder)>,
+    start: Option<String>,
+    error: Option<Error>,
+}
+
+impl GrammarBuilder {
+    pub fn new() -> GrammarBuilder {
+        GrammarBuilder::default()
+    }
+
+    /// Adds the alternatives `rule` writes to `<name>`.
+    pub fn rule<F>(mut self, name: &str, rule: F) -> GrammarBuilder
+    where
+        F: FnOnce(RuleBuilder) -> RuleBuilder,
+    {
+        if self.error.is_some() {
+            return self;
+        }
+        if name.is_empty() {
+            self.error = Some(Error::GrammarError("Rule with an empty name".to_string()));
+            return self;
+        }
+
+        let rule = rule(RuleBuilder::new());
+        if let Err(message) = rule.check() {
+            self.error = Some(Error::GrammarError(format!(
+                "Rule <{}> has an {}",
+                name, message
+            )));
+            return self;
+        }
+        match self.rules.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => existing.extend(rule),
+            None => self.rules.push((name.to_string(), rule)),
+        }
+        self
+    }
+
+    /// Makes `<name>` the start rule.
+    pub fn start(mut self, name: &str) -> GrammarBuilder {
+        self.start = Some(name.to_string());
+        self
+    }
+
+    /// The grammar, once every nonterminal used has a rule.
+    pub fn build(mut self) -> Result<Grammar, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if let Some(start) = &self.start {
+            match self.rules.iter().position(|(n, _)| n == start) {
+                Some(i) => {
+                    let rule = self.rules.remove(i);
+                    self.rules.insert(0, rule);
+                }
+                None => {
+                    return Err(Error::GrammarError(format!(
+                        "Start rule <{}> isn't defined",
+                        start
+                    )))
+                }
+            }
+        }
+        if self.rules.is_empty() {
+            return Err(Error::GrammarError("Grammar has no rules".to_string()));
+        }
+
+        let mut taken: Vec<String> = self.rules.iter().map(|(n, _)| n.to_string()).collect();
+        let mut productions = vec![];
+        for (name, rule) in self.rules {
+            rule.productions(&name, &mut taken, &mut productions);
+        }
+
+        for production in &productions {
+            for expr in production.rhs_iter() {
+                for term in expr.terms_iter() {
+                    if let Term::Nonterminal(name) = term {
+                        if !taken.contains(name) {
+                            return Err(Error::GrammarError(format!(
+                                "Rule {} uses <{}>, which isn't defined",
+                                production.lhs, name
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Grammar::from_parts(productions))
+    }
+}
@@ -1,6 +1,11 @@
+use crate::action::Actions;
 use crate::earley::EarleyParser;
 use crate::error::Error;
+use crate::layout::Layout;
 use crate::outcome::EarleyOutcome;
+use crate::restrict::Restrictions;
+use crate::token::Trivia;
+use bnf::Grammar;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Clone, Debug, Eq, Hash, PartialEq)]
@@ -17,6 +22,64 @@ impl EarleyChart {
         Ok(outcome)
     }
 
+    pub fn eval_grammar(
+        grammar: &Grammar,
+        input: &str,
+        split_on: Option<char>,
+    ) -> Result<EarleyOutcome, Error> {
+        let parser = EarleyParser::from_grammar(grammar.clone(), input);
+        parser.earley_parse(split_on)
+    }
+
+    /// Parses `input` keeping the trivia around its tokens, see
+    /// `EarleyParser::earley_parse_lossless`.
+    pub fn eval_lossless(
+        grammar: &str,
+        input: &str,
+        trivia: &Trivia,
+    ) -> Result<EarleyOutcome, Error> {
+        let parser = EarleyParser::new(grammar, input)?;
+        parser.earley_parse_lossless(trivia)
+    }
+
+    /// Parses `input` letting `layout` through between symbols, see
+    /// `EarleyParser::with_layout`.
+    pub fn eval_layout(
+        grammar: &str,
+        input: &str,
+        split_on: Option<char>,
+        layout: Layout,
+    ) -> Result<EarleyOutcome, Error> {
+        let parser = EarleyParser::new(grammar, input)?.with_layout(layout)?;
+        parser.earley_parse(split_on)
+    }
+
+    /// Parses `input` with the lexical `restrictions`, see
+    /// `EarleyParser::with_restrictions`.
+    pub fn eval_restricted(
+        grammar: &str,
+        input: &str,
+        split_on: Option<char>,
+        restrictions: Restrictions,
+    ) -> Result<EarleyOutcome, Error> {
+        let parser = EarleyParser::new(grammar, input)?.with_restrictions(restrictions)?;
+        parser.earley_parse(split_on)
+    }
+
+    /// Parses `input` and returns the value `actions` build for each of its
+    /// trees, with no values for rejected input.
+    pub fn eval_with<T: Clone>(
+        grammar: &str,
+        input: &str,
+        split_on: Option<char>,
+        actions: &Actions<T>,
+    ) -> Result<Vec<T>, Error> {
+        match EarleyChart::eval(grammar, input, split_on)? {
+            EarleyOutcome::Accepted(accepted) => accepted.evaluate(actions),
+            EarleyOutcome::Rejected => Ok(vec![]),
+        }
+    }
+
     pub fn accept(grammar: &str, input: &str, split_on: Option<char>) -> Result<bool, Error> {
         let parser = EarleyParser::new(grammar, input)?;
         let res = parser.earley_parse(split_on)?;
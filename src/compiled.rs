@@ -0,0 +1,210 @@
+use crate::istate::IState;
+use crate::outcome::{EarleyAccepted, EarleyOutcome};
+use crate::prod::EarleyProd;
+use crate::token::TokenizerInfo;
+use bnf::{Expression, Grammar, Production, Term};
+use std::collections::HashSet;
+
+/// A term of a compiled rule: an index into `CompiledGrammar::terminals`
+/// or `CompiledGrammar::nonterminals`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Symbol {
+    Terminal(u32),
+    Nonterminal(u32),
+}
+
+/// One alternative of a production.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Rule {
+    pub lhs: u32,
+    pub rhs: &'static [Symbol],
+}
+
+/// The tables `earley-codegen` generates for a grammar at build time.
+///
+/// Every symbol is numbered, the input is classified into terminal ids once
+/// up front, and prediction is a lookup: `predict[n]` lists every rule
+/// `EarleyParser::earley_predict` would end up adding, directly or through
+/// the rules it predicts in turn, for an item waiting on nonterminal `n`.
+/// The recognizer then runs the same predict/scan/complete steps as
+/// `EarleyParser` without comparing a single `Term`, and the chart it builds
+/// holds the same states, so the `Tree`s it gives are the interpreter's.
+#[derive(Clone, Copy, Debug)]
+pub struct CompiledGrammar {
+    pub nonterminals: &'static [&'static str],
+    pub terminals: &'static [&'static str],
+    pub rules: &'static [Rule],
+    /// The alternatives of the grammar's first production.
+    pub start: &'static [u32],
+    pub predict: &'static [&'static [u32]],
+    /// The terminal id of an input token, if it's a terminal at all.
+    pub classify: fn(&str) -> Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Item {
+    rule: u32,
+    dot: u32,
+    origin: u32,
+}
+
+/// The items of one chart position, along with the items waiting on each
+/// nonterminal so completion doesn't have to search for them.
+struct ItemSet {
+    items: Vec<Item>,
+    seen: HashSet<Item>,
+    waiting: Vec<Vec<usize>>,
+}
+
+impl ItemSet {
+    fn new(nonterminals: usize) -> ItemSet {
+        ItemSet {
+            items: vec![],
+            seen: HashSet::new(),
+            waiting: vec![vec![]; nonterminals],
+        }
+    }
+
+    fn insert(&mut self, item: Item) {
+        if self.seen.insert(item) {
+            self.items.push(item);
+        }
+    }
+}
+
+impl CompiledGrammar {
+    pub fn parse(&self, input: &str, split_on: Option<char>) -> EarleyOutcome {
+        let tokenizer_info = TokenizerInfo::split(input, split_on);
+        let input_symbols = tokenizer_info.tokens();
+
+        let mut tokens = vec![];
+        for symbol in &input_symbols {
+            match (self.classify)(symbol) {
+                Some(id) => tokens.push(id),
+                None => return EarleyOutcome::Rejected,
+            }
+        }
+
+        let sets = self.recognize(&tokens);
+        let n = tokens.len() as u32;
+        let accepted: Vec<Item> = self
+            .start
+            .iter()
+            .map(|rule| Item {
+                rule: *rule,
+                dot: self.rules[*rule as usize].rhs.len() as u32,
+                origin: 0,
+            })
+            .filter(|item| sets[n as usize].seen.contains(item))
+            .collect();
+        if accepted.is_empty() {
+            return EarleyOutcome::Rejected;
+        }
+
+        let chart = sets
+            .iter()
+            .map(|set| set.items.iter().map(|item| self.state(item)).collect())
+            .collect();
+        let accepted_states = accepted.iter().map(|item| self.state(item)).collect();
+        EarleyOutcome::Accepted(Box::new(
+            EarleyAccepted::new(chart, accepted_states, input_symbols)
+                .with_tokenizer_info(tokenizer_info),
+        ))
+    }
+
+    fn recognize(&self, tokens: &[u32]) -> Vec<ItemSet> {
+        let mut sets: Vec<ItemSet> = (0..=tokens.len())
+            .map(|_| ItemSet::new(self.nonterminals.len()))
+            .collect();
+        for rule in self.start {
+            sets[0].insert(Item {
+                rule: *rule,
+                dot: 0,
+                origin: 0,
+            });
+        }
+
+        for k in 0..sets.len() {
+            let mut predicted = vec![false; self.nonterminals.len()];
+            let mut i = 0;
+            while i < sets[k].items.len() {
+                let item = sets[k].items[i];
+                let rule = &self.rules[item.rule as usize];
+                match rule.rhs.get(item.dot as usize) {
+                    Some(Symbol::Nonterminal(n)) => {
+                        sets[k].waiting[*n as usize].push(i);
+                        if !predicted[*n as usize] {
+                            predicted[*n as usize] = true;
+                            for r in self.predict[*n as usize] {
+                                sets[k].insert(Item {
+                                    rule: *r,
+                                    dot: 0,
+                                    origin: k as u32,
+                                });
+                            }
+                        }
+                    }
+                    Some(Symbol::Terminal(t)) => {
+                        if tokens.get(k) == Some(t) {
+                            sets[k + 1].insert(Item {
+                                dot: item.dot + 1,
+                                ..item
+                            });
+                        }
+                    }
+                    None => {
+                        // Every rule consumes input, so `origin < k` and the
+                        // items waiting there are all known by now.
+                        let origin = item.origin as usize;
+                        let advanced: Vec<Item> = sets[origin].waiting[rule.lhs as usize]
+                            .iter()
+                            .map(|w| sets[origin].items[*w])
+                            .map(|waiting| Item {
+                                dot: waiting.dot + 1,
+                                ..waiting
+                            })
+                            .collect();
+                        for item in advanced {
+                            sets[k].insert(item);
+                        }
+                    }
+                }
+                i += 1;
+            }
+        }
+
+        sets
+    }
+
+    fn state(&self, item: &Item) -> IState {
+        let rule = &self.rules[item.rule as usize];
+        let prod = EarleyProd::new(
+            Term::Nonterminal(self.nonterminals[rule.lhs as usize].to_string()),
+            rule.rhs.iter().map(|s| self.term(*s)).collect(),
+            item.dot as usize,
+        );
+        IState::new(prod, item.origin as usize)
+    }
+
+    fn term(&self, symbol: Symbol) -> Term {
+        match symbol {
+            Symbol::Terminal(t) => Term::Terminal(self.terminals[t as usize].to_string()),
+            Symbol::Nonterminal(n) => Term::Nonterminal(self.nonterminals[n as usize].to_string()),
+        }
+    }
+
+    /// The grammar the tables were generated from, one production per
+    /// nonterminal, for handing to the interpreter.
+    pub fn to_grammar(&self) -> Grammar {
+        let mut productions: Vec<Production> = vec![];
+        for rule in self.rules {
+            let lhs = Term::Nonterminal(self.nonterminals[rule.lhs as usize].to_string());
+            let expr = Expression::from_parts(rule.rhs.iter().map(|s| self.term(*s)).collect());
+            match productions.iter_mut().find(|p| p.lhs == lhs) {
+                Some(production) => production.add_to_rhs(expr),
+                None => productions.push(Production::from_parts(lhs, vec![expr])),
+            }
+        }
+        Grammar::from_parts(productions)
+    }
+}
@@ -1,13 +1,22 @@
 use crate::error::Error;
 use crate::istate::IState;
+use crate::layout::Layout;
 use crate::outcome::{EarleyAccepted, EarleyOutcome};
 use crate::prod::EarleyProd;
+use crate::restrict::Restrictions;
+use crate::token::{TokenizerInfo, Trivia};
 use bnf::{Expression, Grammar, Production, Term};
 use linked_hash_set::LinkedHashSet;
+use std::collections::HashMap;
+
+type Predicate = Box<dyn Fn(&[String]) -> bool>;
 
 pub struct EarleyParser {
     input: String,
     grammar: Grammar,
+    layout: Option<Layout>,
+    restrictions: Restrictions,
+    predicates: HashMap<(Term, Vec<Term>), Vec<Predicate>>,
 }
 
 impl EarleyParser {
@@ -15,9 +24,86 @@ impl EarleyParser {
         Ok(EarleyParser {
             input: input.to_string(),
             grammar: grammar.parse()?,
+            layout: None,
+            restrictions: Restrictions::new(),
+            predicates: HashMap::new(),
         })
     }
 
+    /// A parser for a grammar that's already been built, e.g. by the
+    /// `grammar!` macro.
+    pub fn from_grammar(grammar: Grammar, input: &str) -> EarleyParser {
+        EarleyParser {
+            input: input.to_string(),
+            grammar,
+            layout: None,
+            restrictions: Restrictions::new(),
+            predicates: HashMap::new(),
+        }
+    }
+
+    /// Lets `layout` through between the symbols of the grammar's
+    /// productions and around the input, see `Layout`.
+    pub fn with_layout(mut self, layout: Layout) -> Result<EarleyParser, Error> {
+        self.layout = Some(layout.resolve(&self.grammar)?);
+        Ok(self)
+    }
+
+    /// Enforces `restrictions` while parsing, see `Restrictions`.
+    pub fn with_restrictions(mut self, restrictions: Restrictions) -> Result<EarleyParser, Error> {
+        restrictions.check(&self.grammar)?;
+        self.restrictions = restrictions;
+        Ok(self)
+    }
+
+    /// Has `predicate` veto completions of every alternative of
+    /// `production`, given in BNF, e.g. `"<num> ::= <digit> <num>"`. It gets
+    /// the tokens the completed state spans and the state is dropped when it
+    /// returns false, so the derivations it rules out never reach the parse
+    /// forest. A production can have several predicates, which all have to
+    /// hold.
+    pub fn with_predicate<F>(self, production: &str, predicate: F) -> Result<EarleyParser, Error>
+    where
+        F: Fn(&[String]) -> bool + Clone + 'static,
+    {
+        let production: Production = production.parse()?;
+        self.with_production_predicate(&production, predicate)
+    }
+
+    /// `with_predicate` for a production that's already been built, which
+    /// helps when its nonterminals can't be written in BNF. Every
+    /// alternative of `production` has to be one of the grammar's, since a
+    /// misspelt one would never be vetoed.
+    pub fn with_production_predicate<F>(
+        mut self,
+        production: &Production,
+        predicate: F,
+    ) -> Result<EarleyParser, Error>
+    where
+        F: Fn(&[String]) -> bool + Clone + 'static,
+    {
+        for expr in production.rhs_iter() {
+            let defined = self
+                .grammar
+                .productions_iter()
+                .any(|p| p.lhs == production.lhs && p.rhs_iter().any(|e| e == expr));
+            if !defined {
+                return Err(Error::GrammarError(format!(
+                    "Predicate production {} ::= {} isn't in grammar: {}",
+                    production.lhs, expr, self.grammar
+                )));
+            }
+        }
+        for expr in production.rhs_iter() {
+            let key = (production.lhs.clone(), expr.terms_iter().cloned().collect());
+            self.predicates
+                .entry(key)
+                .or_default()
+                .push(Box::new(predicate.clone()));
+        }
+        Ok(self)
+    }
+
     fn get_start_states(&self) -> Result<LinkedHashSet<IState>, Error> {
         match self.grammar.productions_iter().peekable().peek() {
             Some(p) => {
@@ -38,16 +124,25 @@ impl EarleyParser {
     }
 
     pub fn earley_parse(self, split_on: Option<char>) -> Result<EarleyOutcome, Error> {
-        let input_symbols: Vec<String>;
-        if let Some(split_char) = split_on {
-            input_symbols = self
-                .input
-                .split(split_char)
-                .map(|s| s.to_string())
-                .collect();
-        } else {
-            input_symbols = self.input.chars().map(|c| c.to_string()).collect();
-        }
+        let tokenizer_info = TokenizerInfo::split(&self.input, split_on);
+        self.earley_parse_tokens(tokenizer_info)
+    }
+
+    /// Parses the words (or chars) of the input around the whitespace and
+    /// comments `trivia` skips, keeping both so the trees of
+    /// `EarleyAccepted::lossless_parse_forest` reproduce the input exactly.
+    pub fn earley_parse_lossless(self, trivia: &Trivia) -> Result<EarleyOutcome, Error> {
+        let tokenizer_info = TokenizerInfo::lossless(&self.input, trivia);
+        Ok(match self.earley_parse_tokens(tokenizer_info)? {
+            EarleyOutcome::Accepted(accepted) => {
+                EarleyOutcome::Accepted(Box::new(accepted.with_trivia(trivia.clone())))
+            }
+            EarleyOutcome::Rejected => EarleyOutcome::Rejected,
+        })
+    }
+
+    fn earley_parse_tokens(self, tokenizer_info: TokenizerInfo) -> Result<EarleyOutcome, Error> {
+        let input_symbols = tokenizer_info.tokens();
 
         let start_states = self.get_start_states()?;
 
@@ -63,9 +158,9 @@ impl EarleyParser {
 
                 chart[k] = self.earley_predict(k, &chart[k]);
                 if k + 1 < chart.len() && k < input_symbols.len() {
-                    chart[k + 1] = self.earley_scan(input_symbols[k].clone(), &chart[k]);
+                    chart[k + 1] = self.earley_scan(k, &input_symbols, &chart[k]);
                 }
-                chart[k] = self.earley_complete(&chart[k], &chart);
+                chart[k] = self.earley_complete(k, &input_symbols, &chart[k], &chart);
             }
         }
 
@@ -75,11 +170,12 @@ impl EarleyParser {
             input_symbols.len(),
             chart.len(),
         ) {
-            Ok(EarleyOutcome::Accepted(EarleyAccepted::new(
-                chart,
-                accepted_states,
-                input_symbols,
-            )))
+            let mut accepted = EarleyAccepted::new(chart, accepted_states, input_symbols)
+                .with_tokenizer_info(tokenizer_info);
+            if let Some(layout) = self.layout {
+                accepted = accepted.with_layout(layout);
+            }
+            Ok(EarleyOutcome::Accepted(Box::new(accepted)))
         } else {
             Ok(EarleyOutcome::Rejected)
         }
@@ -142,7 +238,7 @@ impl EarleyParser {
         let find_productions_in_grammar = |term: &Term| {
             let mut ret: Vec<&Production> = vec![];
             for p in self.grammar.productions_iter() {
-                if (*p).lhs == *term {
+                if p.lhs == *term {
                     // ret.push(p.clone())
                     ret.push(p)
                 }
@@ -154,6 +250,16 @@ impl EarleyParser {
         let mut ret_state_set: LinkedHashSet<IState> = state_set.clone();
 
         for state in state_set.iter() {
+            if let Some(layout) = self.layout.as_ref().filter(|_| self.layout_gap(state)) {
+                find_productions_in_grammar(layout.nonterminal())
+                    .iter()
+                    .flat_map(|p| p.rhs_iter())
+                    .for_each(|e| {
+                        let rhs = e.terms_iter().cloned().collect::<Vec<Term>>();
+                        let earley_prod = EarleyProd::new(layout.nonterminal().clone(), rhs, 0);
+                        ret_state_set.insert(IState::new(earley_prod, k));
+                    });
+            }
             if let Some(term) = state.prod.get_next() {
                 if let Term::Nonterminal(_) = term {
                     // let prods = self.find_productions_in_grammar(term);
@@ -184,19 +290,24 @@ impl EarleyParser {
     /// add `new_state: IState` to the returned state set, where:
     /// new_state = curr_state.clone()
     /// new_state.prod.dot = curr_state.prod.dot + 1
+    ///
+    /// Terminals with a lookahead only match when the symbol after `a` is
+    /// one they allow, and states completed by the scan have to get past the
+    /// restrictions and predicates too, see `admits`.
     fn earley_scan(
         &self,
-        symbol: String,
+        k: usize,
+        input: &[String],
         state_set: &LinkedHashSet<IState>,
     ) -> LinkedHashSet<IState> {
         let mut ret_state_set: LinkedHashSet<IState> = LinkedHashSet::new();
 
         for state in state_set.iter() {
-            if let Some(term) = state.prod.get_next() {
-                if let Term::Terminal(s) = term {
-                    if *s == symbol {
-                        let mut incremented = state.clone();
-                        incremented.prod.dot = state.prod.dot + 1;
+            if let Some(term @ Term::Terminal(s)) = state.prod.get_next() {
+                if *s == input[k] && self.restrictions.admits_terminal(term, input.get(k + 1)) {
+                    let mut incremented = state.clone();
+                    incremented.prod.dot = state.prod.dot + 1;
+                    if self.admits(&incremented, k + 1, input) {
                         ret_state_set.insert(incremented);
                     }
                 }
@@ -219,8 +330,15 @@ impl EarleyParser {
     /// and add a state to the returned state set where:
     /// new_state = chart_state.clone()
     /// new_state.dot = chart_state.prod.dot + 1
+    ///
+    /// With a `Layout`, a completed layout state (L → γ •, j) also carries
+    /// every state in S(j) sitting at a gap layout may fill over to S(k)
+    /// as it is, see `layout_gap`. Like scanning, completion leaves out the
+    /// completed states `Restrictions` or predicates rule out.
     fn earley_complete(
         &self,
+        k: usize,
+        input: &[String],
         state_set: &LinkedHashSet<IState>,
         chart: &[LinkedHashSet<IState>],
     ) -> LinkedHashSet<IState> {
@@ -244,11 +362,66 @@ impl EarleyParser {
                 for n in next_states {
                     let mut new_state = n.clone();
                     new_state.prod.dot = n.prod.dot + 1;
-                    ret_state_set.insert(new_state);
+                    if self.admits(&new_state, k, input) {
+                        ret_state_set.insert(new_state);
+                    }
+                }
+
+                let is_layout = self
+                    .layout
+                    .as_ref()
+                    .is_some_and(|layout| state.prod.lhs == *layout.nonterminal());
+                if is_layout {
+                    let at_end = k + 1 == chart.len();
+                    for gap in chart[state.origin]
+                        .iter()
+                        .filter(|s| self.layout_gap(s) && (at_end || s.prod.get_next().is_some()))
+                    {
+                        ret_state_set.insert(gap.clone());
+                    }
                 }
             }
         }
 
         ret_state_set
     }
+
+    /// Whether `state`, in S(k), gets past the restrictions and predicates:
+    /// always when it isn't completed yet.
+    fn admits(&self, state: &IState, k: usize, input: &[String]) -> bool {
+        if state.prod.get_next().is_some() {
+            return true;
+        }
+        if !self.restrictions.admits_completed(state, k, input) {
+            return false;
+        }
+        let key = (state.prod.lhs.clone(), state.prod.rhs.clone());
+        match self.predicates.get(&key) {
+            Some(predicates) => predicates
+                .iter()
+                .all(|predicate| predicate(&input[state.origin..k])),
+            None => true,
+        }
+    }
+
+    /// Whether layout may come right where `state`'s dot is: between two
+    /// symbols of a production that isn't lexical, or before or after the
+    /// start production at the ends of the input. (`earley_complete` only
+    /// lets the end of the start production through at the end of the
+    /// input.)
+    fn layout_gap(&self, state: &IState) -> bool {
+        let layout = match &self.layout {
+            Some(layout) => layout,
+            None => return false,
+        };
+        let len = state.prod.rhs.len();
+        let is_start = state.origin == 0
+            && self
+                .grammar
+                .productions_iter()
+                .next()
+                .is_some_and(|p| p.lhs == state.prod.lhs);
+        (layout.allows_between(&state.prod) && state.prod.dot > 0 && state.prod.dot < len)
+            || (is_start && (state.prod.dot == 0 || state.prod.dot == len))
+    }
 }
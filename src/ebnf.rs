@@ -0,0 +1,610 @@
+use crate::earley::EarleyParser;
+use crate::error::Error;
+use crate::lower::{self, Alternatives, Lower};
+use crate::outcome::EarleyOutcome;
+use bnf::{Grammar, Production, Term};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A grammar in the EBNF notation of the W3C specs (XML 1.0, section 6),
+/// so productions can be pasted straight from a standard:
+///
+/// ```text
+/// [4]  NameStartChar ::= ":" | [A-Z] | "_" | [a-z] | [#xC0-#xD6]
+/// [15] Comment       ::= '<!--' ((Char - '-') | ('-' (Char - '-')))* '-->'
+/// ```
+///
+/// Rule numbers like `[4]`, `/* comments */` and well-formedness and
+/// validity constraints (`[ WFC: ... ]`, `[ VC: ... ]`) are skipped. Quoted
+/// strings, `#xN` chars, `[a-z]` and `[^...]` classes, groups, `A?`, `A*`,
+/// `A+` and the exclusion `A - B` all work, and the grammar is parsed char
+/// by char.
+///
+/// A class can span most of Unicode, so the grammar is built for an input:
+/// a class only gets alternatives for the chars of the input it contains.
+/// Exclusions are enforced by the parser `parser` sets up: a completed
+/// `A - B` is dropped when its span matches `B`, so grammars from `to_grammar`
+/// match `A` wherever the spec says `A - B`.
+///
+/// Like ABNF, groups, repetitions, classes and exclusions get helper
+/// nonterminals named after their EBNF text, and the empty alternatives are
+/// taken out.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ebnf {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rule {
+    name: String,
+    line: usize,
+    alternation: Alternation,
+}
+
+type Alternation = Vec<Vec<Item>>;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Item {
+    expr: Expr,
+    /// The item as written, with runs of whitespace made single spaces.
+    text: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Expr {
+    Rule(String),
+    Text(String),
+    Char(char),
+    /// A char class, negated or not, as its ranges.
+    Class(bool, Vec<(char, char)>),
+    Group(Alternation),
+    Optional(Box<Item>),
+    ZeroOrMore(Box<Item>),
+    OneOrMore(Box<Item>),
+    Exclusion(Box<Item>, Box<Item>),
+}
+
+impl Ebnf {
+    pub fn new(source: &str) -> Result<Ebnf, Error> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let mut rules: Vec<Rule> = vec![];
+        loop {
+            parser.skip_whitespace();
+            if parser.peek().is_none() {
+                break;
+            }
+            let line = parser.line();
+            let (name, alternation) = parser.rule()?;
+            if let Some(rule) = rules.iter().find(|r| r.name == name) {
+                return Err(Error::EbnfError(format!(
+                    "line {}: {} is already defined on line {}",
+                    line, name, rule.line
+                )));
+            }
+            rules.push(Rule {
+                name,
+                line,
+                alternation,
+            });
+        }
+
+        if rules.is_empty() {
+            return Err(Error::EbnfError("No rules found in EBNF".to_string()));
+        }
+        Ok(Ebnf { rules })
+    }
+
+    /// The grammar starting from the first rule, with classes matching the
+    /// chars of `alphabet`, and without its exclusions.
+    pub fn to_grammar(&self, alphabet: &str) -> Result<Grammar, Error> {
+        self.to_grammar_from(&self.rules[0].name, alphabet)
+    }
+
+    /// `to_grammar` starting from rule `start`, with the rules it doesn't use
+    /// left out.
+    pub fn to_grammar_from(&self, start: &str, alphabet: &str) -> Result<Grammar, Error> {
+        Ok(self
+            .lower(start, alphabet)?
+            .grammar_from(&self.start(start)))
+    }
+
+    /// A parser for `input` starting from the first rule, enforcing the
+    /// grammar's exclusions.
+    pub fn parser(&self, input: &str) -> Result<EarleyParser, Error> {
+        self.parser_from(&self.rules[0].name, input)
+    }
+
+    /// `parser` starting from rule `start`.
+    pub fn parser_from(&self, start: &str, input: &str) -> Result<EarleyParser, Error> {
+        Rc::new(self.lower(start, input)?).parser(&self.start(start), input)
+    }
+
+    fn start(&self, start: &str) -> Term {
+        Term::Nonterminal(start.to_string())
+    }
+
+    fn lower(&self, start: &str, alphabet: &str) -> Result<Lowered, Error> {
+        let reachable = lower::reachable(
+            start,
+            |name| self.rules.iter().find(|r| r.name == name),
+            Error::EbnfError,
+            "EBNF",
+        )?;
+        let mut lowering = Lowering {
+            rules: reachable
+                .iter()
+                .map(|rule| (rule.name.clone(), vec![]))
+                .collect(),
+            alphabet: lower::alphabet(alphabet),
+            exclusions: vec![],
+        };
+        for (i, rule) in reachable.iter().enumerate() {
+            lowering.rules[i].1 = lowering.alternation(&rule.alternation)?;
+        }
+
+        Ok(Lowered {
+            productions: lower::productions(lower::remove_empty(lowering.rules)),
+            exclusions: lowering.exclusions,
+            matched: RefCell::new(HashMap::new()),
+        })
+    }
+}
+
+impl lower::Rule for Rule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn line(&self) -> usize {
+        self.line
+    }
+
+    fn uses(&self) -> Vec<String> {
+        let mut used = vec![];
+        uses(&self.alternation, &mut used);
+        used
+    }
+}
+
+fn uses(alternation: &Alternation, used: &mut Vec<String>) {
+    for item in alternation.iter().flatten() {
+        expr_uses(&item.expr, used);
+    }
+}
+
+fn expr_uses(expr: &Expr, used: &mut Vec<String>) {
+    match expr {
+        Expr::Rule(name) => used.push(name.to_string()),
+        Expr::Group(inner) => uses(inner, used),
+        Expr::Optional(item) | Expr::ZeroOrMore(item) | Expr::OneOrMore(item) => {
+            expr_uses(&item.expr, used)
+        }
+        Expr::Exclusion(a, b) => {
+            expr_uses(&a.expr, used);
+            expr_uses(&b.expr, used);
+        }
+        Expr::Text(_) | Expr::Char(_) | Expr::Class(_, _) => {}
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        1 + self.chars[..self.pos]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::EbnfError(format!("line {}: {}", self.line(), msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn at(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    /// Skips whitespace, comments and constraints like `[ WFC: ... ]`.
+    fn skip_whitespace(&mut self) {
+        loop {
+            if self.peek().is_some_and(char::is_whitespace) {
+                self.pos += 1;
+            } else if self.at("/*") {
+                self.skip_past("*/");
+            } else if self.at_constraint() {
+                self.skip_past("]");
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) {
+        while self.peek().is_some() && !self.at(end) {
+            self.pos += 1;
+        }
+        self.pos = (self.pos + end.len()).min(self.chars.len());
+    }
+
+    fn at_constraint(&self) -> bool {
+        if self.peek() != Some('[') {
+            return false;
+        }
+        let rest: String = self.chars[self.pos + 1..]
+            .iter()
+            .take_while(|c| **c != ']')
+            .collect();
+        let rest = rest.trim_start().to_ascii_lowercase();
+        rest.starts_with("wfc:") || rest.starts_with("vc:")
+    }
+
+    /// Whether a rule starts here: `[n]? Name ::=`.
+    fn at_rule(&mut self) -> bool {
+        let start = self.pos;
+        self.skip_whitespace();
+        self.rule_number();
+        self.skip_whitespace();
+        let found = self.name().is_some() && {
+            self.skip_whitespace();
+            self.at("::=")
+        };
+        self.pos = start;
+        found
+    }
+
+    fn rule_number(&mut self) {
+        let start = self.pos;
+        if self.peek() == Some('[') {
+            self.pos += 1;
+            while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                self.pos += 1;
+            }
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return;
+            }
+        }
+        self.pos = start;
+    }
+
+    fn name(&mut self) -> Option<String> {
+        if !self.peek().is_some_and(|c| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+        {
+            self.pos += 1;
+        }
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn rule(&mut self) -> Result<(String, Alternation), Error> {
+        self.rule_number();
+        self.skip_whitespace();
+        let name = self
+            .name()
+            .ok_or_else(|| self.error("expected a rule name"))?;
+        self.skip_whitespace();
+        if !self.at("::=") {
+            return Err(self.error(&format!("expected `::=` after {}", name)));
+        }
+        self.pos += 3;
+        let alternation = self.alternation()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok((name, alternation)),
+            Some(_) if self.at_rule() => Ok((name, alternation)),
+            Some(c) => Err(self.error(&format!("unexpected `{}` in rule {}", c, name))),
+        }
+    }
+
+    fn alternation(&mut self) -> Result<Alternation, Error> {
+        let mut alternation = vec![self.sequence()?];
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('|') {
+                return Ok(alternation);
+            }
+            self.pos += 1;
+            alternation.push(self.sequence()?);
+        }
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Item>, Error> {
+        let mut sequence = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some('|') | Some(')') => break,
+                Some(_) if self.at_rule() => break,
+                Some(_) => sequence.push(self.exclusion()?),
+            }
+        }
+        if sequence.is_empty() {
+            return Err(self.error("empty alternative"));
+        }
+        Ok(sequence)
+    }
+
+    /// `item ('-' item)?`
+    fn exclusion(&mut self) -> Result<Item, Error> {
+        let start = self.pos;
+        let item = self.item()?;
+        let before = self.pos;
+        self.skip_whitespace();
+        if self.peek() != Some('-') {
+            self.pos = before;
+            return Ok(item);
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        let excluded = self.item()?;
+        Ok(Item {
+            expr: Expr::Exclusion(Box::new(item), Box::new(excluded)),
+            text: self.text(start),
+        })
+    }
+
+    /// `primary ('?' | '*' | '+')?`
+    fn item(&mut self) -> Result<Item, Error> {
+        let start = self.pos;
+        let primary = Item {
+            expr: self.primary()?,
+            text: self.text(start),
+        };
+        let expr = match self.peek() {
+            Some('?') => Expr::Optional(Box::new(primary)),
+            Some('*') => Expr::ZeroOrMore(Box::new(primary)),
+            Some('+') => Expr::OneOrMore(Box::new(primary)),
+            _ => return Ok(primary),
+        };
+        self.pos += 1;
+        Ok(Item {
+            expr,
+            text: self.text(start),
+        })
+    }
+
+    fn text(&self, start: usize) -> String {
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let alternation = self.alternation()?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected `)`"));
+                }
+                self.pos += 1;
+                Ok(Expr::Group(alternation))
+            }
+            Some(quote @ '"') | Some(quote @ '\'') => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != quote) {
+                    self.pos += 1;
+                }
+                if self.peek().is_none() {
+                    return Err(self.error(&format!("missing closing `{}`", quote)));
+                }
+                self.pos += 1;
+                Ok(Expr::Text(self.chars[start..self.pos - 1].iter().collect()))
+            }
+            Some('#') => Ok(Expr::Char(self.hex_char()?)),
+            Some('[') => self.class(),
+            Some(_) => match self.name() {
+                Some(name) => Ok(Expr::Rule(name)),
+                None => Err(self.error(&format!("unexpected `{}`", self.peek().unwrap()))),
+            },
+            None => Err(self.error("expected an expression")),
+        }
+    }
+
+    /// `#xN`
+    fn hex_char(&mut self) -> Result<char, Error> {
+        if !self.at("#x") {
+            return Err(self.error("expected `#x`"));
+        }
+        self.pos += 2;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("expected a char value after `#x`"))
+    }
+
+    /// `[...]` or `[^...]`, made of chars, `#xN` and ranges of either.
+    fn class(&mut self) -> Result<Expr, Error> {
+        self.pos += 1;
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = vec![];
+        while self.peek() != Some(']') {
+            let from = self.class_char()?;
+            let to = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                self.class_char()?
+            } else {
+                from
+            };
+            if to < from {
+                return Err(self.error("range ends before it starts"));
+            }
+            ranges.push((from, to));
+        }
+        self.pos += 1;
+        Ok(Expr::Class(negated, ranges))
+    }
+
+    fn class_char(&mut self) -> Result<char, Error> {
+        match self.peek() {
+            Some('#') if self.at("#x") => self.hex_char(),
+            Some(c) => {
+                self.pos += 1;
+                Ok(c)
+            }
+            None => Err(self.error("missing closing `]`")),
+        }
+    }
+}
+
+/// The grammar being built for an alphabet, and the exclusions it has.
+struct Lowering {
+    rules: Vec<(String, Alternatives)>,
+    alphabet: Vec<char>,
+    /// The helper for each `A - B` and `B`.
+    exclusions: Vec<(Term, Term)>,
+}
+
+impl Lower for Lowering {
+    type Item = Item;
+
+    fn rules(&mut self) -> &mut Vec<(String, Alternatives)> {
+        &mut self.rules
+    }
+
+    fn item(&mut self, item: &Item) -> Result<Alternatives, Error> {
+        Ok(match &item.expr {
+            Expr::Rule(name) => vec![vec![Term::Nonterminal(name.to_string())]],
+            Expr::Text(s) => vec![s.chars().map(|c| Term::Terminal(c.to_string())).collect()],
+            Expr::Char(c) => vec![vec![Term::Terminal(c.to_string())]],
+            Expr::Class(negated, ranges) => {
+                let alternatives = self
+                    .alphabet
+                    .iter()
+                    .filter(|&&c| {
+                        ranges.iter().any(|(from, to)| *from <= c && c <= *to) != *negated
+                    })
+                    .map(|c| vec![Term::Terminal(c.to_string())])
+                    .collect();
+                vec![vec![self.helper(item.text.clone(), alternatives)]]
+            }
+            Expr::Group(alternation) if alternation.len() == 1 => self.alternation(alternation)?,
+            Expr::Group(alternation) => {
+                let alternatives = self.alternation(alternation)?;
+                vec![vec![self.helper(item.text.clone(), alternatives)]]
+            }
+            Expr::Optional(inner) => {
+                let mut alternatives = self.item(inner)?;
+                alternatives.push(vec![]);
+                alternatives
+            }
+            Expr::ZeroOrMore(inner) => vec![vec![self.one_or_more(inner)?], vec![]],
+            Expr::OneOrMore(inner) => vec![vec![self.one_or_more(inner)?]],
+            Expr::Exclusion(a, b) => {
+                let alternatives = self.item(a)?;
+                let helper = self.helper(item.text.clone(), alternatives);
+                let excluded = self.term(b)?;
+                self.exclusions.push((helper.clone(), excluded));
+                vec![vec![helper]]
+            }
+        })
+    }
+}
+
+impl Lowering {
+    /// The helper `A+` for `A*` and `A+`.
+    fn one_or_more(&mut self, item: &Item) -> Result<Term, Error> {
+        let term = self.term(item)?;
+        let name = format!("{}+", item.text);
+        let more = vec![term.clone(), Term::Nonterminal(name.clone())];
+        Ok(self.helper(name, vec![vec![term], more]))
+    }
+
+    /// `item` as a single term, a helper named after it when it isn't one.
+    fn term(&mut self, item: &Item) -> Result<Term, Error> {
+        let mut alternatives = self.item(item)?;
+        if alternatives.len() == 1 && alternatives[0].len() == 1 {
+            return Ok(alternatives.remove(0).remove(0));
+        }
+        Ok(self.helper(item.text.clone(), alternatives))
+    }
+}
+
+/// A grammar for one alphabet along with the exclusions to enforce while
+/// parsing with it.
+struct Lowered {
+    productions: Vec<Production>,
+    exclusions: Vec<(Term, Term)>,
+    /// Whether each excluded `B` matches a span's text, so a span `B` is
+    /// parsed against once however many `A - B` states complete over it.
+    matched: RefCell<HashMap<(Term, String), bool>>,
+}
+
+impl Lowered {
+    /// The grammar with the productions of `start` first.
+    fn grammar_from(&self, start: &Term) -> Grammar {
+        let (first, rest): (Vec<&Production>, Vec<&Production>) =
+            self.productions.iter().partition(|p| p.lhs == *start);
+        Grammar::from_parts(first.into_iter().chain(rest).cloned().collect())
+    }
+
+    /// A parser for `input` from `start` with a predicate on every
+    /// alternative of an `A - B` helper dropping the spans `B` matches.
+    fn parser(self: &Rc<Self>, start: &Term, input: &str) -> Result<EarleyParser, Error> {
+        let mut parser = EarleyParser::from_grammar(self.grammar_from(start), input);
+        for (helper, excluded) in &self.exclusions {
+            for production in self.productions.iter().filter(|p| p.lhs == *helper) {
+                let lowered = Rc::clone(self);
+                let excluded = excluded.clone();
+                parser = parser.with_production_predicate(production, move |tokens| {
+                    !lowered.matches(&excluded, &tokens.concat())
+                })?;
+            }
+        }
+        Ok(parser)
+    }
+
+    fn matches(self: &Rc<Self>, term: &Term, text: &str) -> bool {
+        match term {
+            Term::Terminal(s) => s == text,
+            Term::Nonterminal(_) => {
+                if !self.productions.iter().any(|p| p.lhs == *term) {
+                    return false;
+                }
+                let key = (term.clone(), text.to_string());
+                if let Some(&matched) = self.matched.borrow().get(&key) {
+                    return matched;
+                }
+                let matched = matches!(
+                    self.parser(term, text)
+                        .and_then(|parser| parser.earley_parse(None)),
+                    Ok(EarleyOutcome::Accepted(_))
+                );
+                self.matched.borrow_mut().insert(key, matched);
+                matched
+            }
+        }
+    }
+}
@@ -7,6 +7,14 @@ pub enum Error {
     BnfError(String),
     GrammarError(String),
     ParseForestError(String),
+    InvalidTree(String),
+    ActionError(String),
+    QueryError(String),
+    RewriteError(String),
+    AbnfError(String),
+    EbnfError(String),
+    ModuleError(String),
+    ParamError(String),
     // InputRejected(String),
 }
 
@@ -16,6 +24,14 @@ impl fmt::Display for Error {
             Error::BnfError(ref s) => write!(f, "{}", s),
             Error::GrammarError(ref s) => write!(f, "{}", s),
             Error::ParseForestError(ref s) => write!(f, "{}", s),
+            Error::InvalidTree(ref s) => write!(f, "{}", s),
+            Error::ActionError(ref s) => write!(f, "{}", s),
+            Error::QueryError(ref s) => write!(f, "{}", s),
+            Error::RewriteError(ref s) => write!(f, "{}", s),
+            Error::AbnfError(ref s) => write!(f, "{}", s),
+            Error::EbnfError(ref s) => write!(f, "{}", s),
+            Error::ModuleError(ref s) => write!(f, "{}", s),
+            Error::ParamError(ref s) => write!(f, "{}", s),
             // Error::InputRejected(ref s) => write!(f, "{}", s),
         }
     }
@@ -27,7 +43,7 @@ impl error::Error for Error {
     }
 }
 
-impl<'a> From<bnf::Error> for Error {
+impl From<bnf::Error> for Error {
     fn from(err: bnf::Error) -> Self {
         Error::BnfError(format!("{:?}", err))
     }
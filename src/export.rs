@@ -0,0 +1,469 @@
+use crate::error::Error;
+use bnf::{Grammar, Term};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The alternatives of every nonterminal, in the order the nonterminals
+/// are first defined, with the productions sharing a left hand side merged.
+pub(crate) fn rules(grammar: &Grammar) -> Vec<(String, Vec<Vec<Term>>)> {
+    let mut rules: Vec<(String, Vec<Vec<Term>>)> = vec![];
+    for production in grammar.productions_iter() {
+        let name = match &production.lhs {
+            Term::Nonterminal(name) | Term::Terminal(name) => name.to_string(),
+        };
+        let i = match rules.iter().position(|(n, _)| *n == name) {
+            Some(i) => i,
+            None => {
+                rules.push((name, vec![]));
+                rules.len() - 1
+            }
+        };
+        for expr in production.rhs_iter() {
+            let terms: Vec<Term> = expr.terms_iter().cloned().collect();
+            if !rules[i].1.contains(&terms) {
+                rules[i].1.push(terms);
+            }
+        }
+    }
+    rules
+}
+
+/// Writes one line per nonterminal, `name` then `define` then its
+/// alternatives separated by `or`, with the names padded to line up.
+fn write_rules<N, T>(grammar: &Grammar, name: N, define: &str, or: &str, terminal: T) -> String
+where
+    N: Fn(&str) -> String,
+    T: Fn(&str) -> String,
+{
+    let rules = rules(grammar);
+    let width = rules.iter().map(|(n, _)| name(n).len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (lhs, alternatives) in &rules {
+        let alternatives: Vec<String> = alternatives
+            .iter()
+            .map(|terms| {
+                terms
+                    .iter()
+                    .map(|term| match term {
+                        Term::Nonterminal(n) => name(n),
+                        Term::Terminal(t) => terminal(t),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect();
+        writeln!(
+            out,
+            "{:width$} {} {}",
+            name(lhs),
+            define,
+            alternatives.join(or),
+            width = width
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Canonical BNF as the `bnf` crate reads it: one line per nonterminal
+/// holding every alternative, whichever productions they came from.
+pub fn to_bnf(grammar: &Grammar) -> String {
+    write_rules(
+        grammar,
+        |name| format!("<{}>", name),
+        "::=",
+        " | ",
+        |terminal| Term::Terminal(terminal.to_string()).to_string(),
+    )
+}
+
+/// W3C EBNF, as read by `Ebnf`. Terminals are spelled out char by char
+/// where they have to be, e.g. `'a' #x9`, which matches the grammar's
+/// meaning when it's parsed char by char. Names that aren't EBNF names are
+/// made into ones.
+pub fn to_ebnf(grammar: &Grammar) -> String {
+    let names = names(grammar, false, |name| {
+        let mut ident: String = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if !ident.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            ident.insert(0, '_');
+        }
+        ident
+    });
+    write_rules(
+        grammar,
+        |name| names[name].to_string(),
+        "::=",
+        " | ",
+        |terminal| {
+            if terminal.is_empty() {
+                return "''".to_string();
+            }
+            let printable = !terminal.chars().any(char::is_control);
+            if printable && !terminal.contains('\'') {
+                format!("'{}'", terminal)
+            } else if printable && !terminal.contains('"') {
+                format!("\"{}\"", terminal)
+            } else {
+                terminal
+                    .chars()
+                    .map(|c| match c {
+                        '\'' => "\"'\"".to_string(),
+                        c if c.is_control() => format!("#x{:X}", c as u32),
+                        c => format!("'{}'", c),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            }
+        },
+    )
+}
+
+/// ABNF (RFC 5234), as read by `Abnf`. Terminals stay case-sensitive,
+/// written `%s"..."` (RFC 7405) or as `%x` values, and names that aren't
+/// ABNF rule names are made into ones.
+pub fn to_abnf(grammar: &Grammar) -> String {
+    let names = names(grammar, true, |name| {
+        let mut ident: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            ident.insert_str(0, "r-");
+        }
+        ident
+    });
+    write_rules(
+        grammar,
+        |name| names[name].to_string(),
+        "=",
+        " / ",
+        |terminal| {
+            let quotable = !terminal.is_empty()
+                && terminal
+                    .chars()
+                    .all(|c| (' '..='~').contains(&c) && c != '"');
+            if quotable && terminal.chars().any(|c| c.is_ascii_alphabetic()) {
+                format!("%s\"{}\"", terminal)
+            } else if quotable {
+                format!("\"{}\"", terminal)
+            } else if terminal.is_empty() {
+                "\"\"".to_string()
+            } else {
+                let values: Vec<String> = terminal
+                    .chars()
+                    .map(|c| format!("{:02X}", c as u32))
+                    .collect();
+                format!("%x{}", values.join("."))
+            }
+        },
+    )
+}
+
+/// The name every nonterminal gets in a notation, made by `ident` and
+/// numbered when two would be the same (ignoring case when the notation
+/// does).
+fn names<F: Fn(&str) -> String>(
+    grammar: &Grammar,
+    ignore_case: bool,
+    ident: F,
+) -> HashMap<String, String> {
+    let key = |name: &str| {
+        if ignore_case {
+            name.to_ascii_lowercase()
+        } else {
+            name.to_string()
+        }
+    };
+
+    let mut all: Vec<String> = vec![];
+    for production in grammar.productions_iter() {
+        let terms = std::iter::once(&production.lhs)
+            .chain(production.rhs_iter().flat_map(|expr| expr.terms_iter()));
+        for term in terms {
+            if let Term::Nonterminal(name) = term {
+                if !all.contains(name) {
+                    all.push(name.to_string());
+                }
+            }
+        }
+    }
+
+    // Names that are fine as they are keep them, ahead of the others,
+    // unless an earlier one is the same.
+    let (valid, invalid): (Vec<String>, Vec<String>) =
+        all.into_iter().partition(|name| ident(name) == *name);
+    let mut taken: Vec<String> = vec![];
+    let mut names: HashMap<String, String> = HashMap::new();
+    for name in valid.into_iter().chain(invalid) {
+        let base = ident(&name);
+        let mut candidate = base.clone();
+        let mut i = 1;
+        while taken.contains(&key(&candidate)) {
+            i += 1;
+            candidate = format!("{}{}", base, i);
+        }
+        taken.push(key(&candidate));
+        names.insert(name, candidate);
+    }
+    names
+}
+
+/// The grammar as JSON, through its serde representation.
+pub fn to_json(grammar: &Grammar) -> String {
+    serde_json::to_string_pretty(grammar).unwrap()
+}
+
+/// Reads back a grammar written by `to_json`.
+pub fn from_json(json: &str) -> Result<Grammar, Error> {
+    serde_json::from_str(json)
+        .map_err(|e| Error::GrammarError(format!("Invalid grammar JSON: {}", e)))
+}
+
+/// A railroad diagram element: a box for a symbol, a row of elements one
+/// after the other, or a stack of them to choose from.
+enum Railroad {
+    Terminal(String),
+    Nonterminal(String),
+    Sequence(Vec<Railroad>),
+    Choice(Vec<Railroad>),
+}
+
+const BOX_HEIGHT: usize = 24;
+const CHAR_WIDTH: usize = 8;
+const GAP: usize = 16;
+const RAIL: usize = 20;
+
+impl Railroad {
+    fn new(alternatives: &[Vec<Term>]) -> Railroad {
+        let mut rows: Vec<Railroad> = alternatives
+            .iter()
+            .map(|terms| {
+                Railroad::Sequence(
+                    terms
+                        .iter()
+                        .map(|term| match term {
+                            Term::Terminal(t) => Railroad::Terminal(t.to_string()),
+                            Term::Nonterminal(n) => Railroad::Nonterminal(n.to_string()),
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+        if rows.len() == 1 {
+            rows.remove(0)
+        } else {
+            Railroad::Choice(rows)
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Railroad::Terminal(text) | Railroad::Nonterminal(text) => {
+                text.chars().count() * CHAR_WIDTH + 20
+            }
+            Railroad::Sequence(items) => {
+                items.iter().map(Railroad::width).sum::<usize>()
+                    + GAP * items.len().saturating_sub(1)
+            }
+            Railroad::Choice(rows) => {
+                rows.iter().map(Railroad::width).max().unwrap_or(0) + 2 * RAIL
+            }
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Railroad::Terminal(_) | Railroad::Nonterminal(_) => BOX_HEIGHT,
+            Railroad::Sequence(items) => items.iter().map(Railroad::height).max().unwrap_or(0),
+            Railroad::Choice(rows) => {
+                rows.iter().map(Railroad::height).sum::<usize>()
+                    + GAP / 2 * rows.len().saturating_sub(1)
+            }
+        }
+    }
+
+    /// Distance from the top of the element to the line running through it.
+    fn baseline(&self) -> usize {
+        match self {
+            Railroad::Terminal(_) | Railroad::Nonterminal(_) => BOX_HEIGHT / 2,
+            Railroad::Sequence(items) => items.iter().map(Railroad::baseline).max().unwrap_or(0),
+            Railroad::Choice(rows) => rows.first().map(Railroad::baseline).unwrap_or(0),
+        }
+    }
+
+    /// Draws the element with its top left corner at `x, y`.
+    fn render(&self, x: usize, y: usize, out: &mut String) {
+        match self {
+            Railroad::Terminal(text) | Railroad::Nonterminal(text) => {
+                let (class, radius) = match self {
+                    Railroad::Terminal(_) => ("terminal", 10),
+                    _ => ("nonterminal", 0),
+                };
+                if let Railroad::Nonterminal(name) = self {
+                    write!(out, "<a href=\"#{}\">", escape(&anchor(name))).unwrap();
+                }
+                write!(
+                    out,
+                    "<g class=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>\
+                     <text x=\"{}\" y=\"{}\">{}</text></g>",
+                    class,
+                    x,
+                    y,
+                    self.width(),
+                    BOX_HEIGHT,
+                    radius,
+                    x + self.width() / 2,
+                    y + BOX_HEIGHT / 2 + 4,
+                    escape(text)
+                )
+                .unwrap();
+                if let Railroad::Nonterminal(_) = self {
+                    out.push_str("</a>");
+                }
+            }
+            Railroad::Sequence(items) => {
+                let line = y + self.baseline();
+                let mut cx = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        line_to(out, (cx, line), (cx + GAP, line));
+                        cx += GAP;
+                    }
+                    item.render(cx, line - item.baseline(), out);
+                    cx += item.width();
+                }
+            }
+            Railroad::Choice(rows) => {
+                let line = y + self.baseline();
+                let right = x + self.width();
+                let mut top = y;
+                for row in rows {
+                    let row_line = top + row.baseline();
+                    path(
+                        out,
+                        &[
+                            (x, line),
+                            (x + RAIL / 2, line),
+                            (x + RAIL / 2, row_line),
+                            (x + RAIL, row_line),
+                        ],
+                    );
+                    row.render(x + RAIL, top, out);
+                    path(
+                        out,
+                        &[
+                            (x + RAIL + row.width(), row_line),
+                            (right - RAIL / 2, row_line),
+                            (right - RAIL / 2, line),
+                            (right, line),
+                        ],
+                    );
+                    top += row.height() + GAP / 2;
+                }
+            }
+        }
+    }
+}
+
+fn line_to(out: &mut String, from: (usize, usize), to: (usize, usize)) {
+    path(out, &[from, to]);
+}
+
+fn path(out: &mut String, points: &[(usize, usize)]) {
+    let points: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+    write!(out, "<polyline points=\"{}\"/>", points.join(" ")).unwrap();
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// The HTML id of a nonterminal's diagram.
+fn anchor(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+const STYLE: &str = "polyline { fill: none; stroke: #333; stroke-width: 2 } \
+rect { stroke: #333; stroke-width: 2 } \
+.terminal rect { fill: #fffbe0 } .nonterminal rect { fill: #e6f0ff } \
+text { font: 14px monospace; text-anchor: middle }";
+
+/// A railroad diagram of the alternatives of `<nonterminal>`, if it has
+/// any, as an SVG document. Nonterminal boxes link to `#name`, the ids
+/// `railroad_html` gives the diagrams.
+pub fn railroad_svg(grammar: &Grammar, nonterminal: &str) -> Option<String> {
+    let (_, alternatives) = rules(grammar).into_iter().find(|(n, _)| n == nonterminal)?;
+    let diagram = Railroad::new(&alternatives);
+
+    // Rails lead in and out of the diagram from a dot on either side.
+    let (width, height) = (diagram.width() + 4 * RAIL, diagram.height() + 2 * RAIL);
+    let line = RAIL + diagram.baseline();
+    let mut out = String::new();
+    write!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {} {}\"><style>{}</style>",
+        width, height, width, height, STYLE
+    )
+    .unwrap();
+    write!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"4\"/>", RAIL / 2, line).unwrap();
+    line_to(&mut out, (RAIL / 2, line), (2 * RAIL, line));
+    diagram.render(2 * RAIL, RAIL, &mut out);
+    line_to(
+        &mut out,
+        (2 * RAIL + diagram.width(), line),
+        (width - RAIL / 2, line),
+    );
+    write!(
+        out,
+        "<circle cx=\"{}\" cy=\"{}\" r=\"4\"/>",
+        width - RAIL / 2,
+        line
+    )
+    .unwrap();
+    out.push_str("</svg>");
+    Some(out)
+}
+
+/// An HTML page with a railroad diagram per nonterminal, each under a
+/// heading with its name, in the order they're defined.
+pub fn railroad_html(grammar: &Grammar) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Grammar</title>\n</head>\n<body>\n",
+    );
+    for (name, _) in rules(grammar) {
+        writeln!(
+            out,
+            "<h2 id=\"{}\">{}</h2>",
+            escape(&anchor(&name)),
+            escape(&name)
+        )
+        .unwrap();
+        if let Some(svg) = railroad_svg(grammar, &name) {
+            out.push_str(&svg);
+            out.push('\n');
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
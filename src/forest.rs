@@ -0,0 +1,433 @@
+use crate::error::Error;
+use crate::istate::FlippedIState;
+use crate::layout::Layout;
+use crate::outcome::EarleyAccepted;
+use crate::prod::EarleyProd;
+use crate::shape::Shape;
+use crate::tree::{Branch, Tree};
+use bnf::Term;
+use linked_hash_set::LinkedHashSet;
+use std::collections::HashMap;
+
+/// A packed parse forest built from the completed states of an
+/// `EarleyAccepted` chart. Every `ForestNode` is a completed production
+/// spanning `input[start..end]` and is shared by all the derivations that
+/// use it; each of its `families` is one way of splitting that span across
+/// the production's right hand side.
+///
+/// A grammar with a cycle such as `<A> ::= <B>` and `<B> ::= <A> | 'x'`
+/// derives some spans in infinitely many ways. Those derivations show up here
+/// as a node that's reachable from one of its own families, and every node
+/// on such a loop is flagged `cyclic` so the forest stays finite and callers
+/// can tell when enumerating its trees would never end.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Forest {
+    pub nodes: Vec<ForestNode>,
+    pub roots: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ForestNode {
+    pub prod: EarleyProd,
+    pub start: usize,
+    pub end: usize,
+    pub families: Vec<Vec<ForestChild>>,
+    pub cyclic: bool,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ForestChild {
+    Nonterminal(usize),
+    Terminal(usize, String),
+}
+
+impl Forest {
+    pub fn new(accepted: &EarleyAccepted) -> Forest {
+        let mut builder = ForestBuilder {
+            input: &accepted.input,
+            chart: accepted.flip_completed(),
+            layout: accepted.layout.as_ref(),
+            roots: accepted.accepted_states.iter().map(|s| &s.prod).collect(),
+            ids: HashMap::new(),
+            nodes: vec![],
+        };
+
+        let end = accepted.input.len();
+        let roots = accepted
+            .accepted_states
+            .iter()
+            .map(|state| builder.node(&state.prod, 0, end))
+            .collect();
+
+        let mut forest = Forest {
+            nodes: builder.nodes,
+            roots,
+        };
+        forest.mark_cycles();
+        forest
+    }
+
+    pub fn get(&self, id: usize) -> Option<&ForestNode> {
+        self.nodes.get(id)
+    }
+
+    /// Whether any tree of the forest runs through a cycle.
+    pub fn is_cyclic(&self) -> bool {
+        self.postorder().iter().any(|v| self.nodes[*v].cyclic)
+    }
+
+    /// Every tree in the forest, or a `ParseForestError` if the grammar's
+    /// cycles give the input infinitely many of them.
+    pub fn trees(&self) -> Result<Vec<Tree>, Error> {
+        self.shaped_trees(&Shape::default())
+    }
+
+    /// Every tree in the forest, shaped by `shape` as it's built.
+    pub fn shaped_trees(&self, shape: &Shape) -> Result<Vec<Tree>, Error> {
+        if let Some(v) = self.postorder().iter().find(|v| self.nodes[**v].cyclic) {
+            let node = &self.nodes[*v];
+            return Err(Error::ParseForestError(format!(
+                "Infinitely many parse trees, {} derives itself over input {}..{}",
+                node.prod, node.start, node.end
+            )));
+        }
+
+        let mut trees: Vec<Option<Vec<Tree>>> = vec![None; self.nodes.len()];
+        for v in self.postorder() {
+            let node = &self.nodes[v];
+            let mut node_trees = vec![];
+            for family in &node.families {
+                let mut partials: Vec<Vec<Branch>> = vec![vec![]];
+                for child in family {
+                    // Each option is the run of branches the child turns
+                    // into, which is empty or several for a shaped child.
+                    let options: Vec<Vec<Branch>> = match child {
+                        ForestChild::Nonterminal(c) => trees[*c]
+                            .iter()
+                            .flatten()
+                            .map(|tree| shape_child(tree, shape))
+                            .collect(),
+                        ForestChild::Terminal(_, s) if shape.is_dropped(s) => vec![vec![]],
+                        ForestChild::Terminal(_, s) => vec![vec![Branch::Terminal(s.to_string())]],
+                    };
+
+                    partials = partials
+                        .iter()
+                        .flat_map(|partial| {
+                            options.iter().map(move |option| {
+                                let mut branches = partial.clone();
+                                branches.extend(option.iter().cloned());
+                                branches
+                            })
+                        })
+                        .collect();
+                }
+
+                node_trees.extend(
+                    partials
+                        .into_iter()
+                        .map(|branches| Tree::from_branches(node.prod.lhs.clone(), branches)),
+                );
+            }
+            trees[v] = Some(node_trees);
+        }
+
+        Ok(self
+            .roots
+            .iter()
+            .flat_map(|r| trees[*r].iter().flatten().cloned())
+            .collect())
+    }
+
+    /// Ids of the nodes reachable from the roots, each one after all of the
+    /// nodes below it (apart from the nodes of a cycle, which can't be).
+    pub fn postorder(&self) -> Vec<usize> {
+        let mut order = vec![];
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack: Vec<(usize, bool)> = self.roots.iter().rev().map(|r| (*r, false)).collect();
+
+        while let Some((v, expanded)) = stack.pop() {
+            if expanded {
+                order.push(v);
+                continue;
+            }
+            if visited[v] {
+                continue;
+            }
+            visited[v] = true;
+
+            stack.push((v, true));
+            for family in self.nodes[v].families.iter().rev() {
+                for child in family.iter().rev() {
+                    if let ForestChild::Nonterminal(c) = child {
+                        if !visited[*c] {
+                            stack.push((*c, false));
+                        }
+                    }
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Flags every node that lies on a cycle, i.e. every node of a strongly
+    /// connected component with more than one node or with a family that
+    /// refers straight back to itself (Tarjan's algorithm).
+    fn mark_cycles(&mut self) {
+        let mut tarjan = Tarjan {
+            forest: self,
+            index: 0,
+            indexes: vec![None; self.nodes.len()],
+            lowlinks: vec![0; self.nodes.len()],
+            on_stack: vec![false; self.nodes.len()],
+            stack: vec![],
+            cyclic: vec![false; self.nodes.len()],
+        };
+
+        for v in 0..self.nodes.len() {
+            if tarjan.indexes[v].is_none() {
+                tarjan.connect(v);
+            }
+        }
+
+        let cyclic = tarjan.cyclic;
+        for (node, cyclic) in self.nodes.iter_mut().zip(cyclic) {
+            node.cyclic = cyclic;
+        }
+    }
+
+    fn children(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[v]
+            .families
+            .iter()
+            .flatten()
+            .filter_map(|child| match child {
+                ForestChild::Nonterminal(c) => Some(*c),
+                ForestChild::Terminal(_, _) => None,
+            })
+    }
+}
+
+/// The branches a child tree turns into in its parent: its only nonterminal
+/// branch when collapsing units, its own branches when it's transparent,
+/// and itself otherwise.
+fn shape_child(tree: &Tree, shape: &Shape) -> Vec<Branch> {
+    let mut tree = tree;
+    if shape.is_collapsing_units() {
+        while let [Branch::Nonterminal(only)] = tree.branches.as_slice() {
+            tree = only;
+        }
+    }
+
+    if shape.is_transparent(tree.name()) {
+        tree.branches.clone()
+    } else {
+        vec![Branch::Nonterminal(tree.clone())]
+    }
+}
+
+struct Tarjan<'a> {
+    forest: &'a Forest,
+    index: usize,
+    indexes: Vec<Option<usize>>,
+    lowlinks: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    cyclic: Vec<bool>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn connect(&mut self, v: usize) {
+        self.indexes[v] = Some(self.index);
+        self.lowlinks[v] = self.index;
+        self.index += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+
+        let children: Vec<usize> = self.forest.children(v).collect();
+        for c in &children {
+            match self.indexes[*c] {
+                None => {
+                    self.connect(*c);
+                    self.lowlinks[v] = self.lowlinks[v].min(self.lowlinks[*c]);
+                }
+                Some(index) if self.on_stack[*c] => {
+                    self.lowlinks[v] = self.lowlinks[v].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.lowlinks[v]) == self.indexes[v] {
+            let mut component = vec![];
+            while let Some(w) = self.stack.pop() {
+                self.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+
+            if component.len() > 1 || children.contains(&v) {
+                for w in component {
+                    self.cyclic[w] = true;
+                }
+            }
+        }
+    }
+}
+
+/// The search for the families of one node: its production, where it ends,
+/// whether it's a root, the children of the split found so far, and the
+/// splits found.
+struct Split<'p> {
+    prod: &'p EarleyProd,
+    end: usize,
+    root: bool,
+    partial: Vec<ForestChild>,
+    families: Vec<Vec<ForestChild>>,
+}
+
+struct ForestBuilder<'a> {
+    input: &'a [String],
+    chart: Vec<LinkedHashSet<FlippedIState>>,
+    layout: Option<&'a Layout>,
+    roots: Vec<&'a EarleyProd>,
+    ids: HashMap<(EarleyProd, usize, usize), usize>,
+    nodes: Vec<ForestNode>,
+}
+
+impl<'a> ForestBuilder<'a> {
+    /// Returns the id of the node for `prod` over `input[start..end]`,
+    /// building it (and everything below it) the first time it's seen.
+    /// The id is reserved before the families are searched so a production
+    /// that derives itself over the same span refers back to its own node.
+    fn node(&mut self, prod: &EarleyProd, start: usize, end: usize) -> usize {
+        let key = (prod.clone(), start, end);
+        if let Some(id) = self.ids.get(&key) {
+            return *id;
+        }
+
+        let id = self.nodes.len();
+        self.ids.insert(key, id);
+        self.nodes.push(ForestNode {
+            prod: prod.clone(),
+            start,
+            end,
+            families: vec![],
+            cyclic: false,
+        });
+
+        let root = start == 0 && end == self.input.len() && self.roots.contains(&prod);
+        let mut split = Split {
+            prod,
+            end,
+            root,
+            partial: vec![],
+            families: vec![],
+        };
+        self.families(&mut split, 0, start);
+        self.nodes[id].families = split.families;
+
+        id
+    }
+
+    /// Every term of a right hand side consumes at least one input symbol, so
+    /// a split is only explored while enough input is left for the terms that
+    /// remain. With a `Layout`, the split may skip over layout wherever the
+    /// parser let it through: between the terms of a production that isn't
+    /// lexical, and before and after a root.
+    ///
+    /// Splits the rest of the span, from `input[i]`, across the terms of the
+    /// production from `t` on.
+    fn families(&mut self, split: &mut Split, t: usize, i: usize) {
+        let (prod, end) = (split.prod, split.end);
+        let rhs = &prod.rhs[t..];
+        let gap = match self.layout {
+            Some(layout) => {
+                (t > 0 && !rhs.is_empty() && layout.allows_between(prod))
+                    || (split.root && (t == 0 || rhs.is_empty()))
+            }
+            None => false,
+        };
+        let starts = if gap {
+            self.after_layout(i, end)
+        } else {
+            vec![i]
+        };
+
+        let term = match rhs.first() {
+            Some(term) => term,
+            None => {
+                if starts.contains(&end) {
+                    split.families.push(split.partial.clone());
+                }
+                return;
+            }
+        };
+
+        for i in starts {
+            if end < i + rhs.len() {
+                continue;
+            }
+
+            match term {
+                Term::Terminal(symbol) => {
+                    if self.input.get(i) == Some(symbol) {
+                        split
+                            .partial
+                            .push(ForestChild::Terminal(i, symbol.to_string()));
+                        self.families(split, t + 1, i + 1);
+                        split.partial.pop();
+                    }
+                }
+                Term::Nonterminal(_) => {
+                    let limit = end - (rhs.len() - 1);
+                    let last = rhs.len() == 1 && !split.root;
+                    let candidates: Vec<FlippedIState> = match self.chart.get(i) {
+                        Some(state_set) => state_set
+                            .iter()
+                            .filter(|s| &s.prod.lhs == term && s.end <= limit)
+                            .filter(|s| !last || s.end == end)
+                            .cloned()
+                            .collect(),
+                        None => vec![],
+                    };
+
+                    for candidate in candidates {
+                        let child = self.node(&candidate.prod, i, candidate.end);
+                        split.partial.push(ForestChild::Nonterminal(child));
+                        self.families(split, t + 1, candidate.end);
+                        split.partial.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    /// The positions reachable from `i` over any number of layout
+    /// derivations without passing `end`, `i` included.
+    fn after_layout(&self, i: usize, end: usize) -> Vec<usize> {
+        let layout = match self.layout {
+            Some(layout) => layout.nonterminal(),
+            None => return vec![i],
+        };
+        let mut positions = vec![i];
+        let mut k = 0;
+        while k < positions.len() {
+            if let Some(state_set) = self.chart.get(positions[k]) {
+                for state in state_set {
+                    if &state.prod.lhs == layout
+                        && state.end <= end
+                        && !positions.contains(&state.end)
+                    {
+                        positions.push(state.end);
+                    }
+                }
+            }
+            k += 1;
+        }
+        positions
+    }
+}
@@ -0,0 +1,140 @@
+use crate::error::Error;
+use crate::grammar::{StaticProduction, StaticTerm};
+use crate::tree::{Branch, Tree};
+use bnf::{Expression, Term};
+use std::str::FromStr;
+
+pub use earley_macros::FromTree;
+
+/// Conversion from a parse tree into a typed value, usually derived.
+///
+/// `#[derive(FromTree)]` takes an `#[earley(rule = "...")]` attribute on a
+/// struct, or on every variant of an enum, naming the production (or
+/// productions, as alternatives) a tree must have to convert into it. The
+/// fields are then filled from the tree's branches, left to right: a field
+/// takes the next nonterminal branch through its own `FromTree`, or, when
+/// marked `#[earley(terminal)]`, the next terminal leaf through `FromStr`.
+///
+/// ```ignore
+/// #[derive(FromTree)]
+/// enum Sum {
+///     #[earley(rule = "<S> ::= <S> '+' <M>")]
+///     Add(Box<Sum>, Product),
+///     #[earley(rule = "<S> ::= <M>")]
+///     Product(Product),
+/// }
+/// ```
+pub trait FromTree: Sized {
+    fn from_tree(tree: &Tree) -> Result<Self, Error>;
+}
+
+impl FromTree for Tree {
+    fn from_tree(tree: &Tree) -> Result<Self, Error> {
+        Ok(tree.clone())
+    }
+}
+
+/// A nonterminal read as a string is the text of its leaves.
+impl FromTree for String {
+    fn from_tree(tree: &Tree) -> Result<Self, Error> {
+        Ok(tree.yield_tokens().join(""))
+    }
+}
+
+impl<T: FromTree> FromTree for Box<T> {
+    fn from_tree(tree: &Tree) -> Result<Self, Error> {
+        T::from_tree(tree).map(Box::new)
+    }
+}
+
+/// Whether the tree's production is one of the alternatives of `rule`,
+/// which the derive lays out at compile time.
+pub fn matches(tree: &Tree, rule: &StaticProduction) -> bool {
+    let same = |expr: &Expression, terms: &[StaticTerm]| {
+        expr.terms_iter().count() == terms.len()
+            && expr
+                .terms_iter()
+                .zip(terms)
+                .all(|(term, static_term)| match (term, static_term) {
+                    (Term::Terminal(a), StaticTerm::Terminal(b)) => a == b,
+                    (Term::Nonterminal(a), StaticTerm::Nonterminal(b)) => a == b,
+                    _ => false,
+                })
+    };
+
+    matches!(&tree.production.lhs, Term::Nonterminal(lhs) if lhs == rule.lhs)
+        && tree
+            .production
+            .rhs_iter()
+            .all(|expr| rule.alternatives.iter().any(|terms| same(expr, terms)))
+}
+
+/// The error for a tree that matches none of a type's rules.
+pub fn mismatch(tree: &Tree, type_name: &str, rules: &[&str]) -> Error {
+    Error::InvalidTree(format!(
+        "Can't build {} from {} over {:?}, expected one of: {}",
+        type_name,
+        tree.production,
+        tree.yield_tokens().join(" "),
+        rules.join(", ")
+    ))
+}
+
+/// Hands out a tree's branches to the fields of a derived `FromTree` in
+/// order.
+pub struct Branches<'a> {
+    tree: &'a Tree,
+    next: usize,
+    type_name: &'static str,
+}
+
+impl<'a> Branches<'a> {
+    pub fn new(tree: &'a Tree, type_name: &'static str) -> Branches<'a> {
+        Branches {
+            tree,
+            next: 0,
+            type_name,
+        }
+    }
+
+    /// The next nonterminal branch converted into `T`.
+    pub fn nonterminal<T: FromTree>(&mut self, field: &str) -> Result<T, Error> {
+        while let Some(branch) = self.tree.branches.get(self.next) {
+            self.next += 1;
+            if let Branch::Nonterminal(tree) = branch {
+                return T::from_tree(tree).map_err(|e| {
+                    Error::InvalidTree(format!("{}.{}: {}", self.type_name, field, e))
+                });
+            }
+        }
+
+        Err(self.missing(field, "nonterminal"))
+    }
+
+    /// The next terminal leaf parsed into `T`.
+    pub fn terminal<T: FromStr>(&mut self, field: &str) -> Result<T, Error> {
+        while let Some(branch) = self.tree.branches.get(self.next) {
+            self.next += 1;
+            if let Branch::Terminal(s) = branch {
+                return s.parse().map_err(|_| {
+                    Error::InvalidTree(format!(
+                        "{}.{}: can't read terminal {:?} as {}",
+                        self.type_name,
+                        field,
+                        s,
+                        std::any::type_name::<T>()
+                    ))
+                });
+            }
+        }
+
+        Err(self.missing(field, "terminal"))
+    }
+
+    fn missing(&self, field: &str, kind: &str) -> Error {
+        Error::InvalidTree(format!(
+            "{}.{}: no {} branch left in {}",
+            self.type_name, field, kind, self.tree.production
+        ))
+    }
+}
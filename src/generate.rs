@@ -0,0 +1,271 @@
+use crate::error::Error;
+use crate::export::rules;
+use crate::sample::choose;
+use crate::tree::{Branch, Tree};
+use bnf::{Grammar, Production, Term};
+use rand::Rng;
+use std::collections::{BTreeSet, HashMap};
+
+/// The least length of something that derives nothing.
+const NEVER: usize = usize::MAX;
+
+/// Generates sentences of a grammar, with the trees that derive them:
+/// at random, within limits on a tree's depth and its sentence's length
+/// and with weights on the productions, or all of them up to a length.
+///
+/// Every terminal is one token of the sentence, so a sentence is fed back
+/// to the parser by joining its tokens with the `split_on` char, or with
+/// nothing for single char terminals.
+pub struct Generator {
+    rules: Vec<(String, Vec<Vec<Term>>)>,
+    weights: HashMap<(Term, Vec<Term>), f64>,
+    max_depth: usize,
+    max_length: usize,
+    // The least length of a tree for each nonterminal within each depth,
+    // counting only alternatives with a weight, up to the depth where it
+    // stops getting shorter.
+    shortest: Vec<HashMap<String, usize>>,
+}
+
+impl Generator {
+    pub fn new(grammar: &str) -> Result<Generator, Error> {
+        Ok(Generator::from_grammar(grammar.parse()?))
+    }
+
+    pub fn from_grammar(grammar: Grammar) -> Generator {
+        let mut generator = Generator {
+            rules: rules(&grammar),
+            weights: HashMap::new(),
+            max_depth: 32,
+            max_length: 64,
+            shortest: vec![],
+        };
+        generator.bound();
+        generator
+    }
+
+    /// Limits random trees to `max_depth` nonterminals from the root to a
+    /// leaf, 32 unless it's given.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Generator {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits random sentences to `max_length` tokens, 64 unless it's
+    /// given.
+    pub fn with_max_length(mut self, max_length: usize) -> Generator {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Weighs every alternative of `production`, given in BNF, e.g.
+    /// `"<S> ::= <S> '+' <M>"`. The alternatives of a nonterminal are
+    /// picked with probability proportional to their weights, one unless
+    /// they're given, and never with a weight of zero. Every alternative
+    /// has to be one of the grammar's, since a misspelt one would weigh
+    /// nothing.
+    pub fn with_weight(mut self, production: &str, weight: f64) -> Result<Generator, Error> {
+        let production: Production = production.parse()?;
+        for expr in production.rhs_iter() {
+            let terms: Vec<Term> = expr.terms_iter().cloned().collect();
+            let defined = self.rules.iter().any(|(name, alternatives)| {
+                production.lhs == Term::Nonterminal(name.to_string())
+                    && alternatives.contains(&terms)
+            });
+            if !defined {
+                return Err(Error::GrammarError(format!(
+                    "Weighted production {} ::= {} isn't in grammar",
+                    production.lhs, expr
+                )));
+            }
+        }
+        for expr in production.rhs_iter() {
+            let key = (production.lhs.clone(), expr.terms_iter().cloned().collect());
+            self.weights.insert(key, weight.max(0.0));
+        }
+        self.bound();
+        Ok(self)
+    }
+
+    fn weight(&self, name: &str, terms: &[Term]) -> f64 {
+        let key = (Term::Nonterminal(name.to_string()), terms.to_vec());
+        self.weights.get(&key).copied().unwrap_or(1.0)
+    }
+
+    /// The least length of a tree of `term` no deeper than `depth`.
+    fn least(&self, term: &Term, depth: usize) -> usize {
+        match term {
+            Term::Terminal(_) => 1,
+            Term::Nonterminal(name) => {
+                let within = &self.shortest[depth.min(self.shortest.len() - 1)];
+                within.get(name).copied().unwrap_or(NEVER)
+            }
+        }
+    }
+
+    /// The least length of the trees `terms` derive, none deeper than
+    /// `depth`.
+    fn length_of(&self, terms: &[Term], depth: usize) -> usize {
+        terms.iter().fold(0, |length: usize, term| {
+            length.saturating_add(self.least(term, depth))
+        })
+    }
+
+    /// Works out the least length of every nonterminal within each depth,
+    /// a depth at a time until it stops changing.
+    fn bound(&mut self) {
+        self.shortest = vec![HashMap::new()];
+        loop {
+            let depth = self.shortest.len() - 1;
+            let within: HashMap<String, usize> = self
+                .rules
+                .iter()
+                .map(|(name, alternatives)| {
+                    let length = alternatives
+                        .iter()
+                        .filter(|terms| self.weight(name, terms) > 0.0)
+                        .map(|terms| self.length_of(terms, depth))
+                        .min()
+                        .unwrap_or(NEVER);
+                    (name.to_string(), length)
+                })
+                .collect();
+            if self.shortest.last() == Some(&within) {
+                break;
+            }
+            self.shortest.push(within);
+        }
+    }
+
+    /// A random tree of the start symbol within the limits, or `None` when
+    /// the limits and weights leave none. Its sentence is
+    /// `tree.yield_tokens()`.
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Tree> {
+        let (start, _) = self.rules.first()?;
+        self.expand(rng, start, self.max_depth, self.max_length)
+            .map(|(tree, _)| tree)
+    }
+
+    /// A tree of `<name>` no deeper than `depth` with at most `budget`
+    /// tokens, and how many it has.
+    fn expand<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        name: &str,
+        depth: usize,
+        budget: usize,
+    ) -> Option<(Tree, usize)> {
+        let (_, alternatives) = self.rules.iter().find(|(n, _)| n == name)?;
+        let below = depth.checked_sub(1)?;
+        // An alternative whose children fit below within the budget always
+        // leads to a tree within the limits.
+        let weights: Vec<f64> = alternatives
+            .iter()
+            .map(|terms| {
+                if self.length_of(terms, below) <= budget {
+                    self.weight(name, terms)
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let terms = &alternatives[choose(rng, &weights)?];
+
+        // Each term may use what the terms after it don't need at least.
+        let mut rest = self.length_of(terms, below);
+        let mut used = 0;
+        let mut branches = vec![];
+        for term in terms {
+            rest -= self.least(term, below);
+            match term {
+                Term::Terminal(t) => {
+                    branches.push(Branch::Terminal(t.to_string()));
+                    used += 1;
+                }
+                Term::Nonterminal(n) => {
+                    let (tree, length) = self.expand(rng, n, below, budget - used - rest)?;
+                    branches.push(Branch::Nonterminal(tree));
+                    used += length;
+                }
+            }
+        }
+        Some((
+            Tree::from_branches(Term::Nonterminal(name.to_string()), branches),
+            used,
+        ))
+    }
+
+    /// Every sentence of the start symbol with at most `max_length` tokens,
+    /// shortest first and in order within a length. Alternatives with a
+    /// weight of zero are left out, the depth limit isn't.
+    pub fn enumerate(&self, max_length: usize) -> Vec<Vec<String>> {
+        // The sentences of each nonterminal by length, built up one length
+        // at a time since no alternative derives the empty string.
+        let mut table: Vec<HashMap<String, BTreeSet<Vec<String>>>> = vec![HashMap::new()];
+        for length in 1..=max_length {
+            table.push(HashMap::new());
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for (name, alternatives) in &self.rules {
+                    for terms in alternatives {
+                        if self.weight(name, terms) <= 0.0 || terms.len() > length {
+                            continue;
+                        }
+                        for sentence in sentences(&table, terms, length) {
+                            changed |= table[length]
+                                .entry(name.to_string())
+                                .or_default()
+                                .insert(sentence);
+                        }
+                    }
+                }
+            }
+        }
+
+        let start = match self.rules.first() {
+            Some((start, _)) => start,
+            None => return vec![],
+        };
+        table
+            .iter()
+            .filter_map(|sentences| sentences.get(start))
+            .flat_map(|sentences| sentences.iter().cloned())
+            .collect()
+    }
+}
+
+/// The sentences of `length` tokens `terms` derive, given those of every
+/// nonterminal up to that length so far.
+fn sentences(
+    table: &[HashMap<String, BTreeSet<Vec<String>>>],
+    terms: &[Term],
+    length: usize,
+) -> Vec<Vec<String>> {
+    let (first, rest) = match terms.split_first() {
+        Some(split) => split,
+        None if length == 0 => return vec![vec![]],
+        None => return vec![],
+    };
+    let mut out = vec![];
+    // Every term after the first takes a token at least.
+    for taken in 1..=length - rest.len() {
+        let heads: Vec<Vec<String>> = match first {
+            Term::Terminal(t) if taken == 1 => vec![vec![t.to_string()]],
+            Term::Terminal(_) => continue,
+            Term::Nonterminal(n) => match table[taken].get(n) {
+                Some(heads) => heads.iter().cloned().collect(),
+                None => continue,
+            },
+        };
+        let tails = sentences(table, rest, length - taken);
+        for head in &heads {
+            for tail in &tails {
+                let mut sentence = head.clone();
+                sentence.extend(tail.iter().cloned());
+                out.push(sentence);
+            }
+        }
+    }
+    out
+}
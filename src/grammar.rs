@@ -0,0 +1,63 @@
+use bnf::{Expression, Grammar, Production, Term};
+
+pub use earley_macros::grammar;
+
+/// A term of a `StaticGrammar`, with the same meaning as a `bnf::Term`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StaticTerm {
+    Terminal(&'static str),
+    Nonterminal(&'static str),
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StaticProduction {
+    pub lhs: &'static str,
+    pub alternatives: &'static [&'static [StaticTerm]],
+}
+
+/// A grammar checked and laid out at compile time by the `grammar!` macro,
+/// so the parser can be handed a `bnf::Grammar` without parsing any BNF at
+/// run time.
+///
+/// ```ignore
+/// static WIKI: StaticGrammar = grammar! {
+///     <P> ::= <S>
+///     <S> ::= <S> "+" <M> | <M>
+///     <M> ::= <M> "*" <T> | <T>
+///     <T> ::= "1" | "2" | "3" | "4"
+/// };
+/// ```
+///
+/// Terminals are Rust string (or char) literals, so a multi-char terminal
+/// has to be written with double quotes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StaticGrammar {
+    pub productions: &'static [StaticProduction],
+}
+
+impl StaticTerm {
+    pub fn to_term(self) -> Term {
+        match self {
+            StaticTerm::Terminal(s) => Term::Terminal(s.to_string()),
+            StaticTerm::Nonterminal(s) => Term::Nonterminal(s.to_string()),
+        }
+    }
+}
+
+impl StaticProduction {
+    pub fn to_production(&self) -> Production {
+        Production::from_parts(
+            Term::Nonterminal(self.lhs.to_string()),
+            self.alternatives
+                .iter()
+                .map(|terms| Expression::from_parts(terms.iter().map(|t| t.to_term()).collect()))
+                .collect(),
+        )
+    }
+}
+
+impl StaticGrammar {
+    pub fn to_grammar(&self) -> Grammar {
+        Grammar::from_parts(self.productions.iter().map(|p| p.to_production()).collect())
+    }
+}
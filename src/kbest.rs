@@ -0,0 +1,215 @@
+use crate::forest::{Forest, ForestChild};
+use crate::tree::{Branch, Tree};
+use bnf::Production;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// One derivation of a forest node: the family it was built from and, for
+/// every child of that family, the rank of the derivation used for it.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Derivation {
+    family: usize,
+    ranks: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Candidate {
+    score: f64,
+    derivation: Derivation,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Higher scores first, ties broken towards earlier families and lower
+/// ranks so the order is stable between runs.
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.derivation.family.cmp(&self.derivation.family))
+            .then_with(|| other.derivation.ranks.cmp(&self.derivation.ranks))
+    }
+}
+
+/// Lazy k-best extraction over a `Forest` ("Algorithm 3" in Huang & Chiang,
+/// Better k-best Parsing, 2005). Each node keeps the derivations found so far
+/// in score order along with a heap of candidates for the next one; the
+/// next best derivation of a node is only ever computed when a parent asks
+/// for it.
+///
+/// A tree's score is the sum of `score` over the productions of its nodes,
+/// which is what keeps the lazy search exact.
+pub struct KBest<'a, F>
+where
+    F: Fn(&Production) -> f64,
+{
+    forest: &'a Forest,
+    score: F,
+    productions: Vec<Production>,
+    found: Vec<Vec<(f64, Derivation)>>,
+    candidates: Vec<Option<BinaryHeap<Candidate>>>,
+    seen: Vec<HashSet<Derivation>>,
+}
+
+impl<'a, F> KBest<'a, F>
+where
+    F: Fn(&Production) -> f64,
+{
+    pub fn new(forest: &'a Forest, score: F) -> KBest<'a, F> {
+        let size = forest.nodes.len() + 1;
+        KBest {
+            forest,
+            score,
+            productions: forest
+                .nodes
+                .iter()
+                .map(|node| node.prod.to_production())
+                .collect(),
+            found: vec![vec![]; size],
+            candidates: vec![None; size],
+            seen: vec![HashSet::new(); size],
+        }
+    }
+
+    /// The top `k` trees of the forest, best first.
+    pub fn trees(&mut self, k: usize) -> Vec<(f64, Tree)> {
+        let root = self.forest.nodes.len();
+        let mut trees = vec![];
+        for rank in 0..k {
+            match self.kth(root, rank) {
+                Some(score) => {
+                    if let Some(Branch::Nonterminal(tree)) = self.branch(root, rank).pop() {
+                        trees.push((score, tree));
+                    }
+                }
+                None => break,
+            }
+        }
+        trees
+    }
+
+    /// The forest's roots hang off a virtual node one past the last real
+    /// node, each root its own single-child family.
+    fn families(&self, v: usize) -> Vec<Vec<ForestChild>> {
+        match self.forest.nodes.get(v) {
+            Some(node) => node.families.clone(),
+            None => self
+                .forest
+                .roots
+                .iter()
+                .map(|r| vec![ForestChild::Nonterminal(*r)])
+                .collect(),
+        }
+    }
+
+    fn node_score(&self, v: usize) -> f64 {
+        match self.productions.get(v) {
+            Some(production) => (self.score)(production),
+            None => 0.0,
+        }
+    }
+
+    /// The score of the `rank`th best derivation of `v`, computing it if
+    /// needed, or `None` when `v` has fewer derivations than that.
+    fn kth(&mut self, v: usize, rank: usize) -> Option<f64> {
+        if self.candidates[v].is_none() {
+            self.init(v);
+        }
+
+        while self.found[v].len() <= rank {
+            if let Some((_, last)) = self.found[v].last().cloned() {
+                self.push_successors(v, &last);
+            }
+
+            match self.candidates[v].as_mut().and_then(|heap| heap.pop()) {
+                Some(candidate) => self.found[v].push((candidate.score, candidate.derivation)),
+                None => break,
+            }
+        }
+
+        self.found[v].get(rank).map(|(score, _)| *score)
+    }
+
+    fn init(&mut self, v: usize) {
+        self.candidates[v] = Some(BinaryHeap::new());
+        for (family, children) in self.families(v).iter().enumerate() {
+            let derivation = Derivation {
+                family,
+                ranks: vec![0; children.len()],
+            };
+            self.push_candidate(v, derivation);
+        }
+    }
+
+    fn push_successors(&mut self, v: usize, last: &Derivation) {
+        for i in 0..last.ranks.len() {
+            let mut next = last.clone();
+            next.ranks[i] += 1;
+            self.push_candidate(v, next);
+        }
+    }
+
+    fn push_candidate(&mut self, v: usize, derivation: Derivation) {
+        if self.seen[v].contains(&derivation) {
+            return;
+        }
+
+        if let Some(score) = self.score_of(v, &derivation) {
+            self.seen[v].insert(derivation.clone());
+            if let Some(heap) = self.candidates[v].as_mut() {
+                heap.push(Candidate { score, derivation });
+            }
+        }
+    }
+
+    fn score_of(&mut self, v: usize, derivation: &Derivation) -> Option<f64> {
+        let children = self.families(v)[derivation.family].clone();
+        let mut score = self.node_score(v);
+        for (child, rank) in children.iter().zip(derivation.ranks.iter()) {
+            match child {
+                ForestChild::Nonterminal(c) => score += self.kth(*c, *rank)?,
+                ForestChild::Terminal(_, _) => {
+                    if *rank > 0 {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(score)
+    }
+
+    fn branch(&self, v: usize, rank: usize) -> Vec<Branch> {
+        let derivation = &self.found[v][rank].1;
+        let children = &self.families(v)[derivation.family];
+
+        let mut branches = vec![];
+        for (child, rank) in children.iter().zip(derivation.ranks.iter()) {
+            match child {
+                ForestChild::Nonterminal(c) => branches.append(&mut self.branch(*c, *rank)),
+                ForestChild::Terminal(_, s) => branches.push(Branch::Terminal(s.to_string())),
+            }
+        }
+
+        match self.productions.get(v) {
+            Some(production) => vec![Branch::Nonterminal(Tree {
+                production: production.clone(),
+                branches,
+                trivia: Default::default(),
+            })],
+            None => branches,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::error::Error;
+use crate::prod::EarleyProd;
+use bnf::{Grammar, Term};
+
+/// A layout nonterminal, e.g. `<ws>`, that the parser lets through between
+/// the symbols of every production without the grammar having to mention
+/// it, the way SDF treats layout in context-free syntax. That makes
+/// character-level grammars usable on input with arbitrary spacing:
+///
+/// ```text
+/// <Block> ::= <If> | '{' '}'
+/// <If>    ::= <if> <Block> | <if> <Block> <else> <Block>
+/// <if>    ::= 'i' 'f'
+/// <else>  ::= 'e' 'l' 's' 'e'
+/// <ws>    ::= ' ' | '\n' | ' ' <ws> | '\n' <ws>
+/// ```
+///
+/// with `Layout::new("ws").lexical("if").lexical("else")` accepts
+/// `if if {} else { }`, with layout before and after the input too, but not
+/// `i f {}`: the productions of nonterminals marked `lexical`, like the
+/// keywords here, and of the layout itself (along with everything it
+/// derives) are taken as written.
+///
+/// Layout doesn't show up in the parse trees, which only hold the symbols
+/// of the grammar's productions.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Layout {
+    nonterminal: Term,
+    lexical: Vec<Term>,
+}
+
+impl Layout {
+    pub fn new(nonterminal: &str) -> Layout {
+        Layout {
+            nonterminal: Term::Nonterminal(nonterminal.to_string()),
+            lexical: vec![],
+        }
+    }
+
+    /// Keeps layout out of the productions of `<nonterminal>`.
+    pub fn lexical(mut self, nonterminal: &str) -> Layout {
+        self.lexical
+            .push(Term::Nonterminal(nonterminal.to_string()));
+        self
+    }
+
+    pub fn nonterminal(&self) -> &Term {
+        &self.nonterminal
+    }
+
+    /// Whether layout may come between the symbols of `prod`.
+    pub fn allows_between(&self, prod: &EarleyProd) -> bool {
+        prod.lhs != self.nonterminal && !self.lexical.contains(&prod.lhs)
+    }
+
+    /// Checks the layout nonterminal is defined in `grammar` and marks
+    /// every nonterminal it derives lexical, so layout can't nest in
+    /// itself.
+    pub fn resolve(mut self, grammar: &Grammar) -> Result<Layout, Error> {
+        let defines = |term: &Term| grammar.productions_iter().any(|p| p.lhs == *term);
+        if !defines(&self.nonterminal) {
+            return Err(Error::GrammarError(format!(
+                "Layout nonterminal {} isn't defined in grammar: {}",
+                self.nonterminal, grammar
+            )));
+        }
+
+        let mut reachable = vec![self.nonterminal.clone()];
+        let mut i = 0;
+        while i < reachable.len() {
+            let terms: Vec<Term> = grammar
+                .productions_iter()
+                .filter(|p| p.lhs == reachable[i])
+                .flat_map(|p| p.rhs_iter())
+                .flat_map(|expr| expr.terms_iter())
+                .filter(|t| matches!(t, Term::Nonterminal(_)))
+                .cloned()
+                .collect();
+            for term in terms {
+                if !reachable.contains(&term) {
+                    reachable.push(term);
+                }
+            }
+            i += 1;
+        }
+
+        for term in reachable.into_iter().skip(1) {
+            if !self.lexical.contains(&term) {
+                self.lexical.push(term);
+            }
+        }
+        Ok(self)
+    }
+}
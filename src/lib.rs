@@ -1,12 +1,36 @@
 extern crate bnf;
+extern crate earley_macros;
 extern crate linked_hash_set;
+extern crate rand;
 extern crate serde;
 
+pub mod abnf;
+pub mod action;
+pub mod builder;
 pub mod chart;
+pub mod compiled;
 pub mod earley;
+pub mod ebnf;
 pub mod error;
+pub mod export;
+pub mod forest;
+pub mod from_tree;
+pub mod generate;
+pub mod grammar;
 pub mod istate;
-mod itree;
+pub mod kbest;
+pub mod layout;
+mod lower;
+pub mod module;
+pub mod normal;
 pub mod outcome;
+pub mod param;
 pub mod prod;
+pub mod query;
+pub mod restrict;
+pub mod rewrite;
+pub mod sample;
+pub mod shape;
+pub mod token;
 pub mod tree;
+pub mod visit;
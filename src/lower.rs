@@ -0,0 +1,203 @@
+use crate::error::Error;
+use bnf::{Expression, Production, Term};
+use std::collections::HashSet;
+
+/// The alternatives of a rule being lowered, each a sequence of terms that
+/// may be empty until `remove_empty` is done with them.
+pub(crate) type Alternatives = Vec<Vec<Term>>;
+
+/// A rule of a grammar written in a notation richer than BNF, like ABNF or
+/// EBNF.
+pub(crate) trait Rule {
+    fn name(&self) -> &str;
+
+    /// The line the rule is defined on.
+    fn line(&self) -> usize;
+
+    /// The rule names the rule refers to, in order.
+    fn uses(&self) -> Vec<String>;
+}
+
+/// Rule `start` and the rules it uses, directly or not, in the order
+/// they're reached, with `find` looking a rule up by name. A name that isn't
+/// defined is an error made by `error`, which names the `notation`.
+pub(crate) fn reachable<'r, R: Rule>(
+    start: &str,
+    find: impl Fn(&str) -> Option<&'r R>,
+    error: fn(String) -> Error,
+    notation: &str,
+) -> Result<Vec<&'r R>, Error> {
+    let mut reachable: Vec<&R> = vec![];
+    let mut queue = vec![(start.to_string(), None)];
+    while let Some((name, used_by)) = queue.pop() {
+        let rule = find(&name).ok_or_else(|| match used_by {
+            Some((rule, line)) => error(format!(
+                "line {}: {} uses {}, which isn't defined",
+                line, rule, name
+            )),
+            None => error(format!("No rule {} in {}", name, notation)),
+        })?;
+        if reachable.iter().any(|r| r.name() == rule.name()) {
+            continue;
+        }
+        reachable.push(rule);
+
+        for name in rule.uses().into_iter().rev() {
+            queue.push((name, Some((rule.name().to_string(), rule.line()))));
+        }
+    }
+    Ok(reachable)
+}
+
+/// The sorted, distinct chars of `text`, for classes and ranges to match
+/// against.
+pub(crate) fn alphabet(text: &str) -> Vec<char> {
+    let mut alphabet: Vec<char> = text.chars().collect();
+    alphabet.sort_unstable();
+    alphabet.dedup();
+    alphabet
+}
+
+/// Lowers a notation's alternations into alternatives of terms. The rules
+/// go first, in the order they're reached from the start rule, ahead of the
+/// helper nonterminals lowering them adds, named after the notation's text.
+pub(crate) trait Lower {
+    /// What a sequence in the notation is made of.
+    type Item;
+
+    fn rules(&mut self) -> &mut Vec<(String, Alternatives)>;
+
+    /// The sequences of terms `item` can be.
+    fn item(&mut self, item: &Self::Item) -> Result<Alternatives, Error>;
+
+    /// The sequences of terms `alternation` can be: for each of its
+    /// sequences, every way of picking one for each of its items in turn.
+    fn alternation(&mut self, alternation: &[Vec<Self::Item>]) -> Result<Alternatives, Error> {
+        let mut alternatives = vec![];
+        for sequence in alternation {
+            let mut sequences: Alternatives = vec![vec![]];
+            for item in sequence {
+                let options = self.item(item)?;
+                sequences = sequences
+                    .iter()
+                    .flat_map(|sequence| {
+                        options.iter().map(move |option| {
+                            let mut sequence = sequence.clone();
+                            sequence.extend(option.iter().cloned());
+                            sequence
+                        })
+                    })
+                    .collect();
+            }
+            for sequence in sequences {
+                if !alternatives.contains(&sequence) {
+                    alternatives.push(sequence);
+                }
+            }
+        }
+        Ok(alternatives)
+    }
+
+    /// A nonterminal named `name` with `alternatives`, added the first time
+    /// it's needed.
+    fn helper(&mut self, name: String, alternatives: Alternatives) -> Term {
+        let rules = self.rules();
+        if !rules.iter().any(|(n, _)| *n == name) {
+            rules.push((name.clone(), alternatives));
+        }
+        Term::Nonterminal(name)
+    }
+}
+
+/// The rules as productions.
+pub(crate) fn productions(rules: Vec<(String, Alternatives)>) -> Vec<Production> {
+    rules
+        .into_iter()
+        .map(|(name, alternatives)| {
+            Production::from_parts(
+                Term::Nonterminal(name),
+                alternatives
+                    .into_iter()
+                    .map(Expression::from_parts)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Takes out the empty alternatives, adding to every alternative that uses
+/// a nonterminal matching the empty string a copy without it. A rule left
+/// without alternatives matches nothing, so it's taken out along with every
+/// alternative using it, unless it's the first one. For a rule that only
+/// matched the empty string, the copies without it remain; for one whose
+/// ranges got none of the input's chars, nothing does.
+pub(crate) fn remove_empty(rules: Vec<(String, Alternatives)>) -> Vec<(String, Alternatives)> {
+    let mut nullable: HashSet<Term> = HashSet::new();
+    loop {
+        let before = nullable.len();
+        for (name, alternatives) in &rules {
+            if alternatives
+                .iter()
+                .any(|alternative| alternative.iter().all(|t| nullable.contains(t)))
+            {
+                nullable.insert(Term::Nonterminal(name.to_string()));
+            }
+        }
+        if nullable.len() == before {
+            break;
+        }
+    }
+
+    let mut rules: Vec<(String, Alternatives)> = rules
+        .into_iter()
+        .map(|(name, alternatives)| {
+            let mut expanded: Alternatives = vec![];
+            for alternative in alternatives {
+                let mut sequences: Alternatives = vec![vec![]];
+                for term in alternative {
+                    let dropped = if nullable.contains(&term) {
+                        sequences.clone()
+                    } else {
+                        vec![]
+                    };
+                    for sequence in sequences.iter_mut() {
+                        sequence.push(term.clone());
+                    }
+                    sequences.extend(dropped);
+                }
+                for sequence in sequences {
+                    if !sequence.is_empty() && !expanded.contains(&sequence) {
+                        expanded.push(sequence);
+                    }
+                }
+            }
+            (name, expanded)
+        })
+        .collect();
+
+    loop {
+        let gone: HashSet<Term> = rules
+            .iter()
+            .skip(1)
+            .filter(|(_, alternatives)| alternatives.is_empty())
+            .map(|(name, _)| Term::Nonterminal(name.to_string()))
+            .collect();
+        if gone.is_empty() {
+            return rules;
+        }
+        rules = rules
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (name, _))| {
+                *i == 0 || !gone.contains(&Term::Nonterminal(name.to_string()))
+            })
+            .map(|(_, (name, alternatives))| {
+                let alternatives = alternatives
+                    .into_iter()
+                    .filter(|alternative| !alternative.iter().any(|t| gone.contains(t)))
+                    .collect();
+                (name, alternatives)
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,449 @@
+use crate::error::Error;
+use bnf::{Expression, Grammar, Production, Term};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Define,
+    Override,
+    Extend,
+}
+
+/// `import "path" [as ns]`, which makes every rule of the module usable as
+/// `<ns.rule>`, or `import <a>, <b> from "path" [as ns]`, which makes the
+/// rules named usable as they are. Either way the module's rules are in
+/// namespace `ns`, the file's stem unless it's given.
+struct Import {
+    line: usize,
+    path: String,
+    namespace: String,
+    names: Option<Vec<String>>,
+}
+
+struct Rule {
+    line: usize,
+    kind: Kind,
+    name: String,
+    alternatives: Vec<Vec<Term>>,
+}
+
+struct Module {
+    imports: Vec<Import>,
+    rules: Vec<Rule>,
+}
+
+/// The rules of a module with its imports resolved, under the names they
+/// have in the grammar, and the names other modules import them by.
+struct Instance {
+    rules: Vec<(String, Vec<Vec<Term>>)>,
+    exports: Vec<(String, String)>,
+}
+
+/// Grammars split into modules, BNF files that import rules from each
+/// other, resolved into a single grammar for the parser:
+///
+/// ```text
+/// # calc.bnf
+/// import <number> from "numbers.bnf"
+/// import "ident.bnf" as id
+///
+/// <expr> ::= <expr> '+' <term> | <term>
+/// <term> ::= <number> | <id.ident> | '(' <expr> ')'
+/// extend <id.letter> ::= '_'
+/// ```
+///
+/// A module's rules end up in the grammar under the namespaces of the
+/// imports that lead to it, e.g. `<id.letter>` or `<numbers.digit>`, so
+/// rules of the same name in different modules don't collide. `override`
+/// replaces the alternatives of an imported rule and `extend` adds to
+/// them, everywhere the imported module uses the rule. The first rule of
+/// the main module is the start rule.
+///
+/// Lines starting with `#` are comments. Errors give the file and line of
+/// the rule or import at fault.
+#[derive(Default)]
+pub struct Modules {
+    sources: HashMap<String, String>,
+    dir: Option<PathBuf>,
+}
+
+impl Modules {
+    pub fn new() -> Modules {
+        Modules::default()
+    }
+
+    /// Module `path`, with `source` rather than what's on disk.
+    pub fn with_module(mut self, path: &str, source: &str) -> Modules {
+        self.sources.insert(path.to_string(), source.to_string());
+        self
+    }
+
+    /// Reads modules not given with `with_module` from `dir`, which import
+    /// paths are relative to.
+    pub fn with_dir<P: Into<PathBuf>>(mut self, dir: P) -> Modules {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// The grammar of the module in file `path`, importing from the files
+    /// next to it.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Grammar, Error> {
+        let path = path.as_ref();
+        let file = match path.file_name() {
+            Some(file) => file.to_string_lossy().to_string(),
+            None => {
+                return Err(Error::ModuleError(format!(
+                    "{} isn't a file",
+                    path.display()
+                )))
+            }
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Modules::new().with_dir(dir).resolve(&file)
+    }
+
+    /// The grammar of module `main`.
+    pub fn resolve(&self, main: &str) -> Result<Grammar, Error> {
+        let source = self.source(main).map_err(Error::ModuleError)?;
+        let instance = self.instantiate(main, &source, "", &mut vec![])?;
+        if instance.exports.is_empty() {
+            return Err(Error::ModuleError(format!("{} defines no rules", main)));
+        }
+        Ok(Grammar::from_parts(
+            instance
+                .rules
+                .into_iter()
+                .map(|(name, alternatives)| {
+                    Production::from_parts(
+                        Term::Nonterminal(name),
+                        alternatives
+                            .into_iter()
+                            .map(Expression::from_parts)
+                            .collect(),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    fn source(&self, path: &str) -> Result<String, String> {
+        if let Some(source) = self.sources.get(path) {
+            return Ok(source.to_string());
+        }
+        match &self.dir {
+            Some(dir) => fs::read_to_string(dir.join(path))
+                .map_err(|e| format!("can't read module {}: {}", path, e)),
+            None => Err(format!("no module {}", path)),
+        }
+    }
+
+    /// Resolves module `file`, naming its rules with `prefix`. `stack` holds
+    /// the modules importing it, to catch import cycles.
+    fn instantiate(
+        &self,
+        file: &str,
+        source: &str,
+        prefix: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<Instance, Error> {
+        let error =
+            |line: usize, msg: String| Error::ModuleError(format!("{}:{}: {}", file, line, msg));
+        let module = parse(file, source)?;
+        stack.push(file.to_string());
+
+        // The names usable in this module: the name in the grammar each
+        // stands for, and the module it comes from.
+        let mut scope: HashMap<String, (String, String)> = HashMap::new();
+        let mut namespaces: Vec<&str> = vec![];
+        let mut rules: Vec<(String, Vec<Vec<Term>>)> = vec![];
+        for import in &module.imports {
+            if stack.contains(&import.path) {
+                return Err(error(
+                    import.line,
+                    format!("import cycle {} -> {}", stack.join(" -> "), import.path),
+                ));
+            }
+            if namespaces.contains(&import.namespace.as_str()) {
+                return Err(error(
+                    import.line,
+                    format!("namespace {} is already in use", import.namespace),
+                ));
+            }
+            namespaces.push(&import.namespace);
+
+            let source = self
+                .source(&import.path)
+                .map_err(|msg| error(import.line, msg))?;
+            let namespace = format!("{}{}.", prefix, import.namespace);
+            let instance = self.instantiate(&import.path, &source, &namespace, stack)?;
+            let bound: Vec<(String, String)> = match &import.names {
+                None => instance
+                    .exports
+                    .iter()
+                    .map(|(local, name)| {
+                        (format!("{}.{}", import.namespace, local), name.to_string())
+                    })
+                    .collect(),
+                Some(names) => names
+                    .iter()
+                    .map(
+                        |local| match instance.exports.iter().find(|(l, _)| l == local) {
+                            Some((_, name)) => Ok((local.to_string(), name.to_string())),
+                            None => Err(error(
+                                import.line,
+                                format!("{} has no rule <{}>", import.path, local),
+                            )),
+                        },
+                    )
+                    .collect::<Result<_, _>>()?,
+            };
+            for (local, name) in bound {
+                if let Some((_, from)) = scope.get(&local) {
+                    return Err(error(
+                        import.line,
+                        format!(
+                            "<{}> is imported from both {} and {}",
+                            local, from, import.path
+                        ),
+                    ));
+                }
+                scope.insert(local, (name, import.path.to_string()));
+            }
+            rules.extend(instance.rules);
+        }
+
+        let mut exports: Vec<(String, String)> = vec![];
+        for rule in module.rules.iter().filter(|r| r.kind == Kind::Define) {
+            if let Some((_, from)) = scope.get(&rule.name) {
+                if from != file {
+                    return Err(error(
+                        rule.line,
+                        format!(
+                            "rule <{}> conflicts with <{}> imported from {}, use override or extend",
+                            rule.name, rule.name, from
+                        ),
+                    ));
+                }
+                continue;
+            }
+            if let Some(namespace) = namespaces
+                .iter()
+                .find(|ns| rule.name.starts_with(&format!("{}.", ns)))
+            {
+                return Err(error(
+                    rule.line,
+                    format!(
+                        "rule <{}> is in imported namespace {}",
+                        rule.name, namespace
+                    ),
+                ));
+            }
+            let name = format!("{}{}", prefix, rule.name);
+            scope.insert(rule.name.to_string(), (name.to_string(), file.to_string()));
+            exports.push((rule.name.to_string(), name));
+        }
+
+        let mut own: Vec<(String, Vec<Vec<Term>>)> = exports
+            .iter()
+            .map(|(_, name)| (name.to_string(), vec![]))
+            .collect();
+        for rule in &module.rules {
+            let mut alternatives = vec![];
+            for terms in &rule.alternatives {
+                let mut translated = vec![];
+                for term in terms {
+                    translated.push(match term {
+                        Term::Nonterminal(n) => match scope.get(n) {
+                            Some((name, _)) => Term::Nonterminal(name.to_string()),
+                            None => {
+                                return Err(error(
+                                    rule.line,
+                                    format!(
+                                        "rule <{}> uses <{}>, which isn't defined",
+                                        rule.name, n
+                                    ),
+                                ))
+                            }
+                        },
+                        terminal => terminal.clone(),
+                    });
+                }
+                alternatives.push(translated);
+            }
+
+            let name = match (rule.kind, scope.get(&rule.name)) {
+                (Kind::Define, Some((name, _))) => name,
+                (_, Some((name, from))) if from != file => name,
+                _ => {
+                    return Err(error(
+                        rule.line,
+                        format!(
+                            "can't {} <{}>, it isn't imported",
+                            keyword(rule.kind),
+                            rule.name
+                        ),
+                    ))
+                }
+            };
+            let target = match rule.kind {
+                Kind::Define => &mut own,
+                _ => &mut rules,
+            };
+            let existing = &mut target.iter_mut().find(|(n, _)| n == name).unwrap().1;
+            if rule.kind == Kind::Override {
+                existing.clear();
+            }
+            for terms in alternatives {
+                if !existing.contains(&terms) {
+                    existing.push(terms);
+                }
+            }
+        }
+
+        stack.pop();
+        own.extend(rules);
+        Ok(Instance {
+            rules: own,
+            exports,
+        })
+    }
+}
+
+fn keyword(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Define => "define",
+        Kind::Override => "override",
+        Kind::Extend => "extend",
+    }
+}
+
+/// Whether `text` starts with the word `word`.
+fn starts_with_word(text: &str, word: &str) -> bool {
+    text.starts_with(word)
+        && text[word.len()..]
+            .chars()
+            .next()
+            .is_some_and(char::is_whitespace)
+}
+
+/// The imports and rules of a module. An import takes a line, and a rule
+/// runs on to the next line starting a rule or an import.
+fn parse(file: &str, source: &str) -> Result<Module, Error> {
+    let error =
+        |line: usize, msg: String| Error::ModuleError(format!("{}:{}: {}", file, line, msg));
+
+    let mut statements: Vec<(usize, String)> = vec![];
+    for (i, line) in source.lines().enumerate() {
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let starts = text.starts_with('<')
+            || ["import", "override", "extend"]
+                .iter()
+                .any(|word| starts_with_word(text, word));
+        match statements.last_mut() {
+            Some((_, statement)) if !starts && !statement.starts_with("import") => {
+                statement.push(' ');
+                statement.push_str(text);
+            }
+            _ if !starts => {
+                return Err(error(
+                    i + 1,
+                    format!("expected a rule or an import: {}", text),
+                ))
+            }
+            _ => statements.push((i + 1, text.to_string())),
+        }
+    }
+
+    let mut module = Module {
+        imports: vec![],
+        rules: vec![],
+    };
+    for (line, statement) in statements {
+        if starts_with_word(&statement, "import") {
+            let import =
+                parse_import(line, &statement["import".len()..]).map_err(|msg| error(line, msg))?;
+            module.imports.push(import);
+            continue;
+        }
+
+        let (kind, text) = if starts_with_word(&statement, "override") {
+            (Kind::Override, &statement["override".len()..])
+        } else if starts_with_word(&statement, "extend") {
+            (Kind::Extend, &statement["extend".len()..])
+        } else {
+            (Kind::Define, &statement[..])
+        };
+        let production: Production = text
+            .parse()
+            .map_err(|e| error(line, format!("invalid rule {}: {:?}", text.trim(), e)))?;
+        let name = match production.lhs {
+            Term::Nonterminal(ref name) => name.to_string(),
+            Term::Terminal(ref t) => return Err(error(line, format!("rule for terminal '{}'", t))),
+        };
+        module.rules.push(Rule {
+            line,
+            kind,
+            name,
+            alternatives: production
+                .rhs_iter()
+                .map(|expr| expr.terms_iter().cloned().collect())
+                .collect(),
+        });
+    }
+    Ok(module)
+}
+
+/// What follows `import`: `"path" [as ns]` or `<a>, <b> from "path" [as ns]`,
+/// with an optional `;` at the end.
+fn parse_import(line: usize, text: &str) -> Result<Import, String> {
+    let invalid = || format!("invalid import: import{}", text);
+    let mut rest = text.trim().trim_end_matches(';').trim_end();
+
+    let mut names = None;
+    if rest.starts_with('<') {
+        let from = rest.find(" from ").ok_or_else(invalid)?;
+        let list: Result<Vec<String>, String> = rest[..from]
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                match name.strip_prefix('<').and_then(|n| n.strip_suffix('>')) {
+                    Some(n) if !n.is_empty() => Ok(n.to_string()),
+                    _ => Err(invalid()),
+                }
+            })
+            .collect();
+        names = Some(list?);
+        rest = rest[from + " from ".len()..].trim_start();
+    }
+
+    let path = rest.strip_prefix('"').ok_or_else(invalid)?;
+    let end = path.find('"').ok_or_else(invalid)?;
+    let (path, rest) = (&path[..end], path[end + 1..].trim());
+    let namespace = if rest.is_empty() {
+        Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or_else(invalid)?
+    } else if starts_with_word(rest, "as") {
+        rest["as".len()..].trim().to_string()
+    } else {
+        return Err(invalid());
+    };
+    if namespace.is_empty()
+        || namespace.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')
+    {
+        return Err(invalid());
+    }
+
+    Ok(Import {
+        line,
+        path: path.to_string(),
+        namespace,
+        names,
+    })
+}
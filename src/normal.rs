@@ -0,0 +1,899 @@
+use crate::error::Error;
+use crate::export::rules;
+use crate::tree::{Branch, Tree};
+use bnf::{Expression, Grammar, Production, Term};
+use std::collections::{HashMap, HashSet};
+
+/// How to build the branches a node of a transformed grammar stands for in
+/// the grammar it was transformed from.
+#[derive(Clone, Debug)]
+enum Fragment {
+    /// A node of the original grammar over the branches of the fragments.
+    Node(Term, Vec<Fragment>),
+    /// The branches the node's `i`th branch stands for.
+    Branch(usize),
+    /// The branches the node's `i`th branch stands for, given the slots
+    /// built by the fragments as the branches to its left.
+    Threaded(usize, Vec<Vec<Fragment>>),
+    /// The `k`th slot of branches handed down from the left.
+    Left(usize),
+    /// Branches of the original grammar with nothing to match in the
+    /// transformed one, like the derivations of removed empty strings.
+    Fixed(Branch),
+}
+
+/// Where `reindex` moves a branch index to.
+enum Position {
+    At(usize),
+    Left(usize),
+    Inline(Vec<Fragment>),
+}
+
+/// `fragments` with each branch index `i` moved to `position(i)`.
+fn reindex(fragments: &[Fragment], position: &dyn Fn(usize) -> Position) -> Vec<Fragment> {
+    let mut out = vec![];
+    for fragment in fragments {
+        match fragment {
+            Fragment::Node(lhs, fragments) => {
+                out.push(Fragment::Node(lhs.clone(), reindex(fragments, position)))
+            }
+            Fragment::Branch(i) => match position(*i) {
+                Position::At(j) => out.push(Fragment::Branch(j)),
+                Position::Left(k) => out.push(Fragment::Left(k)),
+                Position::Inline(fragments) => out.extend(fragments),
+            },
+            Fragment::Threaded(i, slots) => {
+                let slots = slots.iter().map(|slot| reindex(slot, position)).collect();
+                match position(*i) {
+                    Position::At(j) => out.push(Fragment::Threaded(j, slots)),
+                    // Only helper nonterminals are threaded, and they're
+                    // never moved into the left context or substituted.
+                    _ => unreachable!("threaded branch {} moved out of its node", i),
+                }
+            }
+            Fragment::Left(_) | Fragment::Fixed(_) => out.push(fragment.clone()),
+        }
+    }
+    out
+}
+
+type Step = HashMap<(Term, Vec<Term>), Vec<Fragment>>;
+
+/// Translates trees of a transformed grammar into trees of the grammar it
+/// was transformed from, one step of the transformation at a time.
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    steps: Vec<Step>,
+}
+
+impl Mapping {
+    fn then(mut self, next: Mapping) -> Mapping {
+        self.steps.extend(next.steps);
+        self
+    }
+
+    /// The tree of the original grammar that `tree`, a tree of the
+    /// transformed grammar, stands for.
+    pub fn translate(&self, tree: &Tree) -> Result<Tree, Error> {
+        let mut tree = tree.clone();
+        for step in self.steps.iter().rev() {
+            let mut branches = translate(step, &Branch::Nonterminal(tree), &[])?;
+            tree = match (branches.pop(), branches.is_empty()) {
+                (Some(Branch::Nonterminal(tree)), true) => tree,
+                _ => {
+                    return Err(Error::InvalidTree(
+                        "Root doesn't stand for a single node".to_string(),
+                    ))
+                }
+            };
+        }
+        Ok(tree)
+    }
+}
+
+fn translate(step: &Step, branch: &Branch, left: &[Vec<Branch>]) -> Result<Vec<Branch>, Error> {
+    let tree = match branch {
+        Branch::Nonterminal(tree) => tree,
+        leaf => return Ok(vec![leaf.clone()]),
+    };
+    let terms: Vec<Term> = tree
+        .production
+        .rhs_iter()
+        .next()
+        .map(|expr| expr.terms_iter().cloned().collect())
+        .unwrap_or_default();
+    match step.get(&(tree.production.lhs.clone(), terms)) {
+        Some(fragments) => eval(step, fragments, &tree.branches, left),
+        None => Err(Error::InvalidTree(format!(
+            "{} isn't in the transformed grammar",
+            tree.production
+        ))),
+    }
+}
+
+fn eval(
+    step: &Step,
+    fragments: &[Fragment],
+    branches: &[Branch],
+    left: &[Vec<Branch>],
+) -> Result<Vec<Branch>, Error> {
+    let branch = |i: usize| {
+        branches
+            .get(i)
+            .ok_or_else(|| Error::InvalidTree(format!("Node has no branch {}", i)))
+    };
+    let mut out = vec![];
+    for fragment in fragments {
+        match fragment {
+            Fragment::Node(lhs, fragments) => {
+                let branches = eval(step, fragments, branches, left)?;
+                out.push(Branch::Nonterminal(Tree::from_branches(
+                    lhs.clone(),
+                    branches,
+                )));
+            }
+            Fragment::Branch(i) => out.extend(translate(step, branch(*i)?, &[])?),
+            Fragment::Threaded(i, slots) => {
+                let slots = slots
+                    .iter()
+                    .map(|slot| eval(step, slot, branches, left))
+                    .collect::<Result<Vec<_>, _>>()?;
+                out.extend(translate(step, branch(*i)?, &slots)?);
+            }
+            Fragment::Left(k) => match left.get(*k) {
+                Some(slot) => out.extend(slot.iter().cloned()),
+                None => {
+                    return Err(Error::InvalidTree(
+                        "Helper node is missing what's to its left".to_string(),
+                    ))
+                }
+            },
+            Fragment::Fixed(branch) => out.push(branch.clone()),
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Clone)]
+struct Alternative {
+    terms: Vec<Term>,
+    template: Vec<Fragment>,
+}
+
+impl Alternative {
+    /// `terms`, standing for the same alternative of `<lhs>`.
+    fn identity(lhs: &str, terms: Vec<Term>) -> Alternative {
+        let template = vec![Fragment::Node(
+            Term::Nonterminal(lhs.to_string()),
+            (0..terms.len()).map(Fragment::Branch).collect(),
+        )];
+        Alternative { terms, template }
+    }
+
+    /// The alternative with its first term, a nonterminal, replaced by
+    /// `first`, one of that nonterminal's alternatives.
+    fn substitute_first(&self, first: &Alternative) -> Alternative {
+        let m = first.terms.len();
+        let mut terms = first.terms.clone();
+        terms.extend(self.terms[1..].iter().cloned());
+        let template = reindex(&self.template, &|i| {
+            if i == 0 {
+                Position::Inline(first.template.clone())
+            } else {
+                Position::At(i + m - 1)
+            }
+        });
+        Alternative { terms, template }
+    }
+}
+
+type Rules = Vec<(String, Vec<Alternative>)>;
+
+fn identity(grammar: &Grammar) -> Rules {
+    rules(grammar)
+        .into_iter()
+        .map(|(name, alternatives)| {
+            let alternatives = alternatives
+                .into_iter()
+                .map(|terms| Alternative::identity(&name, terms))
+                .collect();
+            (name, alternatives)
+        })
+        .collect()
+}
+
+/// The grammar of `rules`, leaving out nonterminals without alternatives,
+/// and the step back to the grammar the templates are written against.
+fn finish(rules: Rules) -> (Grammar, Mapping) {
+    let mut step: Step = HashMap::new();
+    let mut productions = vec![];
+    for (name, alternatives) in rules {
+        let lhs = Term::Nonterminal(name);
+        let mut exprs = vec![];
+        for alternative in alternatives {
+            let key = (lhs.clone(), alternative.terms.clone());
+            if step.contains_key(&key) {
+                continue;
+            }
+            step.insert(key, alternative.template);
+            exprs.push(Expression::from_parts(alternative.terms));
+        }
+        if !exprs.is_empty() {
+            productions.push(Production::from_parts(lhs, exprs));
+        }
+    }
+    (
+        Grammar::from_parts(productions),
+        Mapping { steps: vec![step] },
+    )
+}
+
+/// Every nonterminal name in `rules`, defined or used.
+fn names(rules: &Rules) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for (name, alternatives) in rules {
+        names.insert(name.to_string());
+        for alternative in alternatives {
+            for term in &alternative.terms {
+                if let Term::Nonterminal(n) = term {
+                    names.insert(n.to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// `base`, primed until no nonterminal has the name.
+fn fresh(base: &str, taken: &mut HashSet<String>) -> String {
+    let mut name = base.to_string();
+    while taken.contains(&name) {
+        name.push('\'');
+    }
+    taken.insert(name.to_string());
+    name
+}
+
+fn nonterminal(term: &Term) -> Option<&str> {
+    match term {
+        Term::Nonterminal(name) => Some(name),
+        Term::Terminal(_) => None,
+    }
+}
+
+/// Removes empty strings, written `''`, from the grammar: every
+/// alternative with a nonterminal that can derive the empty string gets a
+/// copy without it. Translated trees get a derivation of the empty string
+/// where it was left out.
+///
+/// The parser takes no empty input, so if the start symbol derives the
+/// empty string, the grammar loses that sentence.
+pub fn remove_epsilon(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let rules = rules(grammar);
+    let is_empty = |term: &Term| *term == Term::Terminal(String::new());
+
+    // A derivation of the empty string for every nonterminal that has one.
+    let mut empty: HashMap<String, Tree> = HashMap::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, alternatives) in &rules {
+            if empty.contains_key(name) {
+                continue;
+            }
+            let nullable = alternatives.iter().find(|terms| {
+                terms.iter().all(|term| {
+                    is_empty(term) || nonterminal(term).is_some_and(|n| empty.contains_key(n))
+                })
+            });
+            if let Some(terms) = nullable {
+                let branches = terms
+                    .iter()
+                    .map(|term| match term {
+                        Term::Nonterminal(n) => Branch::Nonterminal(empty[n].clone()),
+                        Term::Terminal(t) => Branch::Terminal(t.to_string()),
+                    })
+                    .collect();
+                let tree = Tree::from_branches(Term::Nonterminal(name.to_string()), branches);
+                empty.insert(name.to_string(), tree);
+                changed = true;
+            }
+        }
+    }
+
+    let mut out: Rules = vec![];
+    for (name, alternatives) in &rules {
+        let lhs = Term::Nonterminal(name.to_string());
+        let mut variants = vec![];
+        for terms in alternatives {
+            let optional: Vec<usize> = (0..terms.len())
+                .filter(|&i| nonterminal(&terms[i]).is_some_and(|n| empty.contains_key(n)))
+                .collect();
+            // Every subset of the optional terms to leave out, keeping them
+            // all first.
+            for mask in 0..1usize << optional.len() {
+                let mut kept = vec![];
+                let mut template = vec![];
+                for (i, term) in terms.iter().enumerate() {
+                    let left_out = optional
+                        .iter()
+                        .position(|&o| o == i)
+                        .is_some_and(|bit| mask & (1 << bit) != 0);
+                    if is_empty(term) {
+                        template.push(Fragment::Fixed(Branch::Terminal(String::new())));
+                    } else if left_out {
+                        let tree = empty[nonterminal(term).unwrap()].clone();
+                        template.push(Fragment::Fixed(Branch::Nonterminal(tree)));
+                    } else {
+                        template.push(Fragment::Branch(kept.len()));
+                        kept.push(term.clone());
+                    }
+                }
+                if !kept.is_empty() {
+                    variants.push(Alternative {
+                        terms: kept,
+                        template: vec![Fragment::Node(lhs.clone(), template)],
+                    });
+                }
+            }
+        }
+        out.push((name.to_string(), variants));
+    }
+
+    // Nonterminals that only derive the empty string are gone, and so are
+    // the alternatives that still use them.
+    loop {
+        let gone: Vec<String> = out
+            .iter()
+            .filter(|(_, alternatives)| alternatives.is_empty())
+            .map(|(name, _)| name.to_string())
+            .collect();
+        let mut changed = false;
+        for (_, alternatives) in out.iter_mut() {
+            let before = alternatives.len();
+            alternatives.retain(|alternative| {
+                !alternative
+                    .terms
+                    .iter()
+                    .any(|term| nonterminal(term).is_some_and(|n| gone.iter().any(|g| g == n)))
+            });
+            changed |= alternatives.len() != before;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    match out.first() {
+        Some((name, alternatives)) if alternatives.is_empty() => Err(Error::GrammarError(format!(
+            "<{}> only derives the empty string",
+            name
+        ))),
+        _ => Ok(finish(out)),
+    }
+}
+
+/// Replaces unit productions, `<A> ::= <B>`, with the alternatives of
+/// `<B>` that aren't units themselves. Translated trees get the chain of
+/// units back.
+pub fn remove_units(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let rules = rules(grammar);
+    let alternatives_of = |name: &str| {
+        rules
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, alternatives)| alternatives.as_slice())
+            .unwrap_or(&[])
+    };
+    let unit = |terms: &[Term]| -> Option<String> {
+        match terms {
+            [Term::Nonterminal(n)] => Some(n.to_string()),
+            _ => None,
+        }
+    };
+
+    let mut out: Rules = vec![];
+    for (name, _) in &rules {
+        // Every nonterminal `name` derives by units, with the chain of
+        // nonterminals leading to it.
+        let mut chains: Vec<Vec<String>> = vec![vec![name.to_string()]];
+        let mut i = 0;
+        while i < chains.len() {
+            let last = chains[i].last().unwrap().to_string();
+            for terms in alternatives_of(&last) {
+                if let Some(next) = unit(terms) {
+                    if !chains.iter().any(|chain| *chain.last().unwrap() == next) {
+                        let mut chain = chains[i].clone();
+                        chain.push(next);
+                        chains.push(chain);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let mut alternatives = vec![];
+        for chain in &chains {
+            let last = chain.last().unwrap();
+            for terms in alternatives_of(last).iter().filter(|t| unit(t).is_none()) {
+                let mut template = vec![Fragment::Node(
+                    Term::Nonterminal(last.to_string()),
+                    (0..terms.len()).map(Fragment::Branch).collect(),
+                )];
+                for outer in chain.iter().rev().skip(1) {
+                    template = vec![Fragment::Node(
+                        Term::Nonterminal(outer.to_string()),
+                        template,
+                    )];
+                }
+                alternatives.push(Alternative {
+                    terms: terms.clone(),
+                    template,
+                });
+            }
+        }
+        out.push((name.to_string(), alternatives));
+    }
+    Ok(finish(out))
+}
+
+/// Removes the nonterminals that derive no sentence, the alternatives
+/// using them, and then whatever the start symbol can't reach.
+pub fn remove_useless(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let mut rules = identity(grammar);
+
+    let mut generating: HashSet<String> = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (name, alternatives) in &rules {
+            if generating.contains(name) {
+                continue;
+            }
+            let generates = alternatives.iter().any(|alternative| {
+                alternative
+                    .terms
+                    .iter()
+                    .all(|term| nonterminal(term).is_none_or(|n| generating.contains(n)))
+            });
+            if generates {
+                generating.insert(name.to_string());
+                changed = true;
+            }
+        }
+    }
+    match rules.first() {
+        Some((start, _)) if !generating.contains(start) => {
+            return Err(Error::GrammarError(format!(
+                "<{}> derives no sentences",
+                start
+            )))
+        }
+        None => return Err(Error::GrammarError("Grammar has no rules".to_string())),
+        _ => {}
+    }
+    rules.retain(|(name, _)| generating.contains(name));
+    for (_, alternatives) in rules.iter_mut() {
+        alternatives.retain(|alternative| {
+            alternative
+                .terms
+                .iter()
+                .all(|term| nonterminal(term).is_none_or(|n| generating.contains(n)))
+        });
+    }
+
+    let mut reachable = vec![rules[0].0.to_string()];
+    let mut i = 0;
+    while i < reachable.len() {
+        if let Some((_, alternatives)) = rules.iter().find(|(n, _)| *n == reachable[i]) {
+            for alternative in alternatives {
+                for n in alternative.terms.iter().filter_map(nonterminal) {
+                    if !reachable.iter().any(|r| r == n) {
+                        reachable.push(n.to_string());
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    rules.retain(|(name, _)| reachable.contains(name));
+    Ok(finish(rules))
+}
+
+/// Chomsky normal form: every alternative is two nonterminals or a
+/// terminal. Empty strings, units and useless symbols are removed first,
+/// then terminals in longer alternatives get nonterminals of their own,
+/// named after them, e.g. `<"+">`, and the alternatives are split in two
+/// with helpers named after the rule, e.g. `<S.1>`.
+pub fn chomsky(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let (grammar, mapping) = remove_epsilon(grammar)?;
+    let (grammar, units) = remove_units(&grammar)?;
+    let (grammar, useless) = remove_useless(&grammar)?;
+    let mapping = mapping.then(units).then(useless);
+
+    let rules = identity(&grammar);
+    let mut taken = names(&rules);
+    let mut terminals: Vec<(Term, String)> = vec![];
+    let mut helpers: Rules = vec![];
+    let mut out: Rules = vec![];
+    for (name, alternatives) in &rules {
+        let mut count = 0;
+        let mut split = vec![];
+        for alternative in alternatives {
+            if alternative.terms.len() == 1 {
+                split.push(alternative.clone());
+                continue;
+            }
+            let terms: Vec<Term> = alternative
+                .terms
+                .iter()
+                .map(|term| match term {
+                    Term::Terminal(_) => {
+                        let lifted = match terminals.iter().find(|(t, _)| t == term) {
+                            Some((_, lifted)) => lifted.to_string(),
+                            None => {
+                                let lifted = fresh(&term.to_string(), &mut taken);
+                                terminals.push((term.clone(), lifted.to_string()));
+                                lifted
+                            }
+                        };
+                        Term::Nonterminal(lifted)
+                    }
+                    nonterminal => nonterminal.clone(),
+                })
+                .collect();
+
+            // `<A> ::= X0 <A.1>`, `<A.1> ::= X1 <A.2>`, ... with each helper
+            // spliced into the node of `<A>`.
+            let n = terms.len();
+            if n == 2 {
+                split.push(Alternative {
+                    terms,
+                    template: alternative.template.clone(),
+                });
+                continue;
+            }
+            let split_names: Vec<Term> = (0..n - 2)
+                .map(|_| {
+                    count += 1;
+                    Term::Nonterminal(fresh(&format!("{}.{}", name, count), &mut taken))
+                })
+                .collect();
+            split.push(Alternative {
+                terms: vec![terms[0].clone(), split_names[0].clone()],
+                template: vec![Fragment::Node(
+                    Term::Nonterminal(name.to_string()),
+                    vec![Fragment::Branch(0), Fragment::Branch(1)],
+                )],
+            });
+            for k in 0..n - 2 {
+                let second = match split_names.get(k + 1) {
+                    Some(next) => next.clone(),
+                    None => terms[n - 1].clone(),
+                };
+                helpers.push((
+                    nonterminal(&split_names[k]).unwrap().to_string(),
+                    vec![Alternative {
+                        terms: vec![terms[k + 1].clone(), second],
+                        template: vec![Fragment::Branch(0), Fragment::Branch(1)],
+                    }],
+                ));
+            }
+        }
+        out.push((name.to_string(), split));
+    }
+    out.extend(helpers);
+    for (terminal, lifted) in terminals {
+        out.push((
+            lifted,
+            vec![Alternative {
+                terms: vec![terminal],
+                template: vec![Fragment::Branch(0)],
+            }],
+        ));
+    }
+    let (grammar, cnf) = finish(out);
+    Ok((grammar, mapping.then(cnf)))
+}
+
+/// Whether some nonterminal derives itself through unit productions alone.
+fn has_unit_cycle(grammar: &Grammar) -> bool {
+    let rules = rules(grammar);
+    rules.iter().any(|(name, _)| {
+        let mut seen: Vec<&str> = vec![];
+        let mut stack: Vec<&str> = vec![name];
+        while let Some(current) = stack.pop() {
+            for (_, alternatives) in rules.iter().filter(|(n, _)| n == current) {
+                for terms in alternatives {
+                    if let [Term::Nonterminal(next)] = terms.as_slice() {
+                        if next == name {
+                            return true;
+                        }
+                        if !seen.contains(&next.as_str()) {
+                            seen.push(next);
+                            stack.push(next);
+                        }
+                    }
+                }
+            }
+        }
+        false
+    })
+}
+
+/// Paull's algorithm over `rules`, in order, with the helper for `<A>`
+/// named `<A'>`.
+fn paull(mut rules: Rules) -> Result<Rules, Error> {
+    let mut taken = names(&rules);
+    let mut helpers: Rules = vec![];
+    for i in 0..rules.len() {
+        for j in 0..i {
+            let (earlier, alternatives) = rules[j].clone();
+            let first = Term::Nonterminal(earlier);
+            let mut substituted = vec![];
+            for alternative in std::mem::take(&mut rules[i].1) {
+                if alternative.terms[0] == first {
+                    for replacement in &alternatives {
+                        substituted.push(alternative.substitute_first(replacement));
+                    }
+                } else {
+                    substituted.push(alternative);
+                }
+            }
+            rules[i].1 = substituted;
+        }
+
+        let name = rules[i].0.to_string();
+        let lhs = Term::Nonterminal(name.to_string());
+        let (recursive, base): (Vec<Alternative>, Vec<Alternative>) =
+            std::mem::take(&mut rules[i].1)
+                .into_iter()
+                .partition(|alternative| alternative.terms[0] == lhs);
+        if recursive.is_empty() {
+            rules[i].1 = base;
+            continue;
+        }
+        if base.is_empty() {
+            return Err(Error::GrammarError(format!(
+                "Every alternative of <{}> starts with <{}>",
+                name, name
+            )));
+        }
+
+        // `<A> ::= <A> a | b` becomes `<A> ::= b <A'> | b`, `<A'> ::= a <A'> | a`,
+        // where `<A'>` gets the node built so far from the left.
+        let helper = fresh(&format!("{}'", name), &mut taken);
+        let mut alternatives = vec![];
+        for alternative in base {
+            let mut terms = alternative.terms.clone();
+            terms.push(Term::Nonterminal(helper.to_string()));
+            alternatives.push(Alternative {
+                template: vec![Fragment::Threaded(
+                    alternative.terms.len(),
+                    vec![alternative.template.clone()],
+                )],
+                terms,
+            });
+            alternatives.push(alternative);
+        }
+        rules[i].1 = alternatives;
+
+        let mut tails = vec![];
+        for alternative in recursive.into_iter().filter(|a| a.terms.len() > 1) {
+            let template = reindex(&alternative.template, &|k| {
+                if k == 0 {
+                    Position::Left(0)
+                } else {
+                    Position::At(k - 1)
+                }
+            });
+            let tail = alternative.terms[1..].to_vec();
+            let mut terms = tail.clone();
+            terms.push(Term::Nonterminal(helper.to_string()));
+            tails.push(Alternative {
+                terms,
+                template: vec![Fragment::Threaded(tail.len(), vec![template.clone()])],
+            });
+            tails.push(Alternative {
+                terms: tail,
+                template,
+            });
+        }
+        helpers.push((helper, tails));
+    }
+    rules.extend(helpers);
+    Ok(rules)
+}
+
+/// Removes left recursion, direct and indirect, with Paull's algorithm:
+/// `<A> ::= <A> 'a' | 'b'` becomes `<A> ::= 'b' <A'> | 'b'` with
+/// `<A'> ::= 'a' <A'> | 'a'`. Translated trees lean left again.
+///
+/// Empty strings are removed first, and so are unit productions if they
+/// make a cycle.
+pub fn remove_left_recursion(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let (mut grammar, mut mapping) = remove_epsilon(grammar)?;
+    if has_unit_cycle(&grammar) {
+        let (without, units) = remove_units(&grammar)?;
+        grammar = without;
+        mapping = mapping.then(units);
+    }
+    let (grammar, step) = finish(paull(identity(&grammar))?);
+    Ok((grammar, mapping.then(step)))
+}
+
+/// The alternatives of `name` with leading nonterminals substituted until
+/// each starts with a terminal.
+fn leading_terminals(
+    name: &str,
+    rules: &Rules,
+    done: &mut HashMap<String, Vec<Alternative>>,
+    visiting: &mut Vec<String>,
+) -> Result<Vec<Alternative>, Error> {
+    if let Some(alternatives) = done.get(name) {
+        return Ok(alternatives.clone());
+    }
+    if visiting.iter().any(|v| v == name) {
+        return Err(Error::GrammarError(format!(
+            "<{}> is still left recursive",
+            name
+        )));
+    }
+    visiting.push(name.to_string());
+
+    let mut out = vec![];
+    let alternatives = rules
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, alternatives)| alternatives.clone())
+        .unwrap_or_default();
+    for alternative in alternatives {
+        match &alternative.terms[0] {
+            Term::Terminal(_) => out.push(alternative),
+            Term::Nonterminal(first) => {
+                for replacement in leading_terminals(first, rules, done, visiting)? {
+                    out.push(alternative.substitute_first(&replacement));
+                }
+            }
+        }
+    }
+
+    visiting.pop();
+    done.insert(name.to_string(), out.clone());
+    Ok(out)
+}
+
+/// Greibach normal form: every alternative is a terminal followed by
+/// nonterminals. Empty strings, units, useless symbols and left recursion
+/// are removed first, then leading nonterminals are substituted and the
+/// other terminals get nonterminals of their own, e.g. `<"+">`.
+pub fn greibach(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let (grammar, mapping) = remove_epsilon(grammar)?;
+    let (grammar, units) = remove_units(&grammar)?;
+    let (grammar, useless) = remove_useless(&grammar)?;
+    let (grammar, step) = finish(paull(identity(&grammar))?);
+    let mapping = mapping.then(units).then(useless).then(step);
+
+    let rules = identity(&grammar);
+    let mut taken = names(&rules);
+    let mut terminals: Vec<(Term, String)> = vec![];
+    let mut done = HashMap::new();
+    let mut out: Rules = vec![];
+    for (name, _) in &rules {
+        let mut alternatives = leading_terminals(name, &rules, &mut done, &mut vec![])?;
+        for alternative in alternatives.iter_mut() {
+            for term in alternative.terms.iter_mut().skip(1) {
+                if let Term::Terminal(_) = term {
+                    let lifted = match terminals.iter().find(|(t, _)| t == term) {
+                        Some((_, lifted)) => lifted.to_string(),
+                        None => {
+                            let lifted = fresh(&term.to_string(), &mut taken);
+                            terminals.push((term.clone(), lifted.to_string()));
+                            lifted
+                        }
+                    };
+                    *term = Term::Nonterminal(lifted);
+                }
+            }
+        }
+        out.push((name.to_string(), alternatives));
+    }
+    for (terminal, lifted) in terminals {
+        out.push((
+            lifted,
+            vec![Alternative {
+                terms: vec![terminal],
+                template: vec![Fragment::Branch(0)],
+            }],
+        ));
+    }
+    let (grammar, gnf) = finish(out);
+    let (grammar, useless) = remove_useless(&grammar)?;
+    Ok((grammar, mapping.then(gnf).then(useless)))
+}
+
+/// Left factors the grammar: alternatives sharing a prefix become one
+/// alternative, the prefix followed by a helper named after the rule, e.g.
+/// `<S'>`, for the rest. There are no empty alternatives, so an
+/// alternative that is the whole prefix stays as it is.
+pub fn left_factor(grammar: &Grammar) -> Result<(Grammar, Mapping), Error> {
+    let mut rules = identity(grammar);
+    // How many slots of branches each rule gets from the left: helpers get
+    // the prefixes they were factored out of.
+    let mut lefts = vec![0; rules.len()];
+    let mut taken = names(&rules);
+    let mut i = 0;
+    while i < rules.len() {
+        while let Some((group, prefix)) = common_prefix(&rules[i].1) {
+            let helper = fresh(&format!("{}'", rules[i].0), &mut taken);
+            let left = lefts[i];
+            let mut kept = vec![];
+            let mut rests = vec![];
+            for (j, alternative) in std::mem::take(&mut rules[i].1).into_iter().enumerate() {
+                if !group.contains(&j) || alternative.terms.len() == prefix {
+                    kept.push(alternative);
+                    continue;
+                }
+                if rests.is_empty() {
+                    let mut terms = alternative.terms[..prefix].to_vec();
+                    terms.push(Term::Nonterminal(helper.to_string()));
+                    let slots = (0..left)
+                        .map(|k| vec![Fragment::Left(k)])
+                        .chain((0..prefix).map(|k| vec![Fragment::Branch(k)]))
+                        .collect();
+                    kept.push(Alternative {
+                        terms,
+                        template: vec![Fragment::Threaded(prefix, slots)],
+                    });
+                }
+                let template = reindex(&alternative.template, &|k| {
+                    if k < prefix {
+                        Position::Left(left + k)
+                    } else {
+                        Position::At(k - prefix)
+                    }
+                });
+                rests.push(Alternative {
+                    terms: alternative.terms[prefix..].to_vec(),
+                    template,
+                });
+            }
+            rules[i].1 = kept;
+            rules.push((helper, rests));
+            lefts.push(left + prefix);
+        }
+        i += 1;
+    }
+    Ok(finish(rules))
+}
+
+/// The first group of alternatives with the same first term and at least
+/// two that are longer than their common prefix, and the prefix's length.
+fn common_prefix(alternatives: &[Alternative]) -> Option<(Vec<usize>, usize)> {
+    alternatives.iter().find_map(|alternative| {
+        let group: Vec<usize> = (0..alternatives.len())
+            .filter(|&j| alternatives[j].terms[0] == alternative.terms[0])
+            .collect();
+        let prefix = group
+            .iter()
+            .map(|&j| {
+                alternative
+                    .terms
+                    .iter()
+                    .zip(&alternatives[j].terms)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            })
+            .min()
+            .unwrap();
+        let longer = group
+            .iter()
+            .filter(|&&j| alternatives[j].terms.len() > prefix)
+            .count();
+        if longer >= 2 {
+            Some((group, prefix))
+        } else {
+            None
+        }
+    })
+}
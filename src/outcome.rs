@@ -1,14 +1,21 @@
+use crate::action::Actions;
 use crate::error::Error;
+use crate::forest::Forest;
 use crate::istate::{FlippedIState, IState};
-use crate::itree::{IBranch, ITree};
+use crate::kbest::KBest;
+use crate::layout::Layout;
+use crate::sample::Sampler;
+use crate::shape::Shape;
+use crate::token::{TokenizerInfo, Trivia};
 use crate::tree::Tree;
-use bnf::Term;
+use bnf::Production;
 use linked_hash_set::LinkedHashSet;
+use rand::Rng;
 use std::fmt;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum EarleyOutcome {
-    Accepted(EarleyAccepted),
+    Accepted(Box<EarleyAccepted>),
     Rejected,
 }
 
@@ -17,6 +24,9 @@ pub struct EarleyAccepted {
     pub chart: Vec<LinkedHashSet<IState>>,
     pub accepted_states: Vec<IState>,
     pub input: Vec<String>,
+    pub tokenizer_info: Option<TokenizerInfo>,
+    pub trivia: Option<Trivia>,
+    pub layout: Option<Layout>,
 }
 
 impl EarleyAccepted {
@@ -29,136 +39,114 @@ impl EarleyAccepted {
             chart,
             accepted_states,
             input,
+            tokenizer_info: None,
+            trivia: None,
+            layout: None,
         }
     }
 
-    fn find_in_chart(
-        &self,
-        parent_state: &FlippedIState,
-        term: &Term,
-        i: usize,
-        limit: Option<usize>,
-        chart: &[LinkedHashSet<FlippedIState>],
-    ) -> Vec<FlippedIState> {
-        let mut candidates = vec![];
-        if let Some(state_set) = chart.get(i) {
-            for state in state_set {
-                if (parent_state != state) && term == &state.prod.lhs {
-                    if let Some(l) = limit {
-                        if state.end < l {
-                            candidates.push(state.clone())
-                        }
-                    } else {
-                        candidates.push(state.clone())
-                    }
-                }
-            }
-        }
-
-        candidates
+    /// Records where `input` came from so trees can be printed back to it.
+    pub fn with_tokenizer_info(mut self, tokenizer_info: TokenizerInfo) -> EarleyAccepted {
+        self.tokenizer_info = Some(tokenizer_info);
+        self
     }
 
-    fn construct(
-        &self,
-        x: usize,
-        state: &FlippedIState,
-        chart: &[LinkedHashSet<FlippedIState>],
-    ) -> ITree {
-        let mut tree = ITree {
-            root: state.clone(),
-            branches: vec![],
-        };
+    /// Records how the input was split around trivia by a lossless parse.
+    pub fn with_trivia(mut self, trivia: Trivia) -> EarleyAccepted {
+        self.trivia = Some(trivia);
+        self
+    }
 
-        let mut idxs: Vec<usize> = vec![x];
+    /// Records the layout the parse let through, which the parse forest
+    /// has to skip over the same way.
+    pub fn with_layout(mut self, layout: Layout) -> EarleyAccepted {
+        self.layout = Some(layout);
+        self
+    }
 
-        let mut terms = state.prod.rhs.iter();
-        let mut term_opt = terms.next();
+    /// Every tree of the parse forest as a lossless concrete syntax tree,
+    /// its nodes recording the whitespace and comments around their
+    /// leaves so that `Tree::to_source` gives back the input byte for byte.
+    /// Without a lossless parse the `split_on` separators are the only
+    /// trivia.
+    pub fn lossless_parse_forest(&self) -> Result<Vec<Tree>, Error> {
+        let tokenizer_info = self.tokenizer_info.as_ref().ok_or_else(|| {
+            Error::ParseForestError("No record of how the input was tokenized".to_string())
+        })?;
+        let trivia = self.trivia.clone().unwrap_or_default();
+        self.parse_forest()?
+            .iter()
+            .map(|tree| tree.with_trivia(tokenizer_info, &trivia))
+            .collect()
+    }
 
-        let mut candidates: Vec<(usize, IBranch)> = vec![];
-        let mut success_indexes: Vec<usize> = vec![];
-        let mut next_idxs: Vec<usize> = vec![];
+    /// Every tree of the parse forest. Fails with a `ParseForestError`
+    /// rather than recursing forever when the grammar's cycles give the
+    /// input infinitely many trees; see `Forest` for inspecting those.
+    pub fn parse_forest(&self) -> Result<Vec<Tree>, Error> {
+        if self.chart.is_empty() {
+            return Err(Error::ParseForestError(
+                "Couldn't a start state candidate!".to_string(),
+            ));
+        }
 
-        while let Some(term) = term_opt {
-            let mut limit: Option<usize> = None;
-            if let Some(n) = terms.clone().peekable().peek() {
-                match n {
-                    Term::Terminal(_) => limit = Some(self.input.len()),
-                    Term::Nonterminal(_) => limit = Some(chart.len()),
-                }
-            }
+        self.forest().trees()
+    }
 
-            term_opt = terms.next();
-            for idx in idxs {
-                match term {
-                    Term::Nonterminal(_) => {
-                        let res = self.find_in_chart(state, term, idx, limit, chart);
-                        for s in res {
-                            let new_branch =
-                                IBranch::Nonterminal(idx, self.construct(idx, &s, chart));
-                            let insert_at = if term_opt.is_none() {
-                                idx
-                            } else {
-                                next_idxs.push(s.end);
-                                s.end
-                            };
-                            let _ = candidates.push((insert_at, new_branch));
-                            success_indexes.push(idx);
-                        }
-                    }
-                    Term::Terminal(symbol) => {
-                        if let Some(found) = self.input.get(idx) {
-                            if found == symbol {
-                                let new_branch = IBranch::Terminal(idx, symbol.to_string());
-                                tree.branches.push(new_branch);
-                                success_indexes.push(idx);
-                                next_idxs.push(idx + 1);
-                            }
-                        }
-                    }
-                }
-            }
-            idxs = next_idxs.clone();
-        }
+    /// Every tree of the parse forest, shaped by `shape` into a more compact
+    /// concrete syntax tree.
+    pub fn shaped_parse_forest(&self, shape: &Shape) -> Result<Vec<Tree>, Error> {
+        self.forest().shaped_trees(shape)
+    }
 
-        for idx in success_indexes {
-            tree.branches.append(
-                &mut candidates
-                    .iter()
-                    .filter(|(i, _)| *i == idx)
-                    .map(|(_, b)| b)
-                    .cloned()
-                    .collect(),
-            );
-        }
+    /// The semantic value of every tree in the parse forest, built by
+    /// `actions` as the forest is walked.
+    pub fn evaluate<T: Clone>(&self, actions: &Actions<T>) -> Result<Vec<T>, Error> {
+        actions.evaluate(&self.forest())
+    }
 
-        tree.branches.dedup();
-        tree.order();
-        tree
+    pub fn forest(&self) -> Forest {
+        Forest::new(self)
     }
 
-    pub fn parse_forest(&self) -> Result<Vec<Tree>, Error> {
-        let flipped_chart = self.flip_completed();
-        let flipped_start_states: Vec<FlippedIState>;
-        if let Some(inital) = flipped_chart.get(0) {
-            flipped_start_states = inital
-                .iter()
-                .filter(|s| s.end == flipped_chart.len() - 1)
-                .cloned()
-                .collect();
-        } else {
+    fn acyclic_forest(&self) -> Result<Forest, Error> {
+        let forest = self.forest();
+        if forest.is_cyclic() {
             return Err(Error::ParseForestError(
-                "Couldn't a start state candidate!".to_string(),
+                "Parse forest has cycles, its trees can't be ranked or sampled".to_string(),
             ));
         }
+        Ok(forest)
+    }
 
-        let mut itrees: Vec<ITree> = vec![];
-        for state in flipped_start_states {
-            itrees.push(self.construct(0, &state, &flipped_chart));
-        }
+    /// The `k` highest scoring trees of the parse forest, best first, where a
+    /// tree scores the sum of `score` over the productions of its nodes.
+    pub fn k_best<F>(&self, k: usize, score: F) -> Result<Vec<(f64, Tree)>, Error>
+    where
+        F: Fn(&Production) -> f64,
+    {
+        let forest = self.acyclic_forest()?;
+        Ok(KBest::new(&forest, score).trees(k))
+    }
 
-        let trees: Vec<Tree> = itrees.iter().map(|t| t.to_tree()).collect();
+    /// Draws one tree uniformly at random from the parse forest.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Tree, Error> {
+        self.sample_weighted(rng, |_| 1.0)
+    }
 
-        Ok(trees)
+    /// Draws one tree from the parse forest with probability proportional to
+    /// the product of `weight` over the productions of its nodes.
+    pub fn sample_weighted<R, F>(&self, rng: &mut R, weight: F) -> Result<Tree, Error>
+    where
+        R: Rng + ?Sized,
+        F: Fn(&Production) -> f64,
+    {
+        let forest = self.acyclic_forest()?;
+        Sampler::weighted(&forest, weight)
+            .sample(rng)
+            .ok_or_else(|| {
+                Error::ParseForestError("Every tree in the forest has a weight of zero".to_string())
+            })
     }
 
     pub fn flip_completed(&self) -> Vec<LinkedHashSet<FlippedIState>> {
@@ -0,0 +1,300 @@
+use crate::error::Error;
+use bnf::{Expression, Grammar, Production, Term};
+use std::collections::HashMap;
+
+/// How deeply instances may nest, e.g. `<f(f(f(x)))>`, before a rule like
+/// `<f(X)> ::= <f(f(X))>` is taken to expand forever.
+const MAX_DEPTH: usize = 32;
+
+/// A nonterminal as written, `name` applied to `args`, none for a plain
+/// nonterminal, or a terminal given as an argument.
+#[derive(Clone, Debug, PartialEq)]
+enum Symbol {
+    Nonterminal(String, Vec<Symbol>),
+    Terminal(String),
+}
+
+impl Symbol {
+    /// The name the symbol has in the expanded grammar, e.g.
+    /// `list(expr, ",")`.
+    fn name(&self) -> String {
+        match self {
+            Symbol::Nonterminal(name, args) if args.is_empty() => name.to_string(),
+            Symbol::Nonterminal(name, args) => {
+                let args: Vec<String> = args.iter().map(Symbol::name).collect();
+                format!("{}({})", name, args.join(", "))
+            }
+            Symbol::Terminal(t) => Term::Terminal(t.to_string()).to_string(),
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match self {
+            Symbol::Nonterminal(_, args) => 1 + args.iter().map(Symbol::depth).max().unwrap_or(0),
+            Symbol::Terminal(_) => 0,
+        }
+    }
+
+    /// The symbol with the parameters in `env` replaced by their arguments.
+    fn substitute(&self, env: &HashMap<String, Symbol>) -> Result<Symbol, String> {
+        match self {
+            Symbol::Nonterminal(name, args) => match env.get(name) {
+                Some(arg) if args.is_empty() => Ok(arg.clone()),
+                Some(_) => Err(format!("parameter {} can't take arguments", name)),
+                None => Ok(Symbol::Nonterminal(
+                    name.to_string(),
+                    args.iter()
+                        .map(|arg| arg.substitute(env))
+                        .collect::<Result<_, _>>()?,
+                )),
+            },
+            terminal => Ok(terminal.clone()),
+        }
+    }
+}
+
+/// Parses a nonterminal name, `name` or `name(arg, ...)`, where each
+/// argument is a quoted terminal or a nonterminal written the same way.
+fn parse(text: &str) -> Result<Symbol, String> {
+    let text = text.trim();
+    let invalid = || format!("invalid nonterminal <{}>", text);
+    let open = match text.find('(') {
+        Some(open) => open,
+        None => return Ok(Symbol::Nonterminal(text.to_string(), vec![])),
+    };
+    let name = text[..open].trim();
+    if name.is_empty() || !text.ends_with(')') {
+        return Err(invalid());
+    }
+
+    let mut args = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut start = open + 1;
+    let inner_end = text.len() - 1;
+    for (i, c) in text.char_indices().skip_while(|(i, _)| *i <= open) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth > 0 => depth -= 1,
+            (None, ')') if i != inner_end => return Err(invalid()),
+            (None, ',') | (None, ')') if depth == 0 => {
+                args.push(parse_arg(&text[start..i]).ok_or_else(invalid)??);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() || depth > 0 {
+        return Err(invalid());
+    }
+    Ok(Symbol::Nonterminal(name.to_string(), args))
+}
+
+fn parse_arg(text: &str) -> Option<Result<Symbol, String>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    for q in ['\'', '"'] {
+        if text.len() >= 2 && text.starts_with(q) && text.ends_with(q) {
+            return Some(Ok(Symbol::Terminal(text[1..text.len() - 1].to_string())));
+        }
+    }
+    Some(parse(text))
+}
+
+struct Template {
+    params: Vec<String>,
+    alternatives: Vec<Vec<Term>>,
+}
+
+/// Expands the parameterized rules of `grammar` into plain ones. A rule
+/// like
+///
+/// ```text
+/// <list(X, sep)> ::= <X> | <X> <sep> <list(X, sep)>
+/// ```
+///
+/// is a template, and every use of it such as `<list(expr, ',')>` gets
+/// a copy of its productions with the arguments in place of the
+/// parameters, named `<list(expr, ",")>`, the name the nodes for it have
+/// in parse trees (see `instance`). Arguments are nonterminals, quoted
+/// terminals, or uses of templates themselves, and can't contain `>`.
+///
+/// Since no alternative can be empty, an optional `X` is written as a
+/// template taking what follows it too, e.g.
+/// `<opt(X, Y)> ::= <X> <Y> | <Y>`.
+///
+/// The templates are dropped from the grammar, and the first plain rule is
+/// the start rule.
+pub fn expand(grammar: &Grammar) -> Result<Grammar, Error> {
+    let error = |msg: String| Error::ParamError(msg);
+
+    let mut templates: HashMap<String, Template> = HashMap::new();
+    let mut productions: Vec<Production> = vec![];
+    for production in grammar.productions_iter() {
+        let lhs = match &production.lhs {
+            Term::Nonterminal(name) => parse(name).map_err(error)?,
+            Term::Terminal(_) => {
+                productions.push(production.clone());
+                continue;
+            }
+        };
+        let (name, params) = match lhs {
+            Symbol::Nonterminal(name, params) if !params.is_empty() => (name, params),
+            _ => {
+                productions.push(production.clone());
+                continue;
+            }
+        };
+
+        let params: Vec<String> = params
+            .into_iter()
+            .map(|param| match param {
+                Symbol::Nonterminal(param, args) if args.is_empty() => Ok(param),
+                param => Err(error(format!(
+                    "parameter {} of <{}> isn't a name",
+                    param.name(),
+                    name
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+        if let Some(i) = (1..params.len()).find(|&i| params[..i].contains(&params[i])) {
+            return Err(error(format!(
+                "parameter {} of <{}> is given twice",
+                params[i], name
+            )));
+        }
+        let alternatives: Vec<Vec<Term>> = production
+            .rhs_iter()
+            .map(|expr| expr.terms_iter().cloned().collect())
+            .collect();
+        match templates.get_mut(&name) {
+            Some(template) if template.params.len() != params.len() => {
+                return Err(error(format!(
+                    "<{}> is defined with {} and {} parameters",
+                    name,
+                    template.params.len(),
+                    params.len()
+                )))
+            }
+            Some(template) => template.alternatives.extend(alternatives),
+            None => {
+                templates.insert(
+                    name,
+                    Template {
+                        params,
+                        alternatives,
+                    },
+                );
+            }
+        }
+    }
+    if productions.is_empty() {
+        return Err(error("Grammar has no rules besides templates".to_string()));
+    }
+
+    // Instantiates templates as they're used, in the order they're first
+    // used, appending their productions.
+    let mut instances: Vec<Symbol> = vec![];
+    let resolve = |term: &Term,
+                   env: &HashMap<String, Symbol>,
+                   instances: &mut Vec<Symbol>|
+     -> Result<Term, String> {
+        let name = match term {
+            Term::Nonterminal(name) => name,
+            terminal => return Ok(terminal.clone()),
+        };
+        let symbol = parse(name)?.substitute(env)?;
+        match &symbol {
+            Symbol::Terminal(t) => Ok(Term::Terminal(t.to_string())),
+            Symbol::Nonterminal(name, args) => {
+                match templates.get(name).map(|t| t.params.len()) {
+                    Some(arity) if arity != args.len() => {
+                        return Err(format!(
+                            "<{}> takes {} arguments, not {}",
+                            name,
+                            arity,
+                            args.len()
+                        ))
+                    }
+                    None if !args.is_empty() => {
+                        return Err(format!("no parameterized rule <{}>", name))
+                    }
+                    _ => {}
+                }
+                if symbol.depth() > MAX_DEPTH {
+                    return Err(format!("expanding <{}> doesn't end", name));
+                }
+                if !args.is_empty() && !instances.contains(&symbol) {
+                    instances.push(symbol.clone());
+                }
+                Ok(Term::Nonterminal(symbol.name()))
+            }
+        }
+    };
+    let rewrite = |alternatives: &mut dyn Iterator<Item = Vec<Term>>,
+                   env: &HashMap<String, Symbol>,
+                   instances: &mut Vec<Symbol>|
+     -> Result<Vec<Expression>, String> {
+        alternatives
+            .map(|terms| {
+                let terms = terms
+                    .iter()
+                    .map(|term| resolve(term, env, instances))
+                    .collect::<Result<_, _>>()?;
+                Ok(Expression::from_parts(terms))
+            })
+            .collect()
+    };
+
+    let mut expanded: Vec<Production> = vec![];
+    for production in &productions {
+        let mut alternatives = production
+            .rhs_iter()
+            .map(|expr| expr.terms_iter().cloned().collect());
+        let rhs = rewrite(&mut alternatives, &HashMap::new(), &mut instances)
+            .map_err(|msg| error(format!("{}: {}", production.lhs, msg)))?;
+        expanded.push(Production::from_parts(production.lhs.clone(), rhs));
+    }
+    let mut i = 0;
+    while i < instances.len() {
+        let instance = instances[i].clone();
+        if let Symbol::Nonterminal(name, args) = &instance {
+            let template = &templates[name];
+            let env: HashMap<String, Symbol> = template
+                .params
+                .iter()
+                .cloned()
+                .zip(args.iter().cloned())
+                .collect();
+            let mut alternatives = template.alternatives.iter().cloned();
+            let rhs = rewrite(&mut alternatives, &env, &mut instances)
+                .map_err(|msg| error(format!("<{}>: {}", instance.name(), msg)))?;
+            expanded.push(Production::from_parts(
+                Term::Nonterminal(instance.name()),
+                rhs,
+            ));
+        }
+        i += 1;
+    }
+    Ok(Grammar::from_parts(expanded))
+}
+
+/// The template a nonterminal of an expanded grammar is an instance of,
+/// and the names of its arguments, e.g. `("list", ["expr", "\",\""])` for
+/// `<list(expr, ",")>`, or `None` for a plain nonterminal.
+pub fn instance(nonterminal: &Term) -> Option<(String, Vec<String>)> {
+    match nonterminal {
+        Term::Nonterminal(name) => match parse(name) {
+            Ok(Symbol::Nonterminal(name, args)) if !args.is_empty() => {
+                Some((name, args.iter().map(Symbol::name).collect()))
+            }
+            _ => None,
+        },
+        Term::Terminal(_) => None,
+    }
+}
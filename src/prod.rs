@@ -1,4 +1,4 @@
-use bnf::Term;
+use bnf::{Expression, Production, Term};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -16,6 +16,12 @@ impl EarleyProd {
     pub fn get_next(&self) -> Option<&Term> {
         self.rhs.get(self.dot)
     }
+    pub fn to_production(&self) -> Production {
+        Production::from_parts(
+            self.lhs.clone(),
+            vec![Expression::from_parts(self.rhs.clone())],
+        )
+    }
 }
 
 impl fmt::Display for EarleyProd {
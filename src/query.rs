@@ -0,0 +1,509 @@
+use crate::error::Error;
+use crate::tree::{Branch, Tree};
+use crate::visit::NodeRef;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A compiled tree query, a small XPath-like path over nonterminal names.
+///
+/// A query is a sequence of steps, each introduced by an axis: `/` for a
+/// child of the current node and `//` for any descendant. A query starting
+/// with `/` begins at the root and one starting with `//` (or no axis at
+/// all) anywhere in the tree. A step tests the node it lands on:
+///
+/// * `NP` or `<NP>` is a nonterminal by name, `<noun phrase>` when the
+///   name has spaces in it, and `*` is any nonterminal;
+/// * `"their"` or `'their'` is a terminal leaf with that text;
+/// * `.` stays on the current node.
+///
+/// A step can be followed by predicates in brackets, each a relative path
+/// that has to lead somewhere from the node, optionally ending in `= "text"`
+/// to also require the text of the node it leads to (its leaves, joined) to
+/// match, and finally by a `@name` capture. So the `<NP>`s whose `<D>` child
+/// is "their" are
+///
+/// ```text
+/// //NP[D = "their"]
+/// ```
+///
+/// and `//VP@verb_phrase/NP[D@det = "their"]@object` captures the verb
+/// phrase and determiner of each such `<NP>` that is the object of a `<VP>`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Query {
+    path: Vec<Step>,
+}
+
+/// A node picked out by a query, with the path of branch indices leading to
+/// it from the root and the range of input tokens under it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Capture<'a> {
+    pub node: NodeRef<'a>,
+    pub path: Vec<usize>,
+    pub tokens: Range<usize>,
+}
+
+/// One way a query matched: the node its last step landed on and whatever
+/// its `@name`s captured along the way, in query order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryMatch<'a> {
+    pub node: Capture<'a>,
+    pub captures: Vec<(String, Capture<'a>)>,
+}
+
+impl<'a> QueryMatch<'a> {
+    pub fn get(&self, name: &str) -> Option<&Capture<'a>> {
+        self.captures
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| c)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Test {
+    Nonterminal(String),
+    AnyNonterminal,
+    Terminal(String),
+    Current,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Step {
+    axis: Axis,
+    test: Test,
+    predicates: Vec<Predicate>,
+    capture: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Predicate {
+    path: Vec<Step>,
+    text: Option<String>,
+}
+
+impl Query {
+    pub fn new(query: &str) -> Result<Query, Error> {
+        let mut parser = QueryParser {
+            query,
+            tokens: lex(query)?,
+            i: 0,
+        };
+        let axis = parser.axis().unwrap_or(Axis::Descendant);
+        let path = parser.path(axis)?;
+        if let Some((at, _)) = parser.tokens.get(parser.i) {
+            return Err(parser.error(*at, "expected `/`, `//` or the end of the query"));
+        }
+        Ok(Query { path })
+    }
+
+    /// Every match of the query in `tree`, in the order of the nodes the
+    /// matches end on.
+    pub fn matches<'a>(&self, tree: &'a Tree) -> Vec<QueryMatch<'a>> {
+        let index = Index::new(tree);
+        let mut results = index.eval(&self.path, None);
+        results.sort_by_key(|(node, _)| *node);
+
+        results
+            .into_iter()
+            .map(|(node, bindings)| QueryMatch {
+                node: index.capture(node),
+                captures: bindings
+                    .into_iter()
+                    .map(|(name, node)| (name, index.capture(node)))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl Tree {
+    /// Runs a `Query` over this tree, see there for the syntax.
+    pub fn query(&self, query: &str) -> Result<Vec<QueryMatch<'_>>, Error> {
+        Ok(Query::new(query)?.matches(self))
+    }
+}
+
+/// Capture names bound to entries of an `Index`.
+type Bindings = Vec<(String, usize)>;
+
+/// The tree's nodes in preorder, so the descendants of an entry are the
+/// entries right after it, up to its `end`.
+struct Index<'a> {
+    entries: Vec<Entry<'a>>,
+    leaves: Vec<&'a str>,
+}
+
+struct Entry<'a> {
+    node: NodeRef<'a>,
+    path: Vec<usize>,
+    tokens: Range<usize>,
+    children: Vec<usize>,
+    end: usize,
+}
+
+impl<'a> Index<'a> {
+    fn new(tree: &'a Tree) -> Index<'a> {
+        let mut index = Index {
+            entries: vec![],
+            leaves: vec![],
+        };
+        index.add(NodeRef::Nonterminal(tree), vec![]);
+        index
+    }
+
+    fn add(&mut self, node: NodeRef<'a>, path: Vec<usize>) -> usize {
+        let id = self.entries.len();
+        let start = self.leaves.len();
+        self.entries.push(Entry {
+            node,
+            path: path.clone(),
+            tokens: start..start,
+            children: vec![],
+            end: id + 1,
+        });
+
+        match node {
+            NodeRef::Nonterminal(tree) => {
+                for (i, branch) in tree.branches.iter().enumerate() {
+                    let mut child_path = path.clone();
+                    child_path.push(i);
+                    let child = match branch {
+                        Branch::Nonterminal(t) => NodeRef::Nonterminal(t),
+                        Branch::Terminal(s) => NodeRef::Terminal(s),
+                    };
+                    let child = self.add(child, child_path);
+                    self.entries[id].children.push(child);
+                }
+            }
+            NodeRef::Terminal(s) => self.leaves.push(s),
+        }
+
+        let end = self.entries.len();
+        let tokens = start..self.leaves.len();
+        let entry = &mut self.entries[id];
+        entry.end = end;
+        entry.tokens = tokens;
+        id
+    }
+
+    fn capture(&self, id: usize) -> Capture<'a> {
+        let entry = &self.entries[id];
+        Capture {
+            node: entry.node,
+            path: entry.path.clone(),
+            tokens: entry.tokens.clone(),
+        }
+    }
+
+    fn text(&self, id: usize) -> String {
+        self.leaves[self.entries[id].tokens.clone()].concat()
+    }
+
+    /// The nodes `axis` leads to from `context`, where no context stands
+    /// for the document above the root.
+    fn axis(&self, context: Option<usize>, axis: Axis) -> Vec<usize> {
+        match (context, axis) {
+            (None, Axis::Child) => vec![0],
+            (None, Axis::Descendant) => (0..self.entries.len()).collect(),
+            (Some(c), Axis::Child) => self.entries[c].children.clone(),
+            (Some(c), Axis::Descendant) => (c + 1..self.entries[c].end).collect(),
+        }
+    }
+
+    fn test(&self, id: usize, test: &Test) -> bool {
+        match (&self.entries[id].node, test) {
+            (NodeRef::Nonterminal(tree), Test::Nonterminal(name)) => tree.name() == name,
+            (NodeRef::Nonterminal(_), Test::AnyNonterminal) => true,
+            (NodeRef::Terminal(s), Test::Terminal(text)) => s == text,
+            _ => false,
+        }
+    }
+
+    /// The nodes `path` leads to from `context`, each with the captures
+    /// made on the way; a node reached along differently captured ways
+    /// comes up once for each.
+    fn eval(&self, path: &[Step], context: Option<usize>) -> Vec<(usize, Bindings)> {
+        let mut current: Vec<(Option<usize>, Bindings)> = vec![(context, vec![])];
+        for step in path {
+            let mut seen = HashSet::new();
+            let mut next = vec![];
+            for (context, bindings) in current {
+                let candidates: Vec<usize> = match step.test {
+                    Test::Current => context.into_iter().collect(),
+                    _ => self
+                        .axis(context, step.axis)
+                        .into_iter()
+                        .filter(|c| self.test(*c, &step.test))
+                        .collect(),
+                };
+
+                for candidate in candidates {
+                    let mut options = vec![bindings.clone()];
+                    for predicate in &step.predicates {
+                        let found: Vec<Bindings> = self
+                            .eval(&predicate.path, Some(candidate))
+                            .into_iter()
+                            .filter(|(node, _)| match &predicate.text {
+                                Some(text) => self.text(*node) == *text,
+                                None => true,
+                            })
+                            .map(|(_, b)| b)
+                            .collect();
+                        options = options
+                            .iter()
+                            .flat_map(|option| {
+                                found.iter().map(move |b| {
+                                    let mut option = option.clone();
+                                    option.extend(b.iter().cloned());
+                                    option
+                                })
+                            })
+                            .collect();
+                    }
+
+                    for mut option in options {
+                        if let Some(name) = &step.capture {
+                            option.push((name.clone(), candidate));
+                        }
+                        if seen.insert((candidate, option.clone())) {
+                            next.push((Some(candidate), option));
+                        }
+                    }
+                }
+            }
+            current = next;
+        }
+
+        current
+            .into_iter()
+            .filter_map(|(node, bindings)| node.map(|n| (n, bindings)))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    Open,
+    Close,
+    Equals,
+    Star,
+    Dot,
+    Name(String),
+    Text(String),
+    Capture(String),
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Splits a query into tokens, each with the byte offset it starts at.
+fn lex(query: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let error =
+        |at: usize, msg: &str| Error::QueryError(format!("{} at {} in query {:?}", msg, at, query));
+
+    let mut tokens = vec![];
+    let mut chars = query.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '/' => {
+                if let Some((_, '/')) = chars.peek() {
+                    chars.next();
+                    Token::DoubleSlash
+                } else {
+                    Token::Slash
+                }
+            }
+            '[' => Token::Open,
+            ']' => Token::Close,
+            '=' => Token::Equals,
+            '*' => Token::Star,
+            '.' => Token::Dot,
+            '<' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '>')) => break,
+                        Some((_, c)) => name.push(c),
+                        None => return Err(error(at, "unclosed `<`")),
+                    }
+                }
+                if name.is_empty() {
+                    return Err(error(at, "empty nonterminal name"));
+                }
+                Token::Name(name)
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err(error(at, "unclosed string")),
+                        },
+                        Some((_, q)) if q == c => break,
+                        Some((_, other)) => text.push(other),
+                        None => return Err(error(at, "unclosed string")),
+                    }
+                }
+                Token::Text(text)
+            }
+            '@' => {
+                let mut name = String::new();
+                while let Some((_, c)) = chars.peek() {
+                    if !is_name_char(*c) {
+                        break;
+                    }
+                    name.push(*c);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    return Err(error(at, "expected a capture name after `@`"));
+                }
+                Token::Capture(name)
+            }
+            c if is_name_char(c) => {
+                let mut name = c.to_string();
+                while let Some((_, c)) = chars.peek() {
+                    if !is_name_char(*c) {
+                        break;
+                    }
+                    name.push(*c);
+                    chars.next();
+                }
+                Token::Name(name)
+            }
+            c => return Err(error(at, &format!("unexpected {:?}", c))),
+        };
+        tokens.push((at, token));
+    }
+
+    Ok(tokens)
+}
+
+struct QueryParser<'q> {
+    query: &'q str,
+    tokens: Vec<(usize, Token)>,
+    i: usize,
+}
+
+impl<'q> QueryParser<'q> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.i).map(|(_, t)| t)
+    }
+
+    fn at(&self) -> usize {
+        self.tokens
+            .get(self.i)
+            .map_or(self.query.len(), |(at, _)| *at)
+    }
+
+    fn error(&self, at: usize, msg: &str) -> Error {
+        Error::QueryError(format!("{} at {} in query {:?}", msg, at, self.query))
+    }
+
+    fn axis(&mut self) -> Option<Axis> {
+        let axis = match self.peek() {
+            Some(Token::Slash) => Axis::Child,
+            Some(Token::DoubleSlash) => Axis::Descendant,
+            _ => return None,
+        };
+        self.i += 1;
+        Some(axis)
+    }
+
+    /// Steps separated by axes, the first one taking `axis`.
+    fn path(&mut self, axis: Axis) -> Result<Vec<Step>, Error> {
+        let mut steps = vec![self.step(axis)?];
+        while let Some(axis) = self.axis() {
+            steps.push(self.step(axis)?);
+        }
+        Ok(steps)
+    }
+
+    fn step(&mut self, axis: Axis) -> Result<Step, Error> {
+        let at = self.at();
+        let test = match self.peek() {
+            Some(Token::Name(name)) => Test::Nonterminal(name.clone()),
+            Some(Token::Star) => Test::AnyNonterminal,
+            Some(Token::Text(text)) => Test::Terminal(text.clone()),
+            Some(Token::Dot) => Test::Current,
+            _ => {
+                return Err(self.error(
+                    at,
+                    "expected a nonterminal name, `*`, a terminal string or `.`",
+                ))
+            }
+        };
+        self.i += 1;
+
+        let mut predicates = vec![];
+        while let Some(Token::Open) = self.peek() {
+            self.i += 1;
+            predicates.push(self.predicate()?);
+        }
+
+        let capture = match self.peek() {
+            Some(Token::Capture(name)) => {
+                let name = name.clone();
+                self.i += 1;
+                Some(name)
+            }
+            _ => None,
+        };
+
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+            capture,
+        })
+    }
+
+    fn predicate(&mut self) -> Result<Predicate, Error> {
+        let path = match self.peek() {
+            Some(Token::Equals) => vec![Step {
+                axis: Axis::Child,
+                test: Test::Current,
+                predicates: vec![],
+                capture: None,
+            }],
+            _ => {
+                let axis = self.axis().unwrap_or(Axis::Child);
+                self.path(axis)?
+            }
+        };
+
+        let text = match self.peek() {
+            Some(Token::Equals) => {
+                self.i += 1;
+                match self.peek() {
+                    Some(Token::Text(text)) => {
+                        let text = text.clone();
+                        self.i += 1;
+                        Some(text)
+                    }
+                    _ => return Err(self.error(self.at(), "expected a string after `=`")),
+                }
+            }
+            _ => None,
+        };
+
+        match self.peek() {
+            Some(Token::Close) => {
+                self.i += 1;
+                Ok(Predicate { path, text })
+            }
+            _ => Err(self.error(self.at(), "expected `]`")),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::error::Error;
+use crate::istate::IState;
+use bnf::{Grammar, Term};
+
+/// A set of input tokens: tokens listed one by one, and single char tokens
+/// within ranges like `[a-z]`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TokenSet {
+    tokens: Vec<String>,
+    ranges: Vec<(char, char)>,
+}
+
+impl TokenSet {
+    pub fn new() -> TokenSet {
+        TokenSet::default()
+    }
+
+    pub fn token(mut self, token: &str) -> TokenSet {
+        self.tokens.push(token.to_string());
+        self
+    }
+
+    /// Every char token from `from` to `to`, both included.
+    pub fn range(mut self, from: char, to: char) -> TokenSet {
+        self.ranges.push((from, to));
+        self
+    }
+
+    pub fn contains(&self, token: &str) -> bool {
+        if self.tokens.iter().any(|t| t == token) {
+            return true;
+        }
+        let mut chars = token.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.ranges.iter().any(|(from, to)| *from <= c && c <= *to),
+            _ => false,
+        }
+    }
+}
+
+/// Lexical disambiguation for character-level grammars, which can't say
+/// "longest match" on their own: without help `ifx` is both the keyword
+/// `if` before `x` and the identifier `ifx`, and `if` is an identifier as
+/// well as a keyword.
+///
+/// ```text
+/// Restrictions::new()
+///     .follow("Id", TokenSet::new().range('a', 'z'))
+///     .follow("if", TokenSet::new().range('a', 'z'))
+///     .reserve("Id", "if")
+/// ```
+///
+/// keeps both `<Id>` and the keyword from stopping right before another
+/// letter, and `<Id>` from being `if`. The parser enforces restrictions
+/// as it fills the chart: the derivations they rule out are never
+/// completed, so they show up in neither the outcome nor the parse forest.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Restrictions {
+    follow: Vec<(Term, TokenSet)>,
+    lookahead: Vec<(Term, TokenSet, bool)>,
+    reserved: Vec<(Term, String)>,
+}
+
+impl Restrictions {
+    pub fn new() -> Restrictions {
+        Restrictions::default()
+    }
+
+    /// A follow restriction: `<nonterminal>` can't be followed by any token
+    /// of `tokens`.
+    pub fn follow(mut self, nonterminal: &str, tokens: TokenSet) -> Restrictions {
+        self.follow
+            .push((Term::Nonterminal(nonterminal.to_string()), tokens));
+        self
+    }
+
+    /// Positive lookahead: `terminal` only matches right before one of
+    /// `tokens`, so never at the end of the input.
+    pub fn lookahead(mut self, terminal: &str, tokens: TokenSet) -> Restrictions {
+        self.lookahead
+            .push((Term::Terminal(terminal.to_string()), tokens, true));
+        self
+    }
+
+    /// Negative lookahead: `terminal` doesn't match right before any of
+    /// `tokens`.
+    pub fn negative_lookahead(mut self, terminal: &str, tokens: TokenSet) -> Restrictions {
+        self.lookahead
+            .push((Term::Terminal(terminal.to_string()), tokens, false));
+        self
+    }
+
+    /// Reserves `word`: `<nonterminal>` doesn't derive input whose tokens
+    /// joined together are `word`.
+    pub fn reserve(mut self, nonterminal: &str, word: &str) -> Restrictions {
+        self.reserved
+            .push((Term::Nonterminal(nonterminal.to_string()), word.to_string()));
+        self
+    }
+
+    /// Checks every symbol restricted is in `grammar`, since a misspelt one
+    /// would restrict nothing.
+    pub fn check(&self, grammar: &Grammar) -> Result<(), Error> {
+        let terms = self
+            .follow
+            .iter()
+            .map(|(term, _)| term)
+            .chain(self.lookahead.iter().map(|(term, _, _)| term))
+            .chain(self.reserved.iter().map(|(term, _)| term));
+        for term in terms {
+            let defined = grammar
+                .productions_iter()
+                .any(|p| p.lhs == *term || p.rhs_iter().any(|e| e.terms_iter().any(|t| t == term)));
+            if !defined {
+                return Err(Error::GrammarError(format!(
+                    "Restricted symbol {} isn't in grammar: {}",
+                    term, grammar
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether terminal `terminal` may match right before `next`, the
+    /// token after it if there is one.
+    pub fn admits_terminal(&self, terminal: &Term, next: Option<&String>) -> bool {
+        self.lookahead
+            .iter()
+            .filter(|(term, _, _)| term == terminal)
+            .all(|(_, tokens, positive)| match next {
+                Some(next) => tokens.contains(next) == *positive,
+                None => !positive,
+            })
+    }
+
+    /// Whether completed `state`, ending right before `input[end]`, is let
+    /// through by the follow restrictions and reserved words.
+    pub fn admits_completed(&self, state: &IState, end: usize, input: &[String]) -> bool {
+        let lhs = &state.prod.lhs;
+        let followed = match input.get(end) {
+            Some(next) => self
+                .follow
+                .iter()
+                .any(|(term, tokens)| term == lhs && tokens.contains(next)),
+            None => false,
+        };
+        if followed {
+            return false;
+        }
+
+        !self
+            .reserved
+            .iter()
+            .any(|(term, word)| term == lhs && *word == input[state.origin..end].concat())
+    }
+}
@@ -0,0 +1,549 @@
+use crate::error::Error;
+use crate::tree::{Branch, Tree};
+use bnf::Term;
+use std::collections::HashMap;
+
+/// Declarative rewrite rules for turning parse trees into simpler trees.
+///
+/// Each rule is a pattern and a replacement written as s-expressions. In a
+/// pattern,
+///
+/// * `(S p1 p2 ...)` (or `(<S> ...)`) matches an `<S>` node whose branches
+///   match `p1 p2 ...` exactly, `(_ ...)` a node with any name and
+///   `($n ...)` one with any name, bound to `n`;
+/// * `"text"` matches a terminal leaf and `_` any single branch;
+/// * `@x` after a pattern captures what it matched, alone it's short for
+///   `_@x`, and `@xs...` captures any run of branches.
+///
+/// A replacement is a list of branches built from `(S ...)` and `($n ...)`
+/// nodes, `"text"` leaves and captures, which put back what they matched.
+/// It takes the place of the matched branch in its parent, so a replacement
+/// may be a single capture or even nothing. The production of a node built
+/// by a replacement is made up from its branches.
+///
+/// ```ignore
+/// let rewriter = Rewriter::new()
+///     // <S> -> <M> -> <T> -> '2' becomes <S> -> '2'
+///     .rule("($a ($b @x))", "($a @x)")?
+///     // drop commas wherever they are
+///     .rule("\",\"", "")?;
+/// ```
+///
+/// `rewrite` applies the rules bottom up, first rule first, and goes over
+/// the tree again until no rule matches anywhere.
+pub struct Rewriter {
+    rules: Vec<(Pattern, Vec<Template>)>,
+    max_passes: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Name {
+    Exactly(String),
+    Any,
+    Bind(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Pattern {
+    Node(Name, Vec<Pattern>, Option<String>),
+    Terminal(String, Option<String>),
+    Any(Option<String>),
+    Rest(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Template {
+    Node(Name, Vec<Template>),
+    Terminal(String),
+    Capture(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Binding {
+    Branches(Vec<Branch>),
+    Name(String),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+impl Default for Rewriter {
+    fn default() -> Rewriter {
+        Rewriter::new()
+    }
+}
+
+impl Rewriter {
+    pub fn new() -> Rewriter {
+        Rewriter {
+            rules: vec![],
+            max_passes: 1000,
+        }
+    }
+
+    /// Adds a rule rewriting whatever matches `pattern` into `replacement`.
+    pub fn rule(mut self, pattern: &str, replacement: &str) -> Result<Rewriter, Error> {
+        let mut pattern_items = Parser::new(pattern).items(true)?;
+        let pattern = match (pattern_items.pop().map(Item::pattern), pattern_items.len()) {
+            (Some(Pattern::Rest(_)), _) | (None, _) | (_, 1..) => {
+                return Err(Error::RewriteError(format!(
+                    "Pattern {:?} has to be exactly one branch",
+                    pattern
+                )))
+            }
+            (Some(pattern), _) => pattern,
+        };
+        let templates = Parser::new(replacement)
+            .items(false)?
+            .into_iter()
+            .map(|item| item.template(replacement))
+            .collect::<Result<Vec<Template>, Error>>()?;
+
+        let mut bound = vec![];
+        pattern.bound(&mut bound);
+        for template in &templates {
+            template.check(&bound, replacement)?;
+        }
+
+        self.rules.push((pattern, templates));
+        Ok(self)
+    }
+
+    /// How many times `rewrite` goes over the tree before giving up on rules
+    /// that keep matching what they produce, 1000 by default.
+    pub fn max_passes(mut self, max_passes: usize) -> Rewriter {
+        self.max_passes = max_passes;
+        self
+    }
+
+    pub fn rewrite(&self, tree: &Tree) -> Result<Tree, Error> {
+        let mut branches = vec![Branch::Nonterminal(tree.clone())];
+        for _ in 0..self.max_passes {
+            let mut changed = false;
+            branches = self.pass(branches, &mut changed);
+            if !changed {
+                return match branches.pop() {
+                    Some(Branch::Nonterminal(tree)) if branches.is_empty() => Ok(tree),
+                    _ => Err(Error::RewriteError(
+                        "Rewriting the root has to leave a single nonterminal".to_string(),
+                    )),
+                };
+            }
+        }
+
+        Err(Error::RewriteError(format!(
+            "Rules still matched after {} passes",
+            self.max_passes
+        )))
+    }
+
+    /// Rewrites the branches below each of `branches` and then the branch
+    /// itself, with the first rule that matches it.
+    fn pass(&self, branches: Vec<Branch>, changed: &mut bool) -> Vec<Branch> {
+        let mut rewritten = vec![];
+        for branch in branches {
+            let branch = match branch {
+                Branch::Nonterminal(tree) => {
+                    let count = tree.branches.len();
+                    let children = self.pass(tree.branches, changed);
+                    // Trivia stays with the leaves only while they keep
+                    // their places.
+                    let trivia = if children.len() == count {
+                        tree.trivia
+                    } else {
+                        Default::default()
+                    };
+                    Branch::Nonterminal(Tree {
+                        production: tree.production,
+                        branches: children,
+                        trivia,
+                    })
+                }
+                terminal => terminal,
+            };
+
+            let rule = self.rules.iter().find_map(|(pattern, templates)| {
+                let mut bindings = Bindings::new();
+                if pattern.matches(&branch, &mut bindings) {
+                    Some((templates, bindings))
+                } else {
+                    None
+                }
+            });
+            match rule {
+                Some((templates, bindings)) => {
+                    let replacement: Vec<Branch> =
+                        templates.iter().flat_map(|t| t.build(&bindings)).collect();
+                    if replacement != [branch] {
+                        *changed = true;
+                    }
+                    rewritten.extend(replacement);
+                }
+                None => rewritten.push(branch),
+            }
+        }
+        rewritten
+    }
+}
+
+/// Binds `name` to `value`, or checks it's the same as what `name` is
+/// already bound to.
+fn bind(bindings: &mut Bindings, name: &str, value: Binding) -> bool {
+    match bindings.get(name) {
+        Some(bound) => *bound == value,
+        None => {
+            bindings.insert(name.to_string(), value);
+            true
+        }
+    }
+}
+
+impl Pattern {
+    fn matches(&self, branch: &Branch, bindings: &mut Bindings) -> bool {
+        let matched = match (self, branch) {
+            (Pattern::Node(name, children, _), Branch::Nonterminal(tree)) => {
+                let name_matches = match name {
+                    Name::Exactly(n) => tree.name() == n,
+                    Name::Any => true,
+                    Name::Bind(n) => bind(
+                        bindings,
+                        &format!("${}", n),
+                        Binding::Name(tree.name().to_string()),
+                    ),
+                };
+                name_matches && Pattern::matches_seq(children, &tree.branches, bindings)
+            }
+            (Pattern::Terminal(text, _), Branch::Terminal(s)) => text == s,
+            (Pattern::Any(_), _) => true,
+            _ => false,
+        };
+
+        match self.capture() {
+            Some(name) if matched => bind(bindings, name, Binding::Branches(vec![branch.clone()])),
+            _ => matched,
+        }
+    }
+
+    /// Matches `patterns` against `branches` in sequence, trying every
+    /// length for each `@xs...` until the rest of the sequence matches too.
+    fn matches_seq(patterns: &[Pattern], branches: &[Branch], bindings: &mut Bindings) -> bool {
+        match patterns.split_first() {
+            None => branches.is_empty(),
+            Some((Pattern::Rest(name), rest)) => {
+                for taken in 0..=branches.len() {
+                    let mut attempt = bindings.clone();
+                    let value = Binding::Branches(branches[..taken].to_vec());
+                    if bind(&mut attempt, name, value)
+                        && Pattern::matches_seq(rest, &branches[taken..], &mut attempt)
+                    {
+                        *bindings = attempt;
+                        return true;
+                    }
+                }
+                false
+            }
+            Some((pattern, rest)) => match branches.split_first() {
+                Some((branch, remaining)) => {
+                    let mut attempt = bindings.clone();
+                    if pattern.matches(branch, &mut attempt)
+                        && Pattern::matches_seq(rest, remaining, &mut attempt)
+                    {
+                        *bindings = attempt;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn capture(&self) -> Option<&String> {
+        match self {
+            Pattern::Node(_, _, capture)
+            | Pattern::Terminal(_, capture)
+            | Pattern::Any(capture) => capture.as_ref(),
+            Pattern::Rest(_) => None,
+        }
+    }
+
+    /// Every name the pattern binds.
+    fn bound(&self, names: &mut Vec<String>) {
+        if let Some(name) = self.capture() {
+            names.push(name.clone());
+        }
+        match self {
+            Pattern::Node(name, children, _) => {
+                if let Name::Bind(n) = name {
+                    names.push(format!("${}", n));
+                }
+                for child in children {
+                    child.bound(names);
+                }
+            }
+            Pattern::Rest(name) => names.push(name.clone()),
+            _ => {}
+        }
+    }
+}
+
+impl Template {
+    fn check(&self, bound: &[String], replacement: &str) -> Result<(), Error> {
+        let unbound = |name: String| {
+            Err(Error::RewriteError(format!(
+                "{} in replacement {:?} isn't bound by the pattern",
+                name, replacement
+            )))
+        };
+        match self {
+            Template::Capture(name) if !bound.contains(name) => unbound(format!("@{}", name)),
+            Template::Node(name, children) => {
+                if let Name::Bind(n) = name {
+                    if !bound.contains(&format!("${}", n)) {
+                        return unbound(format!("${}", n));
+                    }
+                }
+                children
+                    .iter()
+                    .try_for_each(|c| c.check(bound, replacement))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn build(&self, bindings: &Bindings) -> Vec<Branch> {
+        match self {
+            Template::Terminal(text) => vec![Branch::Terminal(text.clone())],
+            Template::Capture(name) => match bindings.get(name) {
+                Some(Binding::Branches(branches)) => branches.clone(),
+                _ => vec![],
+            },
+            Template::Node(name, children) => {
+                let name = match name {
+                    Name::Exactly(n) => n.clone(),
+                    Name::Bind(n) => match bindings.get(&format!("${}", n)) {
+                        Some(Binding::Name(n)) => n.clone(),
+                        _ => String::new(),
+                    },
+                    Name::Any => String::new(),
+                };
+                let branches = children.iter().flat_map(|c| c.build(bindings)).collect();
+                vec![Branch::Nonterminal(Tree::from_branches(
+                    Term::Nonterminal(name),
+                    branches,
+                ))]
+            }
+        }
+    }
+}
+
+/// A pattern or template item as written, before it's known which of the
+/// two it is.
+enum Item {
+    Node(Name, Vec<Item>, Option<String>),
+    Terminal(String, Option<String>),
+    Any(Option<String>),
+    Capture(String, bool),
+}
+
+impl Item {
+    fn pattern(self) -> Pattern {
+        match self {
+            Item::Node(name, children, capture) => Pattern::Node(
+                name,
+                children.into_iter().map(|c| c.pattern()).collect(),
+                capture,
+            ),
+            Item::Terminal(text, capture) => Pattern::Terminal(text, capture),
+            Item::Any(capture) => Pattern::Any(capture),
+            Item::Capture(name, false) => Pattern::Any(Some(name)),
+            Item::Capture(name, true) => Pattern::Rest(name),
+        }
+    }
+
+    fn template(self, source: &str) -> Result<Template, Error> {
+        let invalid = |what: &str| {
+            Err(Error::RewriteError(format!(
+                "Replacement {:?} can't have {}",
+                source, what
+            )))
+        };
+        match self {
+            Item::Node(Name::Any, _, _) => invalid("a node named `_`"),
+            Item::Node(_, _, Some(_)) | Item::Terminal(_, Some(_)) => invalid("a capture"),
+            Item::Any(_) => invalid("`_`"),
+            Item::Node(name, children, None) => Ok(Template::Node(
+                name,
+                children
+                    .into_iter()
+                    .map(|c| c.template(source))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Item::Terminal(text, None) => Ok(Template::Terminal(text)),
+            Item::Capture(name, _) => Ok(Template::Capture(name)),
+        }
+    }
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    chars: Vec<(usize, char)>,
+    i: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn new(source: &'s str) -> Parser<'s> {
+        Parser {
+            source,
+            chars: source.char_indices().collect(),
+            i: 0,
+        }
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        let at = self
+            .chars
+            .get(self.i)
+            .map_or(self.source.len(), |(at, _)| *at);
+        Error::RewriteError(format!("{} at {} in {:?}", msg, at, self.source))
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while let Some((_, c)) = self.chars.get(self.i) {
+            if !c.is_whitespace() {
+                return Some(*c);
+            }
+            self.i += 1;
+        }
+        None
+    }
+
+    fn items(&mut self, pattern: bool) -> Result<Vec<Item>, Error> {
+        let mut items = vec![];
+        while self.peek().is_some() {
+            items.push(self.item(pattern)?);
+        }
+        Ok(items)
+    }
+
+    fn item(&mut self, pattern: bool) -> Result<Item, Error> {
+        let item = match self.peek() {
+            Some('(') => {
+                self.i += 1;
+                let name = match self.peek() {
+                    Some('$') => {
+                        self.i += 1;
+                        Name::Bind(self.name()?)
+                    }
+                    _ => match self.name()? {
+                        ref name if name == "_" => Name::Any,
+                        name => Name::Exactly(name),
+                    },
+                };
+                let mut children = vec![];
+                loop {
+                    match self.peek() {
+                        Some(')') => break,
+                        Some(_) => children.push(self.item(pattern)?),
+                        None => return Err(self.error("unclosed `(`")),
+                    }
+                }
+                self.i += 1;
+                Item::Node(name, children, self.capture()?)
+            }
+            Some('"') | Some('\'') => {
+                let text = self.string()?;
+                Item::Terminal(text, self.capture()?)
+            }
+            Some('_') => {
+                self.i += 1;
+                Item::Any(self.capture()?)
+            }
+            Some('@') => {
+                self.i += 1;
+                let name = self.name()?;
+                let rest = self.source[self.offset()..].starts_with("...");
+                if rest {
+                    self.i += 3;
+                }
+                Item::Capture(name, rest)
+            }
+            _ => {
+                return Err(self.error(if pattern {
+                    "expected `(`, a string, `_` or `@`"
+                } else {
+                    "expected `(`, a string or `@`"
+                }))
+            }
+        };
+        Ok(item)
+    }
+
+    fn offset(&self) -> usize {
+        self.chars
+            .get(self.i)
+            .map_or(self.source.len(), |(at, _)| *at)
+    }
+
+    fn capture(&mut self) -> Result<Option<String>, Error> {
+        if self.chars.get(self.i).map(|(_, c)| *c) == Some('@') {
+            self.i += 1;
+            Ok(Some(self.name()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A bare name, or one in angle brackets which can hold spaces.
+    fn name(&mut self) -> Result<String, Error> {
+        let mut name = String::new();
+        if self.chars.get(self.i).map(|(_, c)| *c) == Some('<') {
+            self.i += 1;
+            loop {
+                match self.chars.get(self.i) {
+                    Some((_, '>')) => break,
+                    Some((_, c)) => name.push(*c),
+                    None => return Err(self.error("unclosed `<`")),
+                }
+                self.i += 1;
+            }
+            self.i += 1;
+        } else {
+            while let Some((_, c)) = self.chars.get(self.i) {
+                if !(c.is_alphanumeric() || *c == '_' || *c == '-') {
+                    break;
+                }
+                name.push(*c);
+                self.i += 1;
+            }
+        }
+
+        if name.is_empty() {
+            return Err(self.error("expected a name"));
+        }
+        Ok(name)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let quote = self.chars[self.i].1;
+        self.i += 1;
+        let mut text = String::new();
+        loop {
+            match self.chars.get(self.i) {
+                Some((_, '\\')) => {
+                    self.i += 1;
+                    match self.chars.get(self.i) {
+                        Some((_, c)) => text.push(*c),
+                        None => return Err(self.error("unclosed string")),
+                    }
+                }
+                Some((_, c)) if *c == quote => break,
+                Some((_, c)) => text.push(*c),
+                None => return Err(self.error("unclosed string")),
+            }
+            self.i += 1;
+        }
+        self.i += 1;
+        Ok(text)
+    }
+}
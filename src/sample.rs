@@ -0,0 +1,126 @@
+use crate::forest::{Forest, ForestChild};
+use crate::tree::{Branch, Tree};
+use bnf::Production;
+use rand::Rng;
+
+/// Draws derivations from a `Forest` without enumerating them. Each node
+/// carries the total weight of the derivations below it (with a weight of
+/// one for every production that's the number of parses of its span), and a
+/// sample picks each family with probability proportional to the weight of
+/// its children. A whole tree is drawn with probability proportional to the
+/// product of its productions' weights.
+pub struct Sampler<'a> {
+    forest: &'a Forest,
+    productions: Vec<Production>,
+    inside: Vec<f64>,
+}
+
+impl<'a> Sampler<'a> {
+    pub fn uniform(forest: &'a Forest) -> Sampler<'a> {
+        Sampler::weighted(forest, |_| 1.0)
+    }
+
+    pub fn weighted<F>(forest: &'a Forest, weight: F) -> Sampler<'a>
+    where
+        F: Fn(&Production) -> f64,
+    {
+        let productions: Vec<Production> = forest
+            .nodes
+            .iter()
+            .map(|node| node.prod.to_production())
+            .collect();
+        let weights: Vec<f64> = productions.iter().map(|p| weight(p).max(0.0)).collect();
+
+        let mut sampler = Sampler {
+            forest,
+            productions,
+            inside: vec![],
+        };
+        sampler.inside = sampler.compute_inside(&weights);
+        sampler
+    }
+
+    /// The summed weight of every tree in the forest.
+    pub fn total(&self) -> f64 {
+        self.forest.roots.iter().map(|r| self.inside[*r]).sum()
+    }
+
+    /// `None` when every tree has a weight of zero.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Tree> {
+        let roots: Vec<f64> = self.forest.roots.iter().map(|r| self.inside[*r]).collect();
+        let root = self.forest.roots[choose(rng, &roots)?];
+        self.sample_node(rng, root)
+    }
+
+    fn sample_node<R: Rng + ?Sized>(&self, rng: &mut R, v: usize) -> Option<Tree> {
+        let families = &self.forest.nodes[v].families;
+        let weights: Vec<f64> = families
+            .iter()
+            .map(|children| self.family_weight(children))
+            .collect();
+
+        let mut branches = vec![];
+        for child in &families[choose(rng, &weights)?] {
+            match child {
+                ForestChild::Nonterminal(c) => {
+                    branches.push(Branch::Nonterminal(self.sample_node(rng, *c)?))
+                }
+                ForestChild::Terminal(_, s) => branches.push(Branch::Terminal(s.to_string())),
+            }
+        }
+
+        Some(Tree {
+            production: self.productions[v].clone(),
+            branches,
+            trivia: Default::default(),
+        })
+    }
+
+    fn family_weight(&self, children: &[ForestChild]) -> f64 {
+        children
+            .iter()
+            .map(|child| match child {
+                ForestChild::Nonterminal(c) => self.inside[*c],
+                ForestChild::Terminal(_, _) => 1.0,
+            })
+            .product()
+    }
+
+    fn compute_inside(&self, weights: &[f64]) -> Vec<f64> {
+        let mut inside = vec![0.0; self.forest.nodes.len()];
+        for v in self.forest.postorder() {
+            let sum: f64 = self.forest.nodes[v]
+                .families
+                .iter()
+                .map(|children| {
+                    children
+                        .iter()
+                        .map(|child| match child {
+                            ForestChild::Nonterminal(c) => inside[*c],
+                            ForestChild::Terminal(_, _) => 1.0,
+                        })
+                        .product::<f64>()
+                })
+                .sum();
+            inside[v] = weights[v] * sum;
+        }
+        inside
+    }
+}
+
+pub(crate) fn choose<R: Rng + ?Sized>(rng: &mut R, weights: &[f64]) -> Option<usize> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut target = rng.gen::<f64>() * total;
+    for (i, w) in weights.iter().enumerate() {
+        if target < *w {
+            return Some(i);
+        }
+        target -= w;
+    }
+
+    weights.iter().rposition(|w| *w > 0.0)
+}
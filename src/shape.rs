@@ -0,0 +1,65 @@
+/// Options for shaping the trees built from a parse forest into a more
+/// compact concrete syntax tree, applied while the trees are built rather
+/// than in a pass over them afterwards. With none set (the default) trees
+/// follow the grammar exactly.
+///
+/// A shaped node's production no longer comes from the grammar but is made
+/// up from the branches it ends up with, so `Tree::validate` will reject
+/// it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Shape {
+    collapse_units: bool,
+    transparent: Vec<String>,
+    transparent_prefixes: Vec<String>,
+    dropped_terminals: Vec<String>,
+}
+
+impl Shape {
+    pub fn new() -> Shape {
+        Shape::default()
+    }
+
+    /// Replaces every node left with a single nonterminal branch, like the
+    /// `<S> ::= <M>` and `<M> ::= <T>` of a unit chain, by that branch. The
+    /// root is kept so trees still start at the start symbol.
+    pub fn collapse_units(mut self) -> Shape {
+        self.collapse_units = true;
+        self
+    }
+
+    /// Splices the branches of every `<name>` node into its parent in place
+    /// of the node.
+    pub fn transparent(mut self, name: &str) -> Shape {
+        self.transparent.push(name.to_string());
+        self
+    }
+
+    /// Makes every nonterminal whose name starts with `prefix` transparent,
+    /// e.g. `"_"` for helper nonterminals like `<_digits>`.
+    pub fn transparent_prefix(mut self, prefix: &str) -> Shape {
+        self.transparent_prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Leaves out every terminal leaf reading `terminal`, e.g. punctuation.
+    pub fn drop_terminal(mut self, terminal: &str) -> Shape {
+        self.dropped_terminals.push(terminal.to_string());
+        self
+    }
+
+    pub fn is_collapsing_units(&self) -> bool {
+        self.collapse_units
+    }
+
+    pub fn is_transparent(&self, name: &str) -> bool {
+        self.transparent.iter().any(|n| n == name)
+            || self
+                .transparent_prefixes
+                .iter()
+                .any(|p| name.starts_with(p.as_str()))
+    }
+
+    pub fn is_dropped(&self, terminal: &str) -> bool {
+        self.dropped_terminals.iter().any(|t| t == terminal)
+    }
+}
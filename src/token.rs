@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+
+/// Byte offsets `start..end` of a token in the source it was split from.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// The source an input was tokenized from along with the span of every
+/// token, so whatever sits between tokens (the separators `earley_parse`
+/// splits on) can be put back when printing a tree.
+#[derive(Deserialize, Serialize, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TokenizerInfo {
+    pub source: String,
+    pub spans: Vec<Span>,
+}
+
+impl TokenizerInfo {
+    /// Splits `source` the way `EarleyParser::earley_parse` does: on every
+    /// `split_on` when there is one and into single chars when there isn't.
+    pub fn split(source: &str, split_on: Option<char>) -> TokenizerInfo {
+        let mut spans = vec![];
+        match split_on {
+            Some(split_char) => {
+                let mut start = 0;
+                for (i, c) in source.char_indices() {
+                    if c == split_char {
+                        spans.push(Span::new(start, i));
+                        start = i + c.len_utf8();
+                    }
+                }
+                spans.push(Span::new(start, source.len()));
+            }
+            None => {
+                for (i, c) in source.char_indices() {
+                    spans.push(Span::new(i, i + c.len_utf8()));
+                }
+            }
+        }
+
+        TokenizerInfo {
+            source: source.to_string(),
+            spans,
+        }
+    }
+
+    pub fn tokens(&self) -> Vec<String> {
+        self.spans
+            .iter()
+            .map(|span| self.source[span.start..span.end].to_string())
+            .collect()
+    }
+
+    /// The source text between the end of token `i - 1` and the start of
+    /// token `i`, where token `spans.len()` is the end of the source.
+    pub fn gap(&self, i: usize) -> &str {
+        let start = match i {
+            0 => 0,
+            _ => self.spans.get(i - 1).map_or(self.source.len(), |s| s.end),
+        };
+        let end = self.spans.get(i).map_or(self.source.len(), |s| s.start);
+        &self.source[start..end.max(start)]
+    }
+}
+
+/// A terminal leaf of a lossless tree: the token's text along with the
+/// trivia (whitespace and comments) around it, so the leaves of a tree
+/// printed in order give back the source byte for byte.
+#[derive(Deserialize, Serialize, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub leading: String,
+    pub trailing: String,
+}
+
+/// The trivia around one terminal leaf of a lossless tree, kept in
+/// `Tree::trivia` by the leaf's branch index.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LeafTrivia {
+    pub leading: String,
+    pub trailing: String,
+}
+
+/// What the lossless tokenizer skips as trivia between tokens: whitespace,
+/// always, and the comments given here.
+///
+/// Trivia goes to the tokens next to it the way formatters like it: the
+/// trivia after a token up to the end of its line trails that token, and
+/// everything from the next line on leads the following token. Trivia
+/// before the first token leads it and trivia after the last token trails
+/// it.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Trivia {
+    chars: bool,
+    line_comments: Vec<String>,
+    block_comments: Vec<(String, String)>,
+}
+
+impl Trivia {
+    /// Trivia between whitespace separated words, which are the tokens.
+    pub fn whitespace() -> Trivia {
+        Trivia::default()
+    }
+
+    /// Makes every char that isn't trivia a token of its own, the way
+    /// `earley_parse` splits input without a `split_on`.
+    pub fn chars(mut self) -> Trivia {
+        self.chars = true;
+        self
+    }
+
+    /// Skips from `start` to the end of the line.
+    pub fn line_comment(mut self, start: &str) -> Trivia {
+        self.line_comments.push(start.to_string());
+        self
+    }
+
+    /// Skips from `start` through the next `end`.
+    pub fn block_comment(mut self, start: &str, end: &str) -> Trivia {
+        self.block_comments
+            .push((start.to_string(), end.to_string()));
+        self
+    }
+
+    /// The length of the trivia at the start of `text`, if it starts with a
+    /// comment or whitespace.
+    fn skip(&self, text: &str) -> Option<usize> {
+        if let Some(c) = text.chars().next().filter(|c| c.is_whitespace()) {
+            return Some(c.len_utf8());
+        }
+        for start in &self.line_comments {
+            if text.starts_with(start.as_str()) {
+                return Some(text.find('\n').unwrap_or(text.len()));
+            }
+        }
+        for (start, end) in &self.block_comments {
+            if text.starts_with(start.as_str()) {
+                return Some(match text[start.len()..].find(end.as_str()) {
+                    Some(i) => start.len() + i + end.len(),
+                    None => text.len(),
+                });
+            }
+        }
+        None
+    }
+
+    /// Where the gap between two tokens splits into the first one's
+    /// trailing trivia and the second one's leading trivia: just past the
+    /// first newline outside a comment.
+    pub fn split_gap(&self, gap: &str) -> usize {
+        let mut i = 0;
+        while let Some(len) = self.skip(&gap[i..]) {
+            if gap[i..].starts_with('\n') {
+                return i + 1;
+            }
+            i += len;
+        }
+        gap.len()
+    }
+}
+
+impl TokenizerInfo {
+    /// Splits `source` into tokens around the trivia `trivia` describes,
+    /// which is left in the gaps between the tokens' spans.
+    pub fn lossless(source: &str, trivia: &Trivia) -> TokenizerInfo {
+        let mut spans = vec![];
+        let mut token_start: Option<usize> = None;
+        let mut i = 0;
+        while i < source.len() {
+            if let Some(len) = trivia.skip(&source[i..]) {
+                if let Some(start) = token_start.take() {
+                    spans.push(Span::new(start, i));
+                }
+                i += len;
+                continue;
+            }
+
+            let c = source[i..].chars().next().map_or(1, |c| c.len_utf8());
+            if trivia.chars {
+                spans.push(Span::new(i, i + c));
+            } else if token_start.is_none() {
+                token_start = Some(i);
+            }
+            i += c;
+        }
+        if let Some(start) = token_start {
+            spans.push(Span::new(start, source.len()));
+        }
+
+        TokenizerInfo {
+            source: source.to_string(),
+            spans,
+        }
+    }
+
+    /// The tokens with the trivia around them attached, split between
+    /// neighbours by `trivia`.
+    pub fn lossless_tokens(&self, trivia: &Trivia) -> Vec<Token> {
+        let n = self.spans.len();
+        let mut tokens: Vec<Token> = self
+            .tokens()
+            .into_iter()
+            .map(|text| Token {
+                text,
+                leading: String::new(),
+                trailing: String::new(),
+            })
+            .collect();
+        if n == 0 {
+            return tokens;
+        }
+
+        tokens[0].leading = self.gap(0).to_string();
+        for i in 1..n {
+            let gap = self.gap(i);
+            let split = trivia.split_gap(gap);
+            tokens[i - 1].trailing = gap[..split].to_string();
+            tokens[i].leading = gap[split..].to_string();
+        }
+        tokens[n - 1].trailing = self.gap(n).to_string();
+        tokens
+    }
+}
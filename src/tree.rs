@@ -1,15 +1,301 @@
-use crate::itree::PPChar;
-use bnf::Production;
+use crate::error::Error;
+use crate::token::{LeafTrivia, Token, TokenizerInfo, Trivia};
+use bnf::{Expression, Grammar, Production, Term};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Tree {
     pub production: Production,
     pub branches: Vec<Branch>,
+    /// The whitespace and comments around the terminal branches of a
+    /// lossless tree, by branch index, see `Tree::with_trivia`. Empty for
+    /// a plain tree.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trivia: BTreeMap<usize, LeafTrivia>,
 }
 
 impl Tree {
+    /// A `<lhs>` node over `branches` with the production they spell out,
+    /// for trees put together by hand or reshaped rather than parsed.
+    pub fn from_branches(lhs: Term, branches: Vec<Branch>) -> Tree {
+        let terms = branches
+            .iter()
+            .map(|branch| match branch {
+                Branch::Nonterminal(tree) => tree.production.lhs.clone(),
+                Branch::Terminal(s) => Term::Terminal(s.clone()),
+            })
+            .collect();
+        Tree {
+            production: Production::from_parts(lhs, vec![Expression::from_parts(terms)]),
+            branches,
+            trivia: BTreeMap::new(),
+        }
+    }
+
+    /// The terminal leaves of the tree, left to right.
+    pub fn yield_tokens(&self) -> Vec<String> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens(&self, tokens: &mut Vec<String>) {
+        for branch in &self.branches {
+            match branch {
+                Branch::Nonterminal(tree) => tree.collect_tokens(tokens),
+                Branch::Terminal(s) => tokens.push(s.to_string()),
+            }
+        }
+    }
+
+    /// The terminal leaves of the tree with the trivia around them, left to
+    /// right. The trivia is empty but for the leaves of a lossless tree.
+    pub fn tokens(&self) -> Vec<Token> {
+        let mut tokens = vec![];
+        self.collect_lossless_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_lossless_tokens(&self, tokens: &mut Vec<Token>) {
+        for (i, branch) in self.branches.iter().enumerate() {
+            match branch {
+                Branch::Nonterminal(tree) => tree.collect_lossless_tokens(tokens),
+                Branch::Terminal(s) => {
+                    let trivia = self.trivia.get(&i).cloned().unwrap_or_default();
+                    tokens.push(Token {
+                        text: s.to_string(),
+                        leading: trivia.leading,
+                        trailing: trivia.trailing,
+                    });
+                }
+            }
+        }
+    }
+
+    /// The tree as source text: the text of its leaves along with the
+    /// trivia of any lossless ones, so a tree from `lossless_parse_forest`
+    /// prints as the exact input it was parsed from.
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        self.write_source(&mut source);
+        source
+    }
+
+    fn write_source(&self, source: &mut String) {
+        for (i, branch) in self.branches.iter().enumerate() {
+            match branch {
+                Branch::Nonterminal(tree) => tree.write_source(source),
+                Branch::Terminal(s) => match self.trivia.get(&i) {
+                    Some(trivia) => {
+                        source.push_str(&trivia.leading);
+                        source.push_str(s);
+                        source.push_str(&trivia.trailing);
+                    }
+                    None => source.push_str(s),
+                },
+            }
+        }
+    }
+
+    /// The same tree with the trivia around every terminal leaf in
+    /// `tokenizer_info`'s source recorded in `Tree::trivia`, split between
+    /// neighbouring tokens by `trivia`.
+    pub fn with_trivia(
+        &self,
+        tokenizer_info: &TokenizerInfo,
+        trivia: &Trivia,
+    ) -> Result<Tree, Error> {
+        let tokens = tokenizer_info.lossless_tokens(trivia);
+        let leaves = self.yield_tokens();
+        if leaves.len() != tokens.len() {
+            return Err(Error::InvalidTree(format!(
+                "Tree has {} leaves for {} tokens",
+                leaves.len(),
+                tokens.len()
+            )));
+        }
+
+        let mut tokens = tokens.into_iter();
+        Ok(self.attach_trivia(&mut tokens))
+    }
+
+    fn attach_trivia(&self, tokens: &mut impl Iterator<Item = Token>) -> Tree {
+        let mut trivia = BTreeMap::new();
+        let branches = self
+            .branches
+            .iter()
+            .enumerate()
+            .map(|(i, branch)| match branch {
+                Branch::Nonterminal(tree) => Branch::Nonterminal(tree.attach_trivia(tokens)),
+                Branch::Terminal(_) => {
+                    if let Some(token) = tokens.next() {
+                        trivia.insert(
+                            i,
+                            LeafTrivia {
+                                leading: token.leading,
+                                trailing: token.trailing,
+                            },
+                        );
+                    }
+                    branch.clone()
+                }
+            })
+            .collect();
+        Tree {
+            production: self.production.clone(),
+            branches,
+            trivia,
+        }
+    }
+
+    /// Prints the tree's leaves back into the source described by
+    /// `tokenizer_info`, keeping everything that was between the original
+    /// tokens. A tree whose leaves were rewritten prints with the new text in
+    /// place of the old; its leaf count still has to match the token count.
+    pub fn unparse(&self, tokenizer_info: &TokenizerInfo) -> Result<String, Error> {
+        let tokens = self.yield_tokens();
+        if tokens.len() != tokenizer_info.spans.len() {
+            return Err(Error::InvalidTree(format!(
+                "Tree has {} leaves for {} tokens",
+                tokens.len(),
+                tokenizer_info.spans.len()
+            )));
+        }
+
+        let mut value = String::new();
+        for (i, token) in tokens.iter().enumerate() {
+            value += tokenizer_info.gap(i);
+            value += token;
+        }
+        value += tokenizer_info.gap(tokens.len());
+
+        Ok(value)
+    }
+
+    /// Checks that this tree is a derivation of `tokens` in `grammar`: the
+    /// root is the grammar's start symbol, every node's production is one of
+    /// the grammar's alternatives, every node's branches line up with the
+    /// terms of that alternative, every terminal leaf is the token at its
+    /// position and the leaves cover all of `tokens`.
+    pub fn validate(&self, grammar: &Grammar, tokens: &[String]) -> Result<(), Error> {
+        if let Some(start) = grammar.productions_iter().next() {
+            if start.lhs != self.production.lhs {
+                return Err(Error::InvalidTree(format!(
+                    "Root {} isn't the start symbol {}",
+                    self.production.lhs, start.lhs
+                )));
+            }
+        }
+
+        let covered = self.validate_node(grammar, tokens, 0, &mut vec![])?;
+        if covered != tokens.len() {
+            return Err(Error::InvalidTree(format!(
+                "Tree covers {} of {} tokens",
+                covered,
+                tokens.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Validates the node at `path`, whose leaves start at `position`, and
+    /// returns the position just past its last leaf.
+    fn validate_node(
+        &self,
+        grammar: &Grammar,
+        tokens: &[String],
+        position: usize,
+        path: &mut Vec<usize>,
+    ) -> Result<usize, Error> {
+        let invalid = |path: &[usize], msg: String| {
+            Error::InvalidTree(format!("{} at {:?} ({})", msg, path, self.production))
+        };
+
+        let exprs = self.production.rhs_iter().collect::<Vec<_>>();
+        if exprs.len() != 1 {
+            return Err(invalid(
+                path,
+                format!("Node has {} alternatives instead of one", exprs.len()),
+            ));
+        }
+        let terms = exprs[0].terms_iter().collect::<Vec<&Term>>();
+
+        let known = grammar
+            .productions_iter()
+            .any(|p| p.lhs == self.production.lhs && p.rhs_iter().any(|expr| expr == exprs[0]));
+        if !known {
+            return Err(invalid(path, "Production isn't in the grammar".to_string()));
+        }
+
+        if terms.len() != self.branches.len() {
+            return Err(invalid(
+                path,
+                format!(
+                    "Node has {} branches for {} terms",
+                    self.branches.len(),
+                    terms.len()
+                ),
+            ));
+        }
+
+        let mut position = position;
+        for (i, (term, branch)) in terms.iter().zip(self.branches.iter()).enumerate() {
+            path.push(i);
+            match (term, branch) {
+                (Term::Nonterminal(_), Branch::Nonterminal(tree)) => {
+                    if tree.production.lhs != **term {
+                        return Err(invalid(
+                            path,
+                            format!("Expected {} but found {}", term, tree.production.lhs),
+                        ));
+                    }
+                    position = tree.validate_node(grammar, tokens, position, path)?;
+                }
+                (Term::Terminal(expected), Branch::Terminal(found)) => {
+                    if expected != found {
+                        return Err(invalid(
+                            path,
+                            format!("Expected terminal {} but found {:?}", term, found),
+                        ));
+                    }
+                    match tokens.get(position) {
+                        Some(token) if token == found => position += 1,
+                        Some(token) => {
+                            return Err(invalid(
+                                path,
+                                format!(
+                                    "Terminal {:?} doesn't match token {:?} at position {}",
+                                    found, token, position
+                                ),
+                            ))
+                        }
+                        None => {
+                            return Err(invalid(
+                                path,
+                                format!(
+                                    "Terminal {:?} is past the end of the input at position {}",
+                                    found, position
+                                ),
+                            ))
+                        }
+                    }
+                }
+                (_, branch) => {
+                    return Err(invalid(
+                        path,
+                        format!("Expected {} but found {}", term, branch.describe()),
+                    ));
+                }
+            }
+            path.pop();
+        }
+
+        Ok(position)
+    }
+
     fn fmt(&self, depth: usize, bars: Vec<usize>, ppchar: PPChar) -> String {
         let mut value: String;
         let mut next_bars;
@@ -51,7 +337,9 @@ impl Tree {
 
 impl PartialEq for Tree {
     fn eq(&self, other: &Self) -> bool {
-        self.production == other.production && self.branches == other.branches
+        self.production == other.production
+            && self.branches == other.branches
+            && self.trivia == other.trivia
     }
 }
 
@@ -76,6 +364,13 @@ pub enum Branch {
 }
 
 impl Branch {
+    fn describe(&self) -> String {
+        match self {
+            Branch::Nonterminal(t) => t.production.lhs.to_string(),
+            Branch::Terminal(s) => format!("terminal {:?}", s),
+        }
+    }
+
     fn fmt(&self, depth: usize, bars: Vec<usize>, ppchar: PPChar) -> String {
         match self {
             Branch::Nonterminal(t) => t.fmt(depth, bars, ppchar),
@@ -103,3 +398,19 @@ impl fmt::Display for Tree {
         write!(f, "{}", self.fmt(0, vec![], PPChar::First))
     }
 }
+
+enum PPChar {
+    Last,
+    Mid,
+    First,
+}
+
+impl PPChar {
+    fn get(&self) -> String {
+        match self {
+            PPChar::Last => "└─".to_string(),
+            PPChar::Mid => "├─".to_string(),
+            PPChar::First => "└─".to_string(),
+        }
+    }
+}
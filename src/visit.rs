@@ -0,0 +1,231 @@
+use crate::tree::{Branch, Tree};
+use bnf::Term;
+use std::collections::{HashMap, VecDeque};
+
+/// Hooks for a depth-first walk over a `Tree`. `enter` runs before a
+/// nonterminal's branches are walked and `leave` after, both with the
+/// nonterminal's name (without the angle brackets) so implementations can
+/// dispatch on it.
+pub trait Visitor {
+    fn enter(&mut self, _name: &str, _tree: &Tree) {}
+    fn leave(&mut self, _name: &str, _tree: &Tree) {}
+    fn terminal(&mut self, _token: &str) {}
+}
+
+/// `Visitor` for walks that change the tree as they go. Changes made in
+/// `enter` are visible to the walk of the node's branches.
+pub trait VisitorMut {
+    fn enter(&mut self, _name: &str, _tree: &mut Tree) {}
+    fn leave(&mut self, _name: &str, _tree: &mut Tree) {}
+    fn terminal(&mut self, _token: &mut String) {}
+}
+
+type Hook<'a> = Box<dyn FnMut(&Tree) + 'a>;
+
+/// A `Visitor` built from closures registered per nonterminal name.
+#[derive(Default)]
+pub struct Hooks<'a> {
+    enter: HashMap<String, Hook<'a>>,
+    leave: HashMap<String, Hook<'a>>,
+}
+
+impl<'a> Hooks<'a> {
+    pub fn new() -> Hooks<'a> {
+        Hooks {
+            enter: HashMap::new(),
+            leave: HashMap::new(),
+        }
+    }
+
+    pub fn on_enter<F: FnMut(&Tree) + 'a>(mut self, name: &str, f: F) -> Hooks<'a> {
+        self.enter.insert(name.to_string(), Box::new(f));
+        self
+    }
+
+    pub fn on_leave<F: FnMut(&Tree) + 'a>(mut self, name: &str, f: F) -> Hooks<'a> {
+        self.leave.insert(name.to_string(), Box::new(f));
+        self
+    }
+}
+
+impl<'a> Visitor for Hooks<'a> {
+    fn enter(&mut self, name: &str, tree: &Tree) {
+        if let Some(f) = self.enter.get_mut(name) {
+            f(tree)
+        }
+    }
+
+    fn leave(&mut self, name: &str, tree: &Tree) {
+        if let Some(f) = self.leave.get_mut(name) {
+            f(tree)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NodeRef<'a> {
+    Nonterminal(&'a Tree),
+    Terminal(&'a str),
+}
+
+/// A node met by one of the tree iterators. `path` holds the index of the
+/// branch taken at every level from the root, so the root's path is empty
+/// and `depth == path.len()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Node<'a> {
+    pub node: NodeRef<'a>,
+    pub depth: usize,
+    pub path: Vec<usize>,
+}
+
+impl<'a> Node<'a> {
+    fn root(tree: &'a Tree) -> Node<'a> {
+        Node {
+            node: NodeRef::Nonterminal(tree),
+            depth: 0,
+            path: vec![],
+        }
+    }
+
+    fn children(&self) -> Vec<Node<'a>> {
+        match self.node {
+            NodeRef::Nonterminal(tree) => tree
+                .branches
+                .iter()
+                .enumerate()
+                .map(|(i, branch)| {
+                    let mut path = self.path.clone();
+                    path.push(i);
+                    Node {
+                        node: match branch {
+                            Branch::Nonterminal(t) => NodeRef::Nonterminal(t),
+                            Branch::Terminal(s) => NodeRef::Terminal(s),
+                        },
+                        depth: self.depth + 1,
+                        path,
+                    }
+                })
+                .collect(),
+            NodeRef::Terminal(_) => vec![],
+        }
+    }
+}
+
+pub struct Preorder<'a> {
+    stack: Vec<Node<'a>>,
+}
+
+impl<'a> Iterator for Preorder<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children().into_iter().rev());
+        Some(node)
+    }
+}
+
+pub struct Postorder<'a> {
+    stack: Vec<(Node<'a>, bool)>,
+}
+
+impl<'a> Iterator for Postorder<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        loop {
+            let (node, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(node);
+            }
+            let children = node.children();
+            self.stack.push((node, true));
+            self.stack
+                .extend(children.into_iter().rev().map(|child| (child, false)));
+        }
+    }
+}
+
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<Node<'a>>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children());
+        Some(node)
+    }
+}
+
+impl Tree {
+    /// The name of the tree's nonterminal without the angle brackets.
+    pub fn name(&self) -> &str {
+        match &self.production.lhs {
+            Term::Nonterminal(name) => name,
+            Term::Terminal(name) => name,
+        }
+    }
+
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        visitor.enter(self.name(), self);
+        for branch in &self.branches {
+            match branch {
+                Branch::Nonterminal(tree) => tree.walk(visitor),
+                Branch::Terminal(s) => visitor.terminal(s),
+            }
+        }
+        visitor.leave(self.name(), self);
+    }
+
+    pub fn walk_mut<V: VisitorMut>(&mut self, visitor: &mut V) {
+        let name = self.name().to_string();
+        visitor.enter(&name, self);
+        for branch in self.branches.iter_mut() {
+            match branch {
+                Branch::Nonterminal(tree) => tree.walk_mut(visitor),
+                Branch::Terminal(s) => visitor.terminal(s),
+            }
+        }
+        visitor.leave(&name, self);
+    }
+
+    pub fn preorder(&self) -> Preorder<'_> {
+        Preorder {
+            stack: vec![Node::root(self)],
+        }
+    }
+
+    pub fn postorder(&self) -> Postorder<'_> {
+        Postorder {
+            stack: vec![(Node::root(self), false)],
+        }
+    }
+
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst {
+            queue: vec![Node::root(self)].into_iter().collect(),
+        }
+    }
+
+    /// Evaluates the tree bottom up: every terminal leaf is turned into a
+    /// value by `terminal`, then every nonterminal by `nonterminal` from the
+    /// values of its branches, in order.
+    pub fn fold<T, N, L>(&self, nonterminal: &mut N, terminal: &mut L) -> T
+    where
+        N: FnMut(&Tree, Vec<T>) -> T,
+        L: FnMut(&str) -> T,
+    {
+        let values = self
+            .branches
+            .iter()
+            .map(|branch| match branch {
+                Branch::Nonterminal(tree) => tree.fold(nonterminal, terminal),
+                Branch::Terminal(s) => terminal(s),
+            })
+            .collect();
+        nonterminal(self, values)
+    }
+}
@@ -0,0 +1,205 @@
+extern crate earley;
+
+use earley::abnf::Abnf;
+use earley::earley::EarleyParser;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use std::fs;
+
+fn abnf(path: &str) -> Abnf {
+    Abnf::new(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn accepts(abnf: &Abnf, start: &str, input: &str) -> bool {
+    let grammar = abnf.to_grammar_from(start).unwrap();
+    match EarleyParser::from_grammar(grammar, input)
+        .earley_parse(None)
+        .unwrap()
+    {
+        EarleyOutcome::Accepted(_) => true,
+        EarleyOutcome::Rejected => false,
+    }
+}
+
+#[test]
+fn rfc3986_uri() {
+    let uri = abnf("tests/res/rfc3986.abnf");
+
+    assert!(accepts(&uri, "URI", "http://example.com/a/b?x=1#top"));
+    assert!(accepts(&uri, "URI", "mailto:someone@example.org"));
+    assert!(accepts(&uri, "URI", "ftp://user:pw@10.0.0.255:21/"));
+    assert!(accepts(&uri, "URI", "urn:isbn"));
+    assert!(accepts(&uri, "URI", "x:"));
+    assert!(accepts(&uri, "URI-reference", "../a%20b"));
+
+    assert!(!accepts(&uri, "URI", "1http://example.com"));
+    assert!(!accepts(&uri, "URI", "http://exa mple.com"));
+    assert!(!accepts(&uri, "URI", "http://a/%zz"));
+    assert!(!accepts(&uri, "URI", ""));
+}
+
+#[test]
+fn rfc3986_ip_addresses() {
+    let uri = abnf("tests/res/rfc3986.abnf");
+
+    assert!(accepts(&uri, "IPv4address", "192.168.0.1"));
+    assert!(accepts(&uri, "IPv4address", "255.255.255.255"));
+    assert!(!accepts(&uri, "IPv4address", "256.1.1.1"));
+    assert!(!accepts(&uri, "IPv4address", "1.1.1"));
+
+    assert!(accepts(&uri, "IPv6address", "::1"));
+    assert!(accepts(&uri, "IPv6address", "fe80::1:2"));
+    assert!(!accepts(&uri, "IPv6address", "1:2:3"));
+}
+
+#[test]
+fn rfc3339_date_time() {
+    let date_time = abnf("tests/res/rfc3339.abnf");
+
+    assert!(accepts(&date_time, "date-time", "1985-04-12T23:20:50.52Z"));
+    assert!(accepts(
+        &date_time,
+        "date-time",
+        "1996-12-19T16:39:57-08:00"
+    ));
+    // Quoted strings are case-insensitive.
+    assert!(accepts(&date_time, "date-time", "1990-12-31t23:59:60z"));
+
+    assert!(!accepts(&date_time, "date-time", "85-04-12T23:20:50Z"));
+    assert!(!accepts(&date_time, "date-time", "1985-04-12T23:20:50."));
+}
+
+#[test]
+fn rfc5234_parses_abnf() {
+    let abnf_of_abnf = abnf("tests/res/rfc5234.abnf");
+
+    assert!(accepts(&abnf_of_abnf, "rulelist", "a = b\r\n"));
+    assert!(accepts(
+        &abnf_of_abnf,
+        "rule",
+        "hex = %x30-39 / \"a\" ; digits\r\n"
+    ));
+    assert!(accepts(&abnf_of_abnf, "repeat", "1*4"));
+    assert!(!accepts(&abnf_of_abnf, "rulelist", "a = b"));
+    assert!(!accepts(&abnf_of_abnf, "rulename", "1a"));
+}
+
+#[test]
+fn grammar_from_first_rule() {
+    let abnf = Abnf::new(
+        "
+        greeting = hello 1*SP name
+        hello    = \"hello\"
+        hello    =/ %s\"Hi\"
+        name     = 1*ALPHA
+        ",
+    )
+    .unwrap();
+    let grammar = abnf.to_grammar().unwrap();
+    let accepts = |input: &str| {
+        EarleyParser::from_grammar(grammar.clone(), input)
+            .earley_parse(None)
+            .unwrap()
+            != EarleyOutcome::Rejected
+    };
+
+    assert!(accepts("hello world"));
+    assert!(accepts("HeLLo  world"));
+    assert!(accepts("Hi world"));
+    assert!(!accepts("hi world"));
+    assert!(!accepts("hello"));
+}
+
+#[test]
+fn helper_nonterminals_in_trees() {
+    let abnf = Abnf::new("number = 1*DIGIT [ \".\" 1*2DIGIT ]").unwrap();
+    let grammar = abnf.to_grammar().unwrap();
+    let outcome = EarleyParser::from_grammar(grammar, "12.5")
+        .earley_parse(None)
+        .unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].query("/number/<1*DIGIT>").unwrap().len(), 1);
+        assert_eq!(trees[0].query("/number/<1*2DIGIT>").unwrap().len(), 1);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn rfc8259_wide_ranges_for_an_input() {
+    let json = abnf("tests/res/rfc8259.abnf");
+    let accepts = |input: &str| {
+        json.parser(input).unwrap().earley_parse(None).unwrap() != EarleyOutcome::Rejected
+    };
+
+    assert!(accepts(r#""plain""#));
+    assert!(accepts("\"caf\\u00e9 \\\"quoted\\\" caf\u{e9} \u{1f600}\""));
+    assert!(accepts(r#""""#));
+    assert!(!accepts("\"tab\tinside\""));
+    assert!(!accepts(r#""unescaped " quote""#));
+    assert!(!accepts(r#""bad \x escape""#));
+
+    // A range only gets alternatives for the chars of the input, and one
+    // that gets none is left out.
+    let grammar = json.to_grammar_for("unescaped", "a\u{e9}\"").unwrap();
+    let alternatives: Vec<(String, usize)> = grammar
+        .productions_iter()
+        .map(|p| (p.lhs.to_string(), p.rhs_iter().count()))
+        .collect();
+    assert_eq!(
+        alternatives,
+        vec![
+            ("<unescaped>".to_string(), 1),
+            ("<%x5D-10FFFF>".to_string(), 2)
+        ]
+    );
+}
+
+#[test]
+fn input_without_chars_of_the_ranges() {
+    // The start rule is left without alternatives and matches nothing.
+    let word = Abnf::new("word = 1*ALPHA").unwrap();
+    for input in ["123", ""] {
+        let outcome = word.parser(input).unwrap().earley_parse(None).unwrap();
+        assert_eq!(outcome, EarleyOutcome::Rejected);
+    }
+}
+
+fn error(source: &str) -> String {
+    match Abnf::new(source).and_then(|abnf| abnf.to_grammar()) {
+        Err(Error::AbnfError(e)) => e,
+        other => panic!("expected AbnfError, got {:?}", other),
+    }
+}
+
+#[test]
+fn abnf_errors() {
+    assert_eq!(error("a = b"), "line 1: a uses b, which isn't defined");
+    assert_eq!(
+        error("a = \"x\"\nA = \"y\""),
+        "line 2: A is already defined on line 1, add alternatives with `=/`"
+    );
+    assert_eq!(
+        error("a =/ \"x\""),
+        "line 1: `=/` adds alternatives to a, which isn't defined yet"
+    );
+    assert_eq!(error("a = ( \"x\""), "line 1: expected `)`");
+    assert_eq!(error("a = \"x"), "line 1: missing closing `\"`");
+    assert_eq!(
+        error("a = <anything>"),
+        "Prose value <anything> can't be parsed"
+    );
+    assert_eq!(error("a = 0\"x\""), "Rule a only matches the empty string");
+    assert_eq!(
+        error("a = %x0-10FFFF"),
+        "Range %x0-10FFFF spans more than 1024 chars, build the grammar for an input"
+    );
+    assert_eq!(
+        error("  a = b\n b = \"x\""),
+        "line 2: rule starts left of the first one"
+    );
+    assert_eq!(error(""), "No rules found in ABNF");
+}
@@ -0,0 +1,96 @@
+extern crate earley;
+
+use earley::action::Actions;
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use std::cell::Cell;
+
+fn arithmetic<'a>() -> Actions<'a, i64> {
+    Actions::new(|token: &str| token.parse().unwrap_or(0))
+        .on("<S> ::= <S> '+' <M>", |v: &[i64]| v[0] + v[2])
+        .unwrap()
+        .on("<M> ::= <M> '*' <T>", |v: &[i64]| v[0] * v[2])
+        .unwrap()
+}
+
+#[test]
+fn actions_evaluate_wikipedia_example() {
+    let grammar_str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+    let values = EarleyChart::eval_with(grammar_str, "2+3*4", None, &arithmetic()).unwrap();
+    assert_eq!(values, vec![14]);
+
+    let values = EarleyChart::eval_with(grammar_str, "2+3*", None, &arithmetic()).unwrap();
+    assert!(values.is_empty());
+}
+
+#[test]
+fn ambiguous_input_has_one_value_per_derivation() {
+    let grammar_str = "
+    <E> ::= <E> '+' <E> | <E> '*' <E> | <N>
+    <N> ::= '1' | '2' | '3'
+    ";
+
+    let actions = Actions::new(|token: &str| token.parse().unwrap_or(0))
+        .on("<E> ::= <E> '+' <E>", |v: &[i64]| v[0] + v[2])
+        .unwrap()
+        .on("<E> ::= <E> '*' <E>", |v: &[i64]| v[0] * v[2])
+        .unwrap();
+
+    let mut values = EarleyChart::eval_with(grammar_str, "1+2*3", None, &actions).unwrap();
+    values.sort();
+    assert_eq!(values, vec![7, 9]);
+}
+
+#[test]
+fn shared_subtrees_run_their_actions_once() {
+    let grammar_str = "
+    <E> ::= <E> '+' <E> | <N>
+    <N> ::= '1'
+    ";
+
+    let sums = Cell::new(0);
+    let numbers = Cell::new(0);
+    let actions = Actions::new(|_: &str| 1)
+        .on("<E> ::= <E> '+' <E>", |v: &[i64]| {
+            sums.set(sums.get() + 1);
+            v[0] + v[2]
+        })
+        .unwrap()
+        .on("<N> ::= '1'", |v: &[i64]| {
+            numbers.set(numbers.get() + 1);
+            v[0]
+        })
+        .unwrap();
+
+    let values = EarleyChart::eval_with(grammar_str, "1+1+1+1", None, &actions).unwrap();
+    assert_eq!(values, vec![4; 5]);
+
+    // every one of the 5 trees has 3 sums and 4 numbers, but the forest
+    // shares them: 3 + 2 * 2 + 5 sums and one number per leaf
+    assert_eq!(sums.get(), 12);
+    assert_eq!(numbers.get(), 4);
+}
+
+#[test]
+fn missing_action_is_an_error() {
+    let grammar_str = "
+    <S> ::= <S> '+' <N> | <N>
+    <N> ::= '1'
+    ";
+
+    let actions = Actions::new(|_: &str| 1);
+    match EarleyChart::eval_with(grammar_str, "1+1", None, &actions) {
+        Err(Error::ActionError(_)) => {}
+        other => panic!("expected an ActionError, got {:?}", other),
+    }
+
+    assert!(Actions::new(|_: &str| 1)
+        .on("<S> ::= ", |v: &[i64]| v[0])
+        .is_err());
+}
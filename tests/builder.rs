@@ -0,0 +1,185 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::Grammar;
+use earley::builder::GrammarBuilder;
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+
+const WIKI: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+fn wiki() -> GrammarBuilder {
+    GrammarBuilder::new()
+        .rule("P", |r| r.nt("S"))
+        .rule("S", |r| r.nt("S").t("+").nt("M").or().nt("M"))
+        .rule("M", |r| r.nt("M").t("*").nt("T").or().nt("T"))
+        .rule("T", |r| r.t("1").or().t("2").or().t("3").or().t("4"))
+}
+
+fn message(result: Result<Grammar, Error>) -> String {
+    match result {
+        Err(Error::GrammarError(message)) => message,
+        other => panic!("expected a grammar error, got {:?}", other),
+    }
+}
+
+#[test]
+fn builder_matches_bnf() {
+    let grammar = wiki().build().unwrap();
+    let expected: Grammar = WIKI.parse().unwrap();
+    assert_eq!(grammar, expected);
+
+    let outcome = EarleyChart::eval_grammar(&grammar, "2+3*4", None).unwrap();
+    assert_eq!(outcome, EarleyChart::eval(WIKI, "2+3*4", None).unwrap());
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].yield_tokens(), vec!["2", "+", "3", "*", "4"]);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn builder_merges_rules_and_moves_start() {
+    let grammar = GrammarBuilder::new()
+        .rule("T", |r| r.t("1").or().t("2"))
+        .rule("S", |r| r.nt("S").t("+").nt("T"))
+        .rule("T", |r| r.t("3").or().t("1"))
+        .rule("S", |r| r.nt("T"))
+        .start("S")
+        .build()
+        .unwrap();
+    let expected: Grammar = "
+        <S> ::= <S> '+' <T> | <T>
+        <T> ::= '1' | '2' | '3' | '1'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(grammar, expected);
+    assert!(matches!(
+        EarleyChart::eval_grammar(&grammar, "3+2+1", None).unwrap(),
+        EarleyOutcome::Accepted(_)
+    ));
+}
+
+#[test]
+fn builder_from_config() {
+    // Rules as they might come out of a config file.
+    let config: Vec<(&str, Vec<Vec<&str>>)> = vec![
+        ("list", vec![vec!["<item>"], vec!["<item>", ",", "<list>"]]),
+        ("item", vec![vec!["a"], vec!["b"]]),
+    ];
+    let builder = config
+        .iter()
+        .fold(GrammarBuilder::new(), |builder, (name, alternatives)| {
+            builder.rule(name, |rule| {
+                alternatives
+                    .iter()
+                    .enumerate()
+                    .fold(rule, |rule, (i, terms)| {
+                        let rule = if i > 0 { rule.or() } else { rule };
+                        terms.iter().fold(rule, |rule, term| {
+                            if term.starts_with('<') && term.ends_with('>') && term.len() > 2 {
+                                rule.nt(&term[1..term.len() - 1])
+                            } else {
+                                rule.t(term)
+                            }
+                        })
+                    })
+            })
+        });
+    let grammar = builder.build().unwrap();
+    assert!(matches!(
+        EarleyChart::eval_grammar(&grammar, "a,b,a", None).unwrap(),
+        EarleyOutcome::Accepted(_)
+    ));
+    assert!(!matches!(
+        EarleyChart::eval_grammar(&grammar, "a,", None).unwrap(),
+        EarleyOutcome::Accepted(_)
+    ));
+}
+
+#[test]
+fn builder_names_groups() {
+    let grammar = GrammarBuilder::new()
+        .rule("E", |r| {
+            r.nt("E")
+                .group(|g| g.t("+").or().t("-"))
+                .nt("E.1")
+                .or()
+                .nt("E.1")
+        })
+        .rule("E.1", |r| {
+            r.t("x")
+                .or()
+                .group(|g| g.t("(").group(|g| g.nt("E")).t(")"))
+        })
+        .build()
+        .unwrap();
+
+    // `<E.1>` is taken, so the group in `<E>` is primed.
+    let expected: Grammar = "
+        <E> ::= <E> <E.1'> <E.1> | <E.1>
+        <E.1'> ::= '+' | '-'
+        <E.1> ::= 'x' | <E.1.1>
+        <E.1.1> ::= '(' <E.1.1.1> ')'
+        <E.1.1.1> ::= <E>
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(grammar, expected);
+
+    let outcome = EarleyChart::eval_grammar(&grammar, "x-(x+x)", None).unwrap();
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].yield_tokens().concat(), "x-(x+x)");
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn builder_errors() {
+    assert_eq!(
+        message(GrammarBuilder::new().build()),
+        "Grammar has no rules"
+    );
+    assert_eq!(
+        message(wiki().rule("", |r| r.t("x")).build()),
+        "Rule with an empty name"
+    );
+    assert_eq!(
+        message(wiki().rule("U", |r| r.t("x").or()).build()),
+        "Rule <U> has an empty alternative"
+    );
+    assert_eq!(
+        message(wiki().rule("U", |r| r.t("x").group(|g| g.nt(""))).build()),
+        "Rule <U> has an empty nonterminal name"
+    );
+    assert_eq!(
+        message(wiki().rule("U", |r| r.group(|g| g.t(""))).build()),
+        "Rule <U> has an empty terminal"
+    );
+    assert_eq!(
+        message(wiki().rule("U", |r| r.nt("V")).build()),
+        "Rule <U> uses <V>, which isn't defined"
+    );
+    assert_eq!(
+        message(wiki().start("U").build()),
+        "Start rule <U> isn't defined"
+    );
+
+    // The first error is the one reported.
+    assert_eq!(
+        message(wiki().rule("U", |r| r.or()).rule("V", |r| r.t("")).build()),
+        "Rule <U> has an empty alternative"
+    );
+}
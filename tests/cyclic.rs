@@ -0,0 +1,53 @@
+extern crate bnf;
+extern crate earley;
+extern crate rand;
+
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[test]
+fn unit_cycle_is_reported_not_overflowed() {
+    let grammar_str = "
+    <A> ::= <B>
+    <B> ::= <A> | 'x'
+    ";
+
+    let outcome = EarleyChart::eval(grammar_str, "x", None).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let forest = accepted.forest();
+        assert!(forest.is_cyclic());
+        assert_eq!(forest.nodes.iter().filter(|n| n.cyclic).count(), 2);
+
+        match accepted.parse_forest() {
+            Err(Error::ParseForestError(_)) => {}
+            other => panic!("expected a ParseForestError, got {:?}", other),
+        }
+
+        assert!(accepted.k_best(1, |_| 0.0).is_err());
+        assert!(accepted.sample(&mut StdRng::seed_from_u64(0)).is_err());
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn longer_cycle_through_other_spans() {
+    let grammar_str = "
+    <S> ::= <S> <S> | <T>
+    <T> ::= <U> | 'a'
+    <U> ::= <S>
+    ";
+
+    let outcome = EarleyChart::eval(grammar_str, "aa", None).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        assert!(accepted.forest().is_cyclic());
+        assert!(accepted.parse_forest().is_err());
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
@@ -0,0 +1,135 @@
+extern crate earley;
+
+use earley::ebnf::Ebnf;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use std::fs;
+
+fn xml() -> Ebnf {
+    Ebnf::new(&fs::read_to_string("tests/res/xml.ebnf").unwrap()).unwrap()
+}
+
+fn accepts(ebnf: &Ebnf, start: &str, input: &str) -> bool {
+    match ebnf
+        .parser_from(start, input)
+        .unwrap()
+        .earley_parse(None)
+        .unwrap()
+    {
+        EarleyOutcome::Accepted(_) => true,
+        EarleyOutcome::Rejected => false,
+    }
+}
+
+#[test]
+fn xml_names_and_classes() {
+    let xml = xml();
+
+    assert!(accepts(&xml, "Name", "xsl:template"));
+    assert!(accepts(&xml, "Name", "_x.1-é"));
+    assert!(accepts(&xml, "Name", "名前"));
+    assert!(!accepts(&xml, "Name", "1abc"));
+    assert!(!accepts(&xml, "Name", "a b"));
+
+    assert!(accepts(&xml, "CharRef", "&#x1F600;"));
+    assert!(!accepts(&xml, "CharRef", "&#xZ;"));
+}
+
+#[test]
+fn xml_negated_classes() {
+    let xml = xml();
+
+    assert!(accepts(&xml, "AttValue", "\"a 'b' &amp; c\""));
+    assert!(accepts(&xml, "AttValue", "'say \"hi\"'"));
+    assert!(!accepts(&xml, "AttValue", "\"a < b\""));
+    assert!(!accepts(&xml, "AttValue", "\"a & b\""));
+}
+
+#[test]
+fn xml_exclusions() {
+    let xml = xml();
+
+    assert!(accepts(&xml, "Comment", "<!-- a - b -->"));
+    assert!(accepts(&xml, "Comment", "<!---->"));
+    assert!(!accepts(&xml, "Comment", "<!-- a -- b -->"));
+    assert!(!accepts(&xml, "Comment", "<!-- a --->"));
+
+    assert!(accepts(&xml, "CDSect", "<![CDATA[<b>x</b>]]>"));
+    assert!(accepts(&xml, "CDSect", "<![CDATA[]]>"));
+    assert!(!accepts(&xml, "CDSect", "<![CDATA[a]]>b]]>"));
+
+    assert!(accepts(&xml, "PI", "<?php echo 1 ?>"));
+    assert!(accepts(&xml, "PI", "<?xml-stylesheet?>"));
+    assert!(!accepts(&xml, "PI", "<?xml version=\"1.0\"?>"));
+    assert!(!accepts(&xml, "PI", "<?XmL?>"));
+}
+
+#[test]
+fn grammar_without_exclusions() {
+    let grammar = xml().to_grammar_from("PITarget", "xml").unwrap();
+    let outcome = earley::earley::EarleyParser::from_grammar(grammar, "xml")
+        .earley_parse(None)
+        .unwrap();
+    assert_ne!(outcome, EarleyOutcome::Rejected);
+}
+
+#[test]
+fn repetition_and_options() {
+    let ebnf = Ebnf::new(
+        "
+        list ::= '(' item (',' item)* ')'   /* a list */
+        item ::= [0-9]+ ('.' [0-9]+)? | 'x'?'y'
+        ",
+    )
+    .unwrap();
+
+    assert!(accepts(&ebnf, "list", "(1)"));
+    assert!(accepts(&ebnf, "list", "(1,2.50,xy,y)"));
+    assert!(!accepts(&ebnf, "list", "()"));
+    assert!(!accepts(&ebnf, "list", "(1.)"));
+    assert!(!accepts(&ebnf, "list", "(1,)"));
+
+    let parser = ebnf.parser("(2)").unwrap();
+    if let EarleyOutcome::Accepted(accepted) = parser.earley_parse(None).unwrap() {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].query("//<[0-9]+>/<[0-9]>").unwrap().len(), 1);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+fn error(source: &str) -> String {
+    match Ebnf::new(source).and_then(|ebnf| ebnf.parser("x")) {
+        Err(Error::EbnfError(e)) => e,
+        Err(e) => panic!("expected EbnfError, got {:?}", e),
+        Ok(_) => panic!("expected EbnfError"),
+    }
+}
+
+#[test]
+fn ebnf_errors() {
+    assert_eq!(error("a ::= b"), "line 1: a uses b, which isn't defined");
+    assert_eq!(
+        error("a ::= 'x'\na ::= 'y'"),
+        "line 2: a is already defined on line 1"
+    );
+    assert_eq!(error("a ::= ('x'"), "line 1: expected `)`");
+    assert_eq!(error("a ::= 'x"), "line 1: missing closing `'`");
+    assert_eq!(error("a ::= [z-a]"), "line 1: range ends before it starts");
+    assert_eq!(error("a 'x'"), "line 1: expected `::=` after a");
+    assert_eq!(error("/* nothing */"), "No rules found in EBNF");
+}
+
+#[test]
+fn cdata_with_brackets() {
+    let xml = xml();
+    let data = "<b>]] ></b>";
+
+    assert!(accepts(&xml, "CDSect", &format!("<![CDATA[{}]]>", data)));
+    assert!(!accepts(
+        &xml,
+        "CDSect",
+        &format!("<![CDATA[{}]]>{}]]>", data, data)
+    ));
+}
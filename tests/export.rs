@@ -0,0 +1,169 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::Grammar;
+use earley::abnf::Abnf;
+use earley::chart::EarleyChart;
+use earley::earley::EarleyParser;
+use earley::ebnf::Ebnf;
+use earley::export;
+use earley::outcome::EarleyOutcome;
+
+const WIKI: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+/// Nonterminal names and terminals each of the notations has to rewrite.
+const AWKWARD: &str = "
+    <start rule> ::= <1*x> | <X> <x> | 'a' 'b' <start rule>
+    <1*x>        ::= 'x' | 'x' <1*x>
+    <X>          ::= 'Q' | \"'\" | ' '
+    <x>          ::= 'q'
+    <X>          ::= '\"'
+    ";
+
+fn accepts(grammar: Grammar, input: &str) -> bool {
+    EarleyParser::from_grammar(grammar, input)
+        .earley_parse(None)
+        .unwrap()
+        != EarleyOutcome::Rejected
+}
+
+const SENTENCES: &[&str] = &[
+    "x", "xxx", "Qq", "'q", " q", "abx", "ababQq", "\"q", "ab\"q", "qQ", "Xx", "ab", "",
+];
+
+/// Terminals longer than a char, which EBNF and ABNF spell out as strings.
+const STRINGS: &str = "<s> ::= \"it's\" | '\"q\"' | 'a\"b' \"'\"";
+
+#[test]
+fn bnf_export() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    assert_eq!(
+        export::to_bnf(&grammar),
+        "<P> ::= <S>\n\
+         <S> ::= <S> \"+\" <M> | <M>\n\
+         <M> ::= <M> \"*\" <T> | <T>\n\
+         <T> ::= \"1\" | \"2\" | \"3\" | \"4\"\n"
+    );
+    assert_eq!(
+        export::to_bnf(&grammar).parse::<Grammar>().unwrap(),
+        grammar
+    );
+
+    // Productions sharing a left hand side end up on one line.
+    let awkward: Grammar = AWKWARD.parse().unwrap();
+    let exported = export::to_bnf(&awkward);
+    assert!(exported.contains("<X>          ::= \"Q\" | \"'\" | \" \" | '\"'\n"));
+    assert_eq!(exported.lines().count(), 4);
+    for sentence in SENTENCES {
+        assert_eq!(
+            accepts(exported.parse().unwrap(), sentence),
+            accepts(awkward.clone(), sentence),
+            "{:?}",
+            sentence
+        );
+    }
+}
+
+#[test]
+fn ebnf_export() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    assert_eq!(
+        export::to_ebnf(&grammar),
+        "P ::= S\n\
+         S ::= S '+' M | M\n\
+         M ::= M '*' T | T\n\
+         T ::= '1' | '2' | '3' | '4'\n"
+    );
+
+    let strings: Grammar = STRINGS.parse().unwrap();
+    assert_eq!(
+        export::to_ebnf(&strings),
+        "s ::= \"it's\" | '\"q\"' | 'a\"b' \"'\"\n"
+    );
+
+    let awkward: Grammar = AWKWARD.parse().unwrap();
+    let ebnf = Ebnf::new(&export::to_ebnf(&awkward)).unwrap();
+    for sentence in SENTENCES {
+        let exported = match ebnf.parser(sentence).unwrap().earley_parse(None).unwrap() {
+            EarleyOutcome::Accepted(_) => true,
+            EarleyOutcome::Rejected => false,
+        };
+        assert_eq!(
+            exported,
+            accepts(awkward.clone(), sentence),
+            "{:?}",
+            sentence
+        );
+    }
+}
+
+#[test]
+fn abnf_export() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    assert_eq!(
+        export::to_abnf(&grammar),
+        "P = S\n\
+         S = S \"+\" M / M\n\
+         M = M \"*\" T / T\n\
+         T = \"1\" / \"2\" / \"3\" / \"4\"\n"
+    );
+
+    let strings: Grammar = STRINGS.parse().unwrap();
+    assert_eq!(
+        export::to_abnf(&strings),
+        "s = %s\"it's\" / %x22.71.22 / %x61.22.62 \"'\"\n"
+    );
+
+    // `<X>` and `<x>` are the same rule name in ABNF, so one is renamed,
+    // and `'Q'` doesn't match `q`.
+    let awkward: Grammar = AWKWARD.parse().unwrap();
+    let exported = Abnf::new(&export::to_abnf(&awkward))
+        .unwrap()
+        .to_grammar()
+        .unwrap();
+    for sentence in SENTENCES {
+        assert_eq!(
+            accepts(exported.clone(), sentence),
+            accepts(awkward.clone(), sentence),
+            "{:?}",
+            sentence
+        );
+    }
+}
+
+#[test]
+fn json_export() {
+    let grammar: Grammar = AWKWARD.parse().unwrap();
+    let json = export::to_json(&grammar);
+    assert_eq!(export::from_json(&json).unwrap(), grammar);
+    assert!(export::from_json("{\"productions\": 1}").is_err());
+
+    let outcome = EarleyChart::eval_grammar(&export::from_json(&json).unwrap(), "xx", None);
+    assert!(matches!(outcome, Ok(EarleyOutcome::Accepted(_))));
+}
+
+#[test]
+fn railroad_export() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+
+    let svg = export::railroad_svg(&grammar, "S").unwrap();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect").count(), 4);
+    assert!(svg.contains("<a href=\"#M\">"));
+    assert!(svg.contains(">+</text>"));
+    assert!(export::railroad_svg(&grammar, "Q").is_none());
+
+    let awkward: Grammar = AWKWARD.parse().unwrap();
+    let html = export::railroad_html(&awkward);
+    assert_eq!(html.matches("<h2 id=").count(), 4);
+    assert_eq!(html.matches("<svg").count(), 4);
+    assert!(html.contains("<h2 id=\"start_rule\">start rule</h2>"));
+    assert!(html.contains("<a href=\"#start_rule\">"));
+    assert!(html.contains(">&quot;</text>"));
+}
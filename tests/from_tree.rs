@@ -0,0 +1,112 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::from_tree::FromTree;
+use earley::outcome::EarleyOutcome;
+use earley::tree::{Branch, Tree};
+
+const GRAMMAR: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+#[derive(FromTree, Debug, PartialEq)]
+#[earley(rule = "<P> ::= <S>")]
+struct Program(Sum);
+
+#[derive(FromTree, Debug, PartialEq)]
+enum Sum {
+    #[earley(rule = "<S> ::= <S> '+' <M>")]
+    Add(Box<Sum>, Product),
+    #[earley(rule = "<S> ::= <M>")]
+    Product(Product),
+}
+
+#[derive(FromTree, Debug, PartialEq)]
+enum Product {
+    #[earley(rule = "<M> ::= <M> '*' <T>")]
+    Mul {
+        lhs: Box<Product>,
+        #[earley(terminal)]
+        op: char,
+        rhs: Digit,
+    },
+    #[earley(rule = "<M> ::= <T>")]
+    Digit(Digit),
+}
+
+#[derive(FromTree, Debug, PartialEq)]
+#[earley(rule = "<T> ::= '1' | '2' | '3' | '4'")]
+struct Digit(#[earley(terminal)] i64);
+
+fn parse(sentence: &str) -> Tree {
+    match EarleyChart::eval(GRAMMAR, sentence, None).unwrap() {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap().remove(0),
+        EarleyOutcome::Rejected => panic!("{} rejected", sentence),
+    }
+}
+
+#[test]
+fn derive_builds_typed_ast() {
+    let program = Program::from_tree(&parse("2+3*4")).unwrap();
+
+    assert_eq!(
+        program,
+        Program(Sum::Add(
+            Box::new(Sum::Product(Product::Digit(Digit(2)))),
+            Product::Mul {
+                lhs: Box::new(Product::Digit(Digit(3))),
+                op: '*',
+                rhs: Digit(4),
+            },
+        ))
+    );
+}
+
+#[test]
+fn derive_reports_shape_mismatch() {
+    let tree = parse("2+3");
+    match Product::from_tree(&tree) {
+        Err(Error::InvalidTree(msg)) => {
+            assert!(
+                msg.contains("Can't build Product from <P> ::= <S>"),
+                "{}",
+                msg
+            );
+            assert!(msg.contains("<M> ::= <M> '*' <T>"), "{}", msg);
+        }
+        other => panic!("expected an InvalidTree error, got {:?}", other),
+    }
+
+    // a <T> leaf that isn't a number fails inside the field that wanted it
+    let mut tree = parse("2");
+    fn rename(tree: &mut Tree) {
+        for branch in tree.branches.iter_mut() {
+            match branch {
+                Branch::Nonterminal(t) => rename(t),
+                Branch::Terminal(s) => *s = "two".to_string(),
+            }
+        }
+    }
+    rename(&mut tree);
+    match Program::from_tree(&tree) {
+        Err(Error::InvalidTree(msg)) => {
+            assert!(msg.contains("Program.0"), "{}", msg);
+            assert!(
+                msg.contains("Digit.0: can't read terminal \"two\""),
+                "{}",
+                msg
+            );
+        }
+        other => panic!("expected an InvalidTree error, got {:?}", other),
+    }
+}
+
+#[test]
+fn string_reads_the_leaves() {
+    let tree = parse("2+3*4");
+    assert_eq!(String::from_tree(&tree).unwrap(), "2+3*4");
+}
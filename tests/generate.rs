@@ -0,0 +1,210 @@
+extern crate earley;
+extern crate rand;
+
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::generate::Generator;
+use earley::outcome::EarleyOutcome;
+use earley::tree::{Branch, Tree};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const WIKI: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+const AMBIGUOUS: &str = "
+    <E> ::= <E> '+' <E> | '(' <E> ')' | '1'
+    ";
+
+const WORDS: &str = "
+    <sentence> ::= <subject> 'likes' <object>
+    <subject> ::= 'alice' | 'bob' | <subject> 'and' <subject>
+    <object> ::= 'tea' | 'cake'
+    ";
+
+fn depth(tree: &Tree) -> usize {
+    let below = tree.branches.iter().map(|branch| match branch {
+        Branch::Nonterminal(tree) => depth(tree),
+        _ => 0,
+    });
+    1 + below.max().unwrap_or(0)
+}
+
+/// Checks the parser accepts every generated sentence, with the tree that
+/// generated it among its parses.
+fn round_trip(grammar: &str, split_on: Option<char>, seeds: u64) {
+    let generator = Generator::new(grammar).unwrap().with_max_length(12);
+    let separator = split_on.map(String::from).unwrap_or_default();
+    for seed in 0..seeds {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let tree = generator.generate(&mut rng).unwrap();
+        let input = tree.yield_tokens().join(&separator);
+        assert!(EarleyChart::accept(grammar, &input, split_on).unwrap());
+
+        let outcome = EarleyChart::eval(grammar, &input, split_on).unwrap();
+        if let EarleyOutcome::Accepted(accepted) = outcome {
+            let forest = accepted.parse_forest().unwrap();
+            assert!(forest.contains(&tree), "{}:\n{}", input, tree);
+        } else {
+            assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+        }
+    }
+}
+
+#[test]
+fn generate_sentences_parse_to_their_trees() {
+    round_trip(WIKI, None, 50);
+    round_trip(AMBIGUOUS, None, 50);
+    round_trip(WORDS, Some(' '), 50);
+}
+
+#[test]
+fn generate_within_limits() {
+    let generator = Generator::new(AMBIGUOUS)
+        .unwrap()
+        .with_max_length(7)
+        .with_max_depth(4);
+    for seed in 0..100 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let tree = generator.generate(&mut rng).unwrap();
+        assert!(tree.yield_tokens().len() <= 7, "{}", tree);
+        assert!(depth(&tree) <= 4, "{}", tree);
+    }
+
+    // `<P>` needs four levels and a token at least.
+    let mut rng = StdRng::seed_from_u64(0);
+    let generator = Generator::new(WIKI).unwrap();
+    assert!(generator.generate(&mut rng).is_some());
+    let generator = Generator::new(WIKI).unwrap().with_max_length(0);
+    assert!(generator.generate(&mut rng).is_none());
+    let generator = Generator::new(WIKI).unwrap().with_max_depth(3);
+    assert!(generator.generate(&mut rng).is_none());
+    let generator = Generator::new(WIKI).unwrap().with_max_depth(4);
+    assert_eq!(depth(&generator.generate(&mut rng).unwrap()), 4);
+}
+
+#[test]
+fn generate_within_both_limits_at_once() {
+    // `<X>` is short enough or shallow enough for the limits, never both.
+    let generator = Generator::new(
+        "
+        <S>  ::= <X> | 'a'
+        <X>  ::= <D1> | 'b' 'b' 'b' 'b' 'b'
+        <D1> ::= <D2>
+        <D2> ::= 'c'
+        ",
+    )
+    .unwrap()
+    .with_max_depth(2)
+    .with_max_length(2);
+    for seed in 0..100 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let tree = generator.generate(&mut rng).unwrap();
+        assert_eq!(tree.yield_tokens(), ["a"]);
+    }
+}
+
+#[test]
+fn generate_with_weights() {
+    let generator = Generator::new(WIKI)
+        .unwrap()
+        .with_weight("<S> ::= <S> '+' <M>", 0.0)
+        .unwrap();
+    for seed in 0..50 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let tokens = generator.generate(&mut rng).unwrap().yield_tokens();
+        assert!(!tokens.contains(&"+".to_string()), "{:?}", tokens);
+    }
+
+    // Only `'1'` is left for `<T>`.
+    let generator = Generator::new(WIKI)
+        .unwrap()
+        .with_weight("<T> ::= '2' | '3' | '4'", 0.0)
+        .unwrap();
+    for seed in 0..50 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let tokens = generator.generate(&mut rng).unwrap().yield_tokens();
+        assert!(
+            tokens.iter().all(|t| t == "1" || t == "+" || t == "*"),
+            "{:?}",
+            tokens
+        );
+    }
+
+    // With no weight left on its way out, `<E>` derives nothing.
+    let generator = Generator::new(AMBIGUOUS)
+        .unwrap()
+        .with_weight("<E> ::= '1'", 0.0)
+        .unwrap();
+    let mut rng = StdRng::seed_from_u64(0);
+    assert!(generator.generate(&mut rng).is_none());
+    assert!(generator.enumerate(5).is_empty());
+
+    assert!(matches!(
+        Generator::new(WIKI).unwrap().with_weight("<S> ::=", 1.0),
+        Err(Error::BnfError(_))
+    ));
+    for production in ["<Sum> ::= <S> '+' <M>", "<S> ::= <S> '-' <M>"] {
+        assert!(matches!(
+            Generator::new(WIKI).unwrap().with_weight(production, 0.0),
+            Err(Error::GrammarError(_))
+        ));
+    }
+}
+
+#[test]
+fn generate_is_reproducible_from_seed() {
+    let generator = Generator::new(AMBIGUOUS).unwrap();
+    let draw = |seed: u64| -> Vec<Tree> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..10)
+            .map(|_| generator.generate(&mut rng).unwrap())
+            .collect()
+    };
+    assert_eq!(draw(3), draw(3));
+}
+
+#[test]
+fn generate_enumerates_every_sentence_in_order() {
+    let generator = Generator::new(WIKI).unwrap();
+    let enumerated: Vec<String> = generator
+        .enumerate(3)
+        .iter()
+        .map(|sentence| sentence.concat())
+        .collect();
+
+    // Every string over the alphabet up to three tokens the parser accepts.
+    let alphabet = ["1", "2", "3", "4", "+", "*"];
+    let mut strings: Vec<String> = vec![String::new()];
+    let mut expected = vec![];
+    for _ in 0..3 {
+        strings = strings
+            .iter()
+            .flat_map(|s| alphabet.iter().map(move |c| format!("{}{}", s, c)))
+            .collect();
+        let mut accepted: Vec<String> = strings
+            .iter()
+            .filter(|s| EarleyChart::accept(WIKI, s, None).unwrap())
+            .cloned()
+            .collect();
+        accepted.sort();
+        expected.extend(accepted);
+    }
+    assert_eq!(enumerated, expected);
+    assert_eq!(enumerated[..4], ["1", "2", "3", "4"]);
+    assert_eq!(enumerated.len(), 4 + 2 * 16);
+
+    let generator = Generator::new(WORDS).unwrap();
+    let sentences: Vec<String> = generator
+        .enumerate(5)
+        .iter()
+        .map(|sentence| sentence.join(" "))
+        .collect();
+    assert_eq!(sentences.len(), 4 + 8);
+    assert_eq!(sentences[0], "alice likes cake");
+    assert!(sentences.contains(&"bob and alice likes tea".to_string()));
+}
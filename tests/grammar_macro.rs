@@ -0,0 +1,72 @@
+extern crate bnf;
+extern crate earley;
+extern crate trybuild;
+
+use bnf::Grammar;
+use earley::chart::EarleyChart;
+use earley::grammar::{grammar, StaticGrammar, StaticTerm};
+use earley::outcome::EarleyOutcome;
+
+static WIKI: StaticGrammar = grammar! {
+    <P> ::= <S>
+    <S> ::= <S> "+" <M> | <M>
+    <M> ::= <M> "*" <T> | <T>
+    <T> ::= "1" | "2" | "3" | "4"
+};
+
+const WIKI_BNF: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+#[test]
+fn static_grammar_matches_bnf() {
+    let grammar: Grammar = WIKI_BNF.parse().unwrap();
+    assert_eq!(WIKI.to_grammar(), grammar);
+    assert_eq!(WIKI.productions[3].lhs, "T");
+    assert_eq!(
+        WIKI.productions[1].alternatives[0][1],
+        StaticTerm::Terminal("+")
+    );
+}
+
+#[test]
+fn static_grammar_parses_like_bnf() {
+    let outcome = EarleyChart::eval_grammar(&WIKI.to_grammar(), "2+3*4", None).unwrap();
+    let expected = EarleyChart::eval(WIKI_BNF, "2+3*4", None).unwrap();
+    assert_eq!(outcome, expected);
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].yield_tokens(), vec!["2", "+", "3", "*", "4"]);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn static_grammar_names_and_separators() {
+    static STATEMENT: StaticGrammar = grammar! {
+        <statement list> ::= <statement> | <statement> <statement list>;
+        <statement> ::= "let" <name> '=' <name> ';';
+        <name> ::= "x" | "y";
+    };
+
+    assert_eq!(STATEMENT.productions[0].lhs, "statement list");
+    let outcome = EarleyChart::eval_grammar(
+        &STATEMENT.to_grammar(),
+        "let x = y ; let y = x ;",
+        Some(' '),
+    )
+    .unwrap();
+    assert!(matches!(outcome, EarleyOutcome::Accepted(_)));
+}
+
+#[test]
+fn grammar_errors_fail_to_compile() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/grammar_*.rs");
+}
@@ -0,0 +1,93 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::Production;
+use earley::chart::EarleyChart;
+use earley::outcome::EarleyOutcome;
+use earley::tree::Branch;
+
+const PP_ATTACHMENT: &str = "
+    <S> ::= <NP> <VP>
+    <VP> ::= <V> <NP> | <V> <NP> <PP>
+    <NP> ::= <N> | <N> <PP>
+    <PP> ::= <P> <NP>
+    <N> ::= 'Garnet' | 'Peridot' | 'binoculars'
+    <V> ::= 'saw'
+    <P> ::= 'with'
+    ";
+
+fn production(s: &str) -> Production {
+    s.parse().unwrap()
+}
+
+#[test]
+fn k_best_ranks_by_score() {
+    let verb_attached = production("<VP> ::= <V> <NP> <PP>");
+    let noun_attached = production("<NP> ::= <N> <PP>");
+
+    let outcome = EarleyChart::eval(
+        PP_ATTACHMENT,
+        "Garnet saw Peridot with binoculars",
+        Some(' '),
+    )
+    .unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let score = |p: &Production| {
+            if *p == verb_attached {
+                2.0
+            } else if *p == noun_attached {
+                1.0
+            } else {
+                0.0
+            }
+        };
+
+        let best = accepted.k_best(5, score).unwrap();
+        assert_eq!(best.len(), 2);
+        assert_eq!(best[0].0, 2.0);
+        assert_eq!(best[1].0, 1.0);
+
+        let vp = |tree: &earley::tree::Tree| match &tree.branches[1] {
+            Branch::Nonterminal(t) => t.production.clone(),
+            Branch::Terminal(_) => panic!("expected <VP>"),
+        };
+        assert_eq!(vp(&best[0].1), verb_attached);
+        assert_ne!(vp(&best[1].1), verb_attached);
+
+        let flipped = accepted.k_best(1, |p| -score(p)).unwrap();
+        assert_eq!(flipped.len(), 1);
+        assert_eq!(flipped[0].0, -1.0);
+        assert_eq!(flipped[0].1, best[1].1);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn k_best_enumerates_each_derivation_once() {
+    let grammar_str = "
+    <E> ::= <E> '+' <E> | '1'
+    ";
+
+    let outcome = EarleyChart::eval(grammar_str, "1+1+1+1", None).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.k_best(10, |_| 1.0).unwrap();
+
+        // Catalan(3) ways of bracketing four operands, each with 7 nodes
+        assert_eq!(trees.len(), 5);
+        for (score, _) in &trees {
+            assert_eq!(*score, 7.0);
+        }
+        for (i, (_, a)) in trees.iter().enumerate() {
+            for (_, b) in trees.iter().skip(i + 1) {
+                assert_ne!(a, b);
+            }
+        }
+
+        assert_eq!(accepted.k_best(3, |_| 1.0).unwrap().len(), 3);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
@@ -0,0 +1,78 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::layout::Layout;
+use earley::outcome::EarleyOutcome;
+
+const IF_ELSE: &str = "
+    <Block> ::= <If> | '{' '}'
+    <If>    ::= <if> <Block> | <if> <Block> <else> <Block>
+    <if>    ::= 'i' 'f'
+    <else>  ::= 'e' 'l' 's' 'e'
+    <ws>    ::= <space> | <space> <ws>
+    <space> ::= ' ' | '
+'
+    ";
+
+fn keywords() -> Layout {
+    Layout::new("ws").lexical("if").lexical("else")
+}
+
+fn accepts(sentence: &str) -> bool {
+    match EarleyChart::eval_layout(IF_ELSE, sentence, None, keywords()).unwrap() {
+        EarleyOutcome::Accepted(_) => true,
+        EarleyOutcome::Rejected => false,
+    }
+}
+
+#[test]
+fn layout_between_tokens() {
+    assert!(accepts("ifif{}else{}"));
+    assert!(accepts("if if {} else {}"));
+    assert!(accepts("if  if {\n} else   { }"));
+    assert!(accepts("if\nif{}\nelse\n{}"));
+}
+
+#[test]
+fn layout_around_input() {
+    assert!(accepts(" if if {} else {}"));
+    assert!(accepts("if if {} else {}\n"));
+    assert!(accepts("\n  if if {} else {}  \n"));
+    assert!(accepts("  {}  "));
+}
+
+#[test]
+fn no_layout_in_lexical_productions() {
+    assert!(!accepts("i f if {} else {}"));
+    assert!(!accepts("if if {} el se {}"));
+    assert!(!accepts("if if {} else {} x"));
+    assert!(!accepts("   "));
+}
+
+#[test]
+fn layout_left_out_of_trees() {
+    let spaced = EarleyChart::eval_layout(IF_ELSE, " if if { } else {}\n", None, keywords());
+    let packed = EarleyChart::eval_layout(IF_ELSE, "ifif{}else{}", None, keywords());
+
+    if let (Ok(EarleyOutcome::Accepted(spaced)), Ok(EarleyOutcome::Accepted(packed))) =
+        (spaced, packed)
+    {
+        let trees = spaced.parse_forest().unwrap();
+        assert_eq!(trees.len(), 2);
+        assert_eq!(trees, packed.parse_forest().unwrap());
+        for tree in &trees {
+            assert!(tree.query("//ws").unwrap().is_empty());
+        }
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn undefined_layout_nonterminal() {
+    match EarleyChart::eval_layout(IF_ELSE, "if {}", None, Layout::new("blank")) {
+        Err(Error::GrammarError(_)) => {}
+        other => panic!("expected GrammarError, got {:?}", other),
+    }
+}
@@ -0,0 +1,107 @@
+extern crate bnf;
+extern crate earley;
+extern crate serde_json;
+
+use bnf::Grammar;
+use earley::chart::EarleyChart;
+use earley::outcome::{EarleyAccepted, EarleyOutcome};
+use earley::token::Trivia;
+use earley::tree::{Branch, Tree};
+
+const STATEMENTS: &str = "
+    <prog> ::= <stmt> | <stmt> <prog>
+    <stmt> ::= 'let' <name> '=' <name> ';'
+    <name> ::= 'x' | 'y'
+    ";
+
+const WIKI: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+fn accepted(outcome: EarleyOutcome) -> EarleyAccepted {
+    match outcome {
+        EarleyOutcome::Accepted(accepted) => *accepted,
+        EarleyOutcome::Rejected => panic!("input rejected"),
+    }
+}
+
+#[test]
+fn whitespace_and_comments_round_trip() {
+    let input = "  # header\nlet x = y ;  # trailing\n\n  let  y\t= x ; ";
+    let trivia = Trivia::whitespace().line_comment("#");
+    let accepted = accepted(EarleyChart::eval_lossless(STATEMENTS, input, &trivia).unwrap());
+    assert_eq!(
+        accepted.input,
+        vec!["let", "x", "=", "y", ";", "let", "y", "=", "x", ";"]
+    );
+
+    let trees = accepted.lossless_parse_forest().unwrap();
+    assert_eq!(trees.len(), 1);
+    assert_eq!(trees[0].to_source(), input);
+    assert_eq!(trees[0].yield_tokens(), accepted.input);
+
+    let tokens = trees[0].tokens();
+    assert_eq!(tokens[0].leading, "  # header\n");
+    assert_eq!(tokens[0].trailing, " ");
+    assert_eq!(tokens[4].text, ";");
+    assert_eq!(tokens[4].trailing, "  # trailing\n");
+    assert_eq!(tokens[5].leading, "\n  ");
+    assert_eq!(tokens[6].trailing, "\t");
+    assert_eq!(tokens[9].trailing, " ");
+
+    let grammar: Grammar = STATEMENTS.parse().unwrap();
+    assert_eq!(trees[0].validate(&grammar, &accepted.input), Ok(()));
+}
+
+#[test]
+fn char_tokens_with_block_comments() {
+    let input = " 2 + 3 /* three,\n  really */ * 4\n";
+    let trivia = Trivia::whitespace().chars().block_comment("/*", "*/");
+    let accepted = accepted(EarleyChart::eval_lossless(WIKI, input, &trivia).unwrap());
+    assert_eq!(accepted.input, vec!["2", "+", "3", "*", "4"]);
+
+    let tree = accepted.lossless_parse_forest().unwrap().remove(0);
+    assert_eq!(tree.to_source(), input);
+
+    let tokens = tree.tokens();
+    assert_eq!(tokens[0].leading, " ");
+    assert_eq!(tokens[2].trailing, " /* three,\n  really */ ");
+    assert_eq!(tokens[3].leading, "");
+    assert_eq!(tokens[4].trailing, "\n");
+}
+
+#[test]
+fn split_on_separators_are_trivia() {
+    let input = "2 + 3 * 4";
+    let outcome = EarleyChart::eval(WIKI, input, Some(' ')).unwrap();
+    let accepted = accepted(outcome);
+
+    let lossless = accepted.lossless_parse_forest().unwrap();
+    let plain = accepted.parse_forest().unwrap();
+    assert_eq!(lossless.len(), plain.len());
+    assert_eq!(lossless[0].to_source(), input);
+    assert_eq!(plain[0].to_source(), "2+3*4");
+    assert_eq!(lossless[0].yield_tokens(), plain[0].yield_tokens());
+    assert_ne!(lossless[0], plain[0]);
+    assert!(plain[0].trivia.is_empty());
+
+    // The trivia is kept on each node, so subtrees and serialized trees
+    // keep theirs, and plain trees serialize as they always have.
+    if let Branch::Nonterminal(sum) = &lossless[0].branches[0] {
+        assert_eq!(sum.to_source(), input);
+    }
+    let json = serde_json::to_string(&lossless[0]).unwrap();
+    let tree: Tree = serde_json::from_str(&json).unwrap();
+    assert_eq!(tree.to_source(), input);
+    assert!(!serde_json::to_string(&plain[0]).unwrap().contains("trivia"));
+}
+
+#[test]
+fn lossless_rejects_what_the_grammar_rejects() {
+    let trivia = Trivia::whitespace().line_comment("#");
+    let outcome = EarleyChart::eval_lossless(STATEMENTS, "let x = # y ;\n", &trivia).unwrap();
+    assert_eq!(outcome, EarleyOutcome::Rejected);
+}
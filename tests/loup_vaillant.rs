@@ -54,77 +54,81 @@ fn loup_vaillant_example_flipped(chart_len: usize) -> Vec<LinkedHashSet<FlippedI
 
     state_set = LinkedHashSet::new();
     origin = 0;
-    x = sum_to_sum_plus_prod(origin, 3).get(0).unwrap().clone().prod;
+    x = sum_to_sum_plus_prod(origin, 3)
+        .first()
+        .unwrap()
+        .clone()
+        .prod;
     state_set.insert(FlippedIState::new(x, 9));
 
-    x = sum_to_prod(origin, 1).get(0).unwrap().clone().prod;
+    x = sum_to_prod(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 1));
 
-    x = prod_to_factor(origin, 1).get(0).unwrap().clone().prod;
+    x = prod_to_factor(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 1));
 
-    x = factor_to_number(origin, 1).get(0).unwrap().clone().prod;
+    x = factor_to_number(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 1));
 
-    x = number_to_1(origin, 1).get(0).unwrap().clone().prod;
+    x = number_to_1(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 1));
     flipped[origin] = state_set.clone();
 
     state_set = LinkedHashSet::new();
     origin = 2;
-    x = prod_to_factor(origin, 1).get(0).unwrap().clone().prod;
+    x = prod_to_factor(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 9));
 
-    x = factor_to_lp_sum_rp(origin, 3).get(0).unwrap().clone().prod;
+    x = factor_to_lp_sum_rp(origin, 3).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 9));
     flipped[origin] = state_set.clone();
 
     state_set = LinkedHashSet::new();
     origin = 3;
-    x = sum_to_sum_sub_prod(origin, 3).get(0).unwrap().clone().prod;
+    x = sum_to_sum_sub_prod(origin, 3).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 8));
 
-    x = sum_to_prod(origin, 1).get(0).unwrap().clone().prod;
+    x = sum_to_prod(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 6));
 
-    x = sum_to_prod(origin, 1).get(0).unwrap().clone().prod;
+    x = sum_to_prod(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 4));
 
     x = prod_to_prod_mul_factor(origin, 3)
-        .get(0)
+        .first()
         .unwrap()
         .clone()
         .prod;
     state_set.insert(FlippedIState::new(x, 6));
 
-    x = prod_to_factor(origin, 1).get(0).unwrap().clone().prod;
+    x = prod_to_factor(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 4));
 
-    x = factor_to_number(origin, 1).get(0).unwrap().clone().prod;
+    x = factor_to_number(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 4));
 
-    x = number_to_2(origin, 1).get(0).unwrap().clone().prod;
+    x = number_to_2(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 4));
     flipped[origin] = state_set.clone();
 
     state_set = LinkedHashSet::new();
     origin = 5;
-    x = factor_to_number(origin, 1).get(0).unwrap().clone().prod;
+    x = factor_to_number(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 6));
 
-    x = number_to_3(origin, 1).get(0).unwrap().clone().prod;
+    x = number_to_3(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 6));
     flipped[origin] = state_set.clone();
 
     state_set = LinkedHashSet::new();
     origin = 7;
-    x = prod_to_factor(origin, 1).get(0).unwrap().clone().prod;
+    x = prod_to_factor(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 8));
 
-    x = factor_to_number(origin, 1).get(0).unwrap().clone().prod;
+    x = factor_to_number(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 8));
 
-    x = number_to_4(origin, 1).get(0).unwrap().clone().prod;
+    x = number_to_4(origin, 1).first().unwrap().clone().prod;
     state_set.insert(FlippedIState::new(x, 8));
     flipped[origin] = state_set.clone();
 
@@ -202,7 +206,7 @@ fn loup_vaillant_example_states() -> Vec<LinkedHashSet<IState>> {
         state_09_hs.push(s);
     }
 
-    vec![
+    [
         state_00_hs,
         state_01_hs,
         state_02_hs,
@@ -0,0 +1,214 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::{Grammar, Term};
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::module::Modules;
+use earley::outcome::EarleyOutcome;
+
+const NUMBERS: &str = "
+<number> ::= <digit> | <digit> <number>
+<digit>  ::= '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9'
+";
+
+fn accepts(grammar: &Grammar, input: &str) -> bool {
+    matches!(
+        EarleyChart::eval_grammar(grammar, input, None).unwrap(),
+        EarleyOutcome::Accepted(_)
+    )
+}
+
+fn message(result: Result<Grammar, Error>) -> String {
+    match result {
+        Err(Error::ModuleError(message)) => message,
+        other => panic!("expected a module error, got {:?}", other),
+    }
+}
+
+#[test]
+fn module_files() {
+    let grammar = Modules::load("tests/res/modules/calc.bnf").unwrap();
+    let names: Vec<String> = grammar
+        .productions_iter()
+        .map(|p| p.lhs.to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "<expr>",
+            "<term>",
+            "<numbers.number>",
+            "<numbers.digit>",
+            "<id.ident>",
+            "<id.letter>",
+            "<id.num.number>",
+            "<id.num.digit>",
+        ]
+    );
+
+    let outcome = EarleyChart::eval_grammar(&grammar, "x_1+(23+y)", None).unwrap();
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].yield_tokens().concat(), "x_1+(23+y)");
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+    assert!(!accepts(&grammar, "1x"));
+    assert!(!accepts(&grammar, "_+"));
+}
+
+#[test]
+fn module_override_and_extend() {
+    let modules = Modules::new()
+        .with_module("numbers.bnf", NUMBERS)
+        .with_module(
+            "binary.bnf",
+            "
+        import \"numbers.bnf\" as num;
+        <binary> ::= 'b' <num.number>
+        override <num.digit> ::= '0'
+                               | '1'
+        ",
+        );
+    let grammar = modules.resolve("binary.bnf").unwrap();
+    assert!(accepts(&grammar, "b1001"));
+    assert!(!accepts(&grammar, "b1021"));
+
+    let modules = modules.with_module(
+        "hex.bnf",
+        "
+        import <number> from \"numbers.bnf\" as num
+        <hex> ::= 'x' <number>
+        extend <num.digit> ::= 'a' | 'b' | 'c' | 'd' | 'e' | 'f'
+        ",
+    );
+    assert_eq!(
+        message(modules.resolve("hex.bnf")),
+        "hex.bnf:4: can't extend <num.digit>, it isn't imported"
+    );
+
+    let modules = modules.with_module(
+        "hex.bnf",
+        "
+        import <number>, <digit> from \"numbers.bnf\" as num
+        <hex> ::= 'x' <number>
+        extend <digit> ::= 'a' | 'b' | 'c' | 'd' | 'e' | 'f'
+        ",
+    );
+    let grammar = modules.resolve("hex.bnf").unwrap();
+    assert!(accepts(&grammar, "x1f0"));
+    assert!(!accepts(&grammar, "xg"));
+}
+
+#[test]
+fn module_named_imports_keep_namespace() {
+    let modules = Modules::new()
+        .with_module("numbers.bnf", NUMBERS)
+        .with_module(
+            "main.bnf",
+            "
+        import <number> from \"numbers.bnf\" as n
+        <list> ::= <number> | <number> ',' <list>
+        <digit> ::= 'd'
+        ",
+        );
+    let grammar = modules.resolve("main.bnf").unwrap();
+
+    // The imported rules keep their own `<digit>`.
+    let digits: Vec<&Term> = grammar
+        .productions_iter()
+        .map(|p| &p.lhs)
+        .filter(|lhs| lhs.to_string().contains("digit"))
+        .collect();
+    assert_eq!(
+        digits,
+        vec![
+            &Term::Nonterminal("digit".to_string()),
+            &Term::Nonterminal("n.digit".to_string())
+        ]
+    );
+    assert!(accepts(&grammar, "12,3"));
+    assert!(!accepts(&grammar, "d"));
+}
+
+#[test]
+fn module_errors() {
+    let with_main = |main: &str| {
+        Modules::new()
+            .with_module("numbers.bnf", NUMBERS)
+            .with_module("main.bnf", main)
+            .resolve("main.bnf")
+    };
+
+    assert_eq!(
+        message(with_main(
+            "import <number> from \"numbers.bnf\"\n<number> ::= 'n'"
+        )),
+        "main.bnf:2: rule <number> conflicts with <number> imported from numbers.bnf, \
+         use override or extend"
+    );
+    assert_eq!(
+        message(with_main("<a> ::= <b>\n\n<c> ::= 'c'")),
+        "main.bnf:1: rule <a> uses <b>, which isn't defined"
+    );
+    assert_eq!(
+        message(with_main("<a> ::= 'a'\noverride <a> ::= 'b'")),
+        "main.bnf:2: can't override <a>, it isn't imported"
+    );
+    assert_eq!(
+        message(with_main("import <integer> from \"numbers.bnf\"")),
+        "main.bnf:1: numbers.bnf has no rule <integer>"
+    );
+    assert_eq!(
+        message(with_main(
+            "import \"numbers.bnf\"\n<numbers.octal> ::= '0' <numbers.number>"
+        )),
+        "main.bnf:2: rule <numbers.octal> is in imported namespace numbers"
+    );
+    assert_eq!(
+        message(with_main(
+            "import \"numbers.bnf\"\nimport \"numbers.bnf\" as numbers"
+        )),
+        "main.bnf:2: namespace numbers is already in use"
+    );
+    assert_eq!(
+        message(with_main(
+            "import <number> from \"numbers.bnf\"\nimport <number> from \"numbers.bnf\" as n"
+        )),
+        "main.bnf:2: <number> is imported from both numbers.bnf and numbers.bnf"
+    );
+    assert_eq!(
+        message(with_main("import \"letters.bnf\"")),
+        "main.bnf:1: no module letters.bnf"
+    );
+    assert_eq!(
+        message(with_main("import numbers.bnf")),
+        "main.bnf:1: invalid import: import numbers.bnf"
+    );
+    assert_eq!(
+        message(with_main("'a' ::= <b>")),
+        "main.bnf:1: expected a rule or an import: 'a' ::= <b>"
+    );
+    assert_eq!(
+        message(with_main("import \"numbers.bnf\"")),
+        "main.bnf defines no rules"
+    );
+
+    // The error is in the module that has it.
+    let cycle = Modules::new()
+        .with_module("a.bnf", "import \"b.bnf\"\n<a> ::= <b.b>")
+        .with_module("b.bnf", "<b> ::= 'b'\nimport \"a.bnf\"");
+    assert_eq!(
+        message(cycle.resolve("a.bnf")),
+        "b.bnf:2: import cycle a.bnf -> b.bnf -> a.bnf"
+    );
+    let nested = Modules::new()
+        .with_module("a.bnf", "import \"b.bnf\"\n<a> ::= <b.b>")
+        .with_module("b.bnf", "<b> ::= 'b'\nextend <c> ::= 'c'");
+    assert_eq!(
+        message(nested.resolve("a.bnf")),
+        "b.bnf:2: can't extend <c>, it isn't imported"
+    );
+}
@@ -0,0 +1,320 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::{Grammar, Term};
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::normal::{self, Mapping};
+use earley::outcome::EarleyOutcome;
+use earley::tree::{Branch, Tree};
+
+const WIKI: &str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+const SUMS: &[&str] = &["1", "2+3", "2+3*4", "1*2*3+4*1", "4+4+4"];
+
+fn trees(grammar: &Grammar, input: &str) -> Vec<Tree> {
+    match EarleyChart::eval_grammar(grammar, input, None).unwrap() {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap(),
+        _ => vec![],
+    }
+}
+
+/// Parses every input with the transformed grammar and checks the trees
+/// translate to the trees the original grammar gives it.
+fn round_trip(original: &Grammar, transformed: &Grammar, mapping: &Mapping, inputs: &[&str]) {
+    for input in inputs {
+        let expected = trees(original, input);
+        let mut translated: Vec<Tree> = trees(transformed, input)
+            .iter()
+            .map(|tree| mapping.translate(tree).unwrap())
+            .collect();
+        assert!(!expected.is_empty(), "{}", input);
+        assert_eq!(translated.len(), expected.len(), "{}", input);
+        for tree in &expected {
+            let i = translated
+                .iter()
+                .position(|t| t == tree)
+                .unwrap_or_else(|| {
+                    panic!("{}: no translated tree is\n{}", input, tree);
+                });
+            translated.remove(i);
+            tree.validate(original, &tree.yield_tokens()).unwrap();
+        }
+    }
+}
+
+fn alternatives(grammar: &Grammar) -> Vec<Vec<Term>> {
+    grammar
+        .productions_iter()
+        .flat_map(|p| p.rhs_iter())
+        .map(|expr| expr.terms_iter().cloned().collect())
+        .collect()
+}
+
+fn node(lhs: &str, branches: Vec<Branch>) -> Branch {
+    Branch::Nonterminal(Tree::from_branches(
+        Term::Nonterminal(lhs.to_string()),
+        branches,
+    ))
+}
+
+fn leaf(terminal: &str) -> Branch {
+    Branch::Terminal(terminal.to_string())
+}
+
+#[test]
+fn normal_remove_epsilon() {
+    let grammar: Grammar = "
+        <S> ::= <A> 'b' <A> | <E> 'c'
+        <A> ::= 'a' | <E>
+        <E> ::= ''
+        "
+    .parse()
+    .unwrap();
+    let (transformed, mapping) = normal::remove_epsilon(&grammar).unwrap();
+    let expected: Grammar = "
+        <S> ::= <A> 'b' <A> | 'b' <A> | <A> 'b' | 'b' | 'c'
+        <A> ::= 'a'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+
+    let parsed = trees(&transformed, "ba");
+    assert_eq!(parsed.len(), 1);
+    let empty = || node("A", vec![node("E", vec![leaf("")])]);
+    let Branch::Nonterminal(tree) = node("S", vec![empty(), leaf("b"), node("A", vec![leaf("a")])])
+    else {
+        unreachable!()
+    };
+    assert_eq!(mapping.translate(&parsed[0]).unwrap(), tree);
+
+    let parsed = trees(&transformed, "c");
+    let Branch::Nonterminal(tree) = node("S", vec![node("E", vec![leaf("")]), leaf("c")]) else {
+        unreachable!()
+    };
+    assert_eq!(mapping.translate(&parsed[0]).unwrap(), tree);
+
+    let only_empty: Grammar = "<S> ::= <E> <E>\n<E> ::= ''".parse().unwrap();
+    assert_eq!(
+        normal::remove_epsilon(&only_empty).err(),
+        Some(Error::GrammarError(
+            "<S> only derives the empty string".to_string()
+        ))
+    );
+}
+
+#[test]
+fn normal_remove_units_and_useless() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let (transformed, mapping) = normal::remove_units(&grammar).unwrap();
+    let expected: Grammar = "
+        <P> ::= <S> '+' <M> | <M> '*' <T> | '1' | '2' | '3' | '4'
+        <S> ::= <S> '+' <M> | <M> '*' <T> | '1' | '2' | '3' | '4'
+        <M> ::= <M> '*' <T> | '1' | '2' | '3' | '4'
+        <T> ::= '1' | '2' | '3' | '4'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+    round_trip(&grammar, &transformed, &mapping, SUMS);
+
+    let grammar: Grammar = "
+        <S> ::= <A> 'x' | <B> | 'y'
+        <A> ::= 'a' <A>
+        <B> ::= 'b' | <C>
+        <C> ::= <C> 'c' | 'c'
+        <D> ::= 'd'
+        "
+    .parse()
+    .unwrap();
+    let (transformed, mapping) = normal::remove_useless(&grammar).unwrap();
+    let expected: Grammar = "
+        <S> ::= <B> | 'y'
+        <B> ::= 'b' | <C>
+        <C> ::= <C> 'c' | 'c'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+    round_trip(&grammar, &transformed, &mapping, &["y", "b", "ccc"]);
+
+    let useless: Grammar = "<S> ::= <S> 'a'".parse().unwrap();
+    assert_eq!(
+        normal::remove_useless(&useless).err(),
+        Some(Error::GrammarError("<S> derives no sentences".to_string()))
+    );
+}
+
+#[test]
+fn normal_chomsky() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let (transformed, mapping) = normal::chomsky(&grammar).unwrap();
+    for terms in alternatives(&transformed) {
+        match terms.as_slice() {
+            [Term::Terminal(_)] | [Term::Nonterminal(_), Term::Nonterminal(_)] => {}
+            _ => panic!("not in Chomsky normal form: {:?}", terms),
+        }
+    }
+    assert!(transformed.to_string().contains("<S> ::= <S> <S.1>"));
+    assert!(transformed.to_string().contains("<S.1> ::= <\"+\"> <M>"));
+    round_trip(&grammar, &transformed, &mapping, SUMS);
+
+    // Ambiguity, empty strings and a long alternative.
+    let grammar: Grammar = "
+        <E> ::= <E> <O> <E> | '(' <L> <E> ')' | 'x'
+        <O> ::= '+' | '-'
+        <L> ::= 'l' | ''
+        "
+    .parse()
+    .unwrap();
+    let (transformed, mapping) = normal::chomsky(&grammar).unwrap();
+    let expected: Grammar = "
+        <E> ::= <E> <E.1> | <\"(\"> <E.2> | <\"(\"> <E.4> | 'x'
+        <O> ::= '+' | '-'
+        <L> ::= 'l'
+        <E.1> ::= <O> <E>
+        <E.2> ::= <L> <E.3>
+        <E.3> ::= <E> <\")\">
+        <E.4> ::= <E> <\")\">
+        <\"(\"> ::= '('
+        <\")\"> ::= ')'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+    for input in ["x+x-x", "(lx+x)", "(x)-(l(x))"] {
+        let parsed = trees(&transformed, input);
+        assert!(!parsed.is_empty());
+        for tree in parsed {
+            let translated = mapping.translate(&tree).unwrap();
+            assert_eq!(translated.yield_tokens().concat(), input);
+        }
+    }
+    assert_eq!(trees(&transformed, "x+x-x").len(), 2);
+}
+
+#[test]
+fn normal_remove_left_recursion() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let (transformed, mapping) = normal::remove_left_recursion(&grammar).unwrap();
+    let expected: Grammar = "
+        <P> ::= <S>
+        <S> ::= <M> <S'> | <M>
+        <M> ::= <T> <M'> | <T>
+        <T> ::= '1' | '2' | '3' | '4'
+        <S'> ::= '+' <M> <S'> | '+' <M>
+        <M'> ::= '*' <T> <M'> | '*' <T>
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+    round_trip(&grammar, &transformed, &mapping, SUMS);
+
+    // Indirect left recursion, through `<B>`.
+    let grammar: Grammar = "
+        <A> ::= <B> 'a' | 'c'
+        <B> ::= <A> 'b' | <B> 'd' | 'e'
+        "
+    .parse()
+    .unwrap();
+    let (transformed, mapping) = normal::remove_left_recursion(&grammar).unwrap();
+    let expected: Grammar = "
+        <A> ::= <B> 'a' | 'c'
+        <B> ::= 'c' 'b' <B'> | 'c' 'b' | 'e' <B'> | 'e'
+        <B'> ::= 'a' 'b' <B'> | 'a' 'b' | 'd' <B'> | 'd'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+    round_trip(
+        &grammar,
+        &transformed,
+        &mapping,
+        &["c", "ea", "cba", "edaba", "cbabda"],
+    );
+
+    let hopeless: Grammar = "<A> ::= <A> 'a'".parse().unwrap();
+    assert_eq!(
+        normal::remove_left_recursion(&hopeless).err(),
+        Some(Error::GrammarError(
+            "Every alternative of <A> starts with <A>".to_string()
+        ))
+    );
+}
+
+#[test]
+fn normal_greibach() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let (transformed, mapping) = normal::greibach(&grammar).unwrap();
+    for terms in alternatives(&transformed) {
+        assert!(matches!(terms[0], Term::Terminal(_)), "{:?}", terms);
+        assert!(
+            terms[1..]
+                .iter()
+                .all(|term| matches!(term, Term::Nonterminal(_))),
+            "{:?}",
+            terms
+        );
+    }
+    round_trip(&grammar, &transformed, &mapping, SUMS);
+
+    let grammar: Grammar = "
+        <A> ::= <B> 'a' | 'c'
+        <B> ::= <A> 'b' | <B> 'd' | 'e'
+        "
+    .parse()
+    .unwrap();
+    let (transformed, mapping) = normal::greibach(&grammar).unwrap();
+    for terms in alternatives(&transformed) {
+        assert!(matches!(terms[0], Term::Terminal(_)), "{:?}", terms);
+    }
+    round_trip(
+        &grammar,
+        &transformed,
+        &mapping,
+        &["c", "ea", "cba", "edaba"],
+    );
+}
+
+#[test]
+fn normal_left_factor() {
+    let grammar: Grammar = "
+        <S> ::= 'a' 'b' <C> | 'a' 'b' 'd' | 'a' 'e' | 'f' | 'a'
+        <C> ::= 'c' | 'c' 'c'
+        "
+    .parse()
+    .unwrap();
+    let (transformed, mapping) = normal::left_factor(&grammar).unwrap();
+    let expected: Grammar = "
+        <S> ::= 'a' <S'> | 'f' | 'a'
+        <C> ::= 'c' | 'c' 'c'
+        <S'> ::= 'b' <S''> | 'e'
+        <S''> ::= <C> | 'd'
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(transformed, expected);
+    round_trip(
+        &grammar,
+        &transformed,
+        &mapping,
+        &["abc", "abcc", "abd", "ae", "f", "a"],
+    );
+}
+
+#[test]
+fn normal_translate_rejects_foreign_trees() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let (_, mapping) = normal::remove_left_recursion(&grammar).unwrap();
+    let tree = &trees(&grammar, "1+2")[0];
+    assert!(matches!(
+        mapping.translate(tree),
+        Err(Error::InvalidTree(_))
+    ));
+}
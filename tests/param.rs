@@ -0,0 +1,173 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::{Grammar, Term};
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use earley::param;
+use earley::tree::{Branch, Tree};
+
+const CALLS: &str = "
+    <call> ::= <name> <parens(list(arg, ','))>
+    <arg> ::= <name> | <call> | <parens(arg)>
+    <name> ::= 'f' | 'g' | 'x' | 'y'
+    <list(X, sep)> ::= <X> | <X> <sep> <list(X, sep)>
+    <parens(X)> ::= '(' <X> ')'
+    ";
+
+fn expand(source: &str) -> Result<Grammar, Error> {
+    param::expand(&source.parse().unwrap())
+}
+
+fn message(result: Result<Grammar, Error>) -> String {
+    match result {
+        Err(Error::ParamError(message)) => message,
+        other => panic!("expected a parameter error, got {:?}", other),
+    }
+}
+
+/// The names of the nodes of `tree`, depth first.
+fn names(tree: &Tree, names: &mut Vec<String>) {
+    if let Term::Nonterminal(name) = &tree.production.lhs {
+        names.push(name.to_string());
+    }
+    for branch in &tree.branches {
+        if let Branch::Nonterminal(tree) = branch {
+            self::names(tree, names);
+        }
+    }
+}
+
+#[test]
+fn param_expands_used_instances() {
+    let grammar = expand(CALLS).unwrap();
+    let expected: Grammar = "
+        <call> ::= <name> <parens(list(arg, \",\"))>
+        <arg> ::= <name> | <call> | <parens(arg)>
+        <name> ::= 'f' | 'g' | 'x' | 'y'
+        <parens(list(arg, \",\"))> ::= '(' <list(arg, \",\")> ')'
+        <parens(arg)> ::= '(' <arg> ')'
+        <list(arg, \",\")> ::= <arg> | <arg> ',' <list(arg, \",\")>
+        "
+    .parse()
+    .unwrap();
+    assert_eq!(grammar, expected);
+}
+
+#[test]
+fn param_trees_name_instances() {
+    let grammar = expand(CALLS).unwrap();
+    let outcome = EarleyChart::eval_grammar(&grammar, "f(x,g((y)))", None).unwrap();
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let trees = accepted.parse_forest().unwrap();
+        assert_eq!(trees.len(), 1);
+        let mut found = vec![];
+        names(&trees[0], &mut found);
+        assert_eq!(
+            found,
+            vec![
+                "call",
+                "name",
+                "parens(list(arg, \",\"))",
+                "list(arg, \",\")",
+                "arg",
+                "name",
+                "list(arg, \",\")",
+                "arg",
+                "call",
+                "name",
+                "parens(list(arg, \",\"))",
+                "list(arg, \",\")",
+                "arg",
+                "parens(arg)",
+                "arg",
+                "name",
+            ]
+        );
+
+        let list = Term::Nonterminal("list(arg, \",\")".to_string());
+        assert_eq!(
+            param::instance(&list),
+            Some((
+                "list".to_string(),
+                vec!["arg".to_string(), "\",\"".to_string()]
+            ))
+        );
+        let call = Term::Nonterminal("call".to_string());
+        assert_eq!(param::instance(&call), None);
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn param_terminal_arguments_and_options() {
+    // Terminals with commas and parentheses in them, and an optional sign.
+    let grammar = expand(
+        "
+        <num> ::= <opt('-', digits)>
+        <digits> ::= <list(digit, ',')> | <list(digit, ')')>
+        <digit> ::= '0' | '1'
+        <list(X, sep)> ::= <X> | <X> <sep> <list(X, sep)>
+        <opt(X, Y)> ::= <X> <Y> | <Y>
+        ",
+    )
+    .unwrap();
+    for (input, accepted) in [
+        ("1", true),
+        ("-1,0", true),
+        ("0)1", true),
+        ("--1", false),
+        ("1,0)1", false),
+    ] {
+        let outcome = EarleyChart::eval_grammar(&grammar, input, None).unwrap();
+        assert_eq!(
+            matches!(outcome, EarleyOutcome::Accepted(_)),
+            accepted,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn param_errors() {
+    assert_eq!(
+        message(expand("<s> ::= <list(x)>\n<list(X, sep)> ::= <X>")),
+        "<s>: <list> takes 2 arguments, not 1"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <pair(x, y)>\n<x> ::= 'x'")),
+        "<s>: no parameterized rule <pair>"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <f(s)>\n<f(X)> ::= <X(X)>")),
+        "<f(s)>: parameter X can't take arguments"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <f(s, s)>\n<f(X, X)> ::= <X>")),
+        "parameter X of <f> is given twice"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <f(s)>\n<f('x')> ::= <s>")),
+        "parameter \"x\" of <f> isn't a name"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <f(s)>\n<f(X)> ::= <X>\n<f(X, Y)> ::= <Y>")),
+        "<f> is defined with 1 and 2 parameters"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <f(s>")),
+        "<s>: invalid nonterminal <f(s>"
+    );
+    assert_eq!(
+        message(expand("<s> ::= <f(s)>\n<f(X)> ::= 'a' <f(f(X))> | 'b'")),
+        "<f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(f(s)))))))))))))))))))))))))))))))>: \
+         expanding <f> doesn't end"
+    );
+    assert_eq!(
+        message(expand("<f(X)> ::= <X>")),
+        "Grammar has no rules besides templates"
+    );
+}
@@ -0,0 +1,106 @@
+extern crate earley;
+
+use earley::earley::EarleyParser;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use earley::tree::Tree;
+
+const BYTES: &str = "
+    <list>  ::= <byte> | <byte> ',' <list>
+    <byte>  ::= <num>
+    <num>   ::= <digit> | <digit> <num>
+    <digit> ::= '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9'
+    ";
+
+fn bytes(sentence: &str) -> Option<Vec<Tree>> {
+    let outcome = EarleyParser::new(BYTES, sentence)
+        .unwrap()
+        .with_predicate("<byte> ::= <num>", |tokens| {
+            tokens.concat().parse::<u32>().is_ok_and(|n| n < 256)
+        })
+        .unwrap()
+        .earley_parse(None)
+        .unwrap();
+    match outcome {
+        EarleyOutcome::Accepted(accepted) => Some(accepted.parse_forest().unwrap()),
+        EarleyOutcome::Rejected => None,
+    }
+}
+
+#[test]
+fn predicate_vetoes_completion() {
+    assert_eq!(bytes("0,17,255").map(|trees| trees.len()), Some(1));
+    assert!(bytes("256").is_none());
+    assert!(bytes("1,1000,2").is_none());
+}
+
+#[test]
+fn predicate_prunes_parse_forest() {
+    let grammar = "
+    <stmt> ::= <call> | <var>
+    <call> ::= <name>
+    <var>  ::= <name>
+    <name> ::= 'f' | 'x'
+    ";
+    let declared = vec!["f".to_string()];
+    let parse = |sentence: &str| {
+        let declared = declared.clone();
+        let outcome = EarleyParser::new(grammar, sentence)
+            .unwrap()
+            .with_predicate("<call> ::= <name>", move |tokens| {
+                declared.contains(&tokens.concat())
+            })
+            .unwrap()
+            .earley_parse(None)
+            .unwrap();
+        match outcome {
+            EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap(),
+            EarleyOutcome::Rejected => vec![],
+        }
+    };
+
+    let trees = parse("f");
+    assert_eq!(trees.len(), 2);
+    let trees = parse("x");
+    assert_eq!(trees.len(), 1);
+    assert!(trees[0].query("//call").unwrap().is_empty());
+}
+
+#[test]
+fn predicates_all_have_to_hold() {
+    let outcome = EarleyParser::new(BYTES, "12")
+        .unwrap()
+        .with_predicate("<num> ::= <digit> <num>", |_| true)
+        .unwrap()
+        .with_predicate("<num> ::= <digit> <num>", |tokens| tokens.len() < 2)
+        .unwrap()
+        .earley_parse(None)
+        .unwrap();
+    assert_eq!(outcome, EarleyOutcome::Rejected);
+}
+
+#[test]
+fn predicate_for_bad_production() {
+    match EarleyParser::new(BYTES, "1")
+        .unwrap()
+        .with_predicate("<byte> ::=", |_| true)
+    {
+        Err(Error::BnfError(_)) => {}
+        Err(e) => panic!("expected BnfError, got {:?}", e),
+        Ok(_) => panic!("expected BnfError"),
+    }
+}
+
+#[test]
+fn predicate_for_production_not_in_grammar() {
+    for production in ["<bite> ::= <num>", "<byte> ::= <digit> <num>"] {
+        match EarleyParser::new(BYTES, "1")
+            .unwrap()
+            .with_predicate(production, |_| true)
+        {
+            Err(Error::GrammarError(_)) => {}
+            Err(e) => panic!("expected GrammarError, got {:?}", e),
+            Ok(_) => panic!("expected GrammarError for {}", production),
+        }
+    }
+}
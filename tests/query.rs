@@ -0,0 +1,102 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use earley::query::Query;
+use earley::tree::Tree;
+use earley::visit::NodeRef;
+
+fn sentence_tree(sentence: &str) -> Tree {
+    let grammar_str = "
+    <S> ::= <NP> <VP>
+    <NP> ::= <D> <N> | <N>
+    <VP> ::= <V> <NP>
+    <D> ::= 'their' | 'the' | 'a'
+    <N> ::= 'dogs' | 'cats' | 'food'
+    <V> ::= 'love' | 'eat'
+    ";
+
+    match EarleyChart::eval(grammar_str, sentence, Some(' ')).unwrap() {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap().remove(0),
+        EarleyOutcome::Rejected => panic!("{} rejected", sentence),
+    }
+}
+
+#[test]
+fn query_by_child_text() {
+    let tree = sentence_tree("the dogs eat their food");
+
+    let matches = tree.query("//NP[D = \"their\"]").unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].node.path, vec![1, 1]);
+    assert_eq!(matches[0].node.tokens, 3..5);
+    assert!(matches[0].captures.is_empty());
+
+    let all = tree.query("//<NP>").unwrap();
+    let paths: Vec<Vec<usize>> = all.iter().map(|m| m.node.path.clone()).collect();
+    assert_eq!(paths, vec![vec![0], vec![1, 1]]);
+}
+
+#[test]
+fn query_captures() {
+    let tree = sentence_tree("the dogs eat their food");
+
+    let matches = tree.query("//VP@vp/NP[D@det = 'their']@object").unwrap();
+    assert_eq!(matches.len(), 1);
+    let names: Vec<&str> = matches[0]
+        .captures
+        .iter()
+        .map(|(n, _)| n.as_str())
+        .collect();
+    assert_eq!(names, vec!["vp", "det", "object"]);
+    assert_eq!(matches[0].get("vp").unwrap().tokens, 2..5);
+    assert_eq!(matches[0].get("det").unwrap().path, vec![1, 1, 0]);
+    assert_eq!(matches[0].get("object").unwrap().node, matches[0].node.node);
+    match matches[0].get("det").unwrap().node {
+        NodeRef::Nonterminal(det) => assert_eq!(det.yield_tokens(), vec!["their"]),
+        NodeRef::Terminal(_) => panic!("captured a leaf"),
+    }
+}
+
+#[test]
+fn query_axes_and_terminals() {
+    let tree = sentence_tree("the dogs eat their food");
+
+    let dogs = tree.query("//N/\"dogs\"").unwrap();
+    assert_eq!(dogs.len(), 1);
+    assert_eq!(dogs[0].node.node, NodeRef::Terminal("dogs"));
+    assert_eq!(dogs[0].node.path, vec![0, 1, 0]);
+    assert_eq!(dogs[0].node.tokens, 1..2);
+
+    assert_eq!(tree.query("/S/NP").unwrap().len(), 1);
+    assert_eq!(tree.query("/NP").unwrap().len(), 0);
+    assert_eq!(tree.query("/S//NP").unwrap().len(), 2);
+    assert_eq!(tree.query("//VP//'food'").unwrap().len(), 1);
+
+    let food = tree.query("//*[. = 'food']").unwrap();
+    assert_eq!(food.len(), 1);
+    assert_eq!(food[0].node.path, vec![1, 1, 1]);
+
+    let with_determiner = tree.query("//NP[D]").unwrap();
+    assert_eq!(with_determiner.len(), 2);
+    assert_eq!(tree.query("//NP[//'cats']").unwrap().len(), 0);
+}
+
+#[test]
+fn query_syntax_errors() {
+    for query in &[
+        "//NP[D = ]",
+        "//NP[D",
+        "<NP",
+        "//NP@",
+        "NP/",
+        "//NP]",
+        "//NP 'x'",
+    ] {
+        match Query::new(query) {
+            Err(Error::QueryError(_)) => {}
+            other => panic!("{:?} gave {:?}", query, other),
+        }
+    }
+}
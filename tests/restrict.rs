@@ -0,0 +1,121 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::earley::EarleyParser;
+use earley::error::Error;
+use earley::layout::Layout;
+use earley::outcome::EarleyOutcome;
+use earley::restrict::{Restrictions, TokenSet};
+use earley::tree::Tree;
+
+const STATEMENTS: &str = "
+    <prog>   ::= <stmt> | <stmt> <prog>
+    <stmt>   ::= <if> <Id> | <Id>
+    <if>     ::= 'i' 'f'
+    <Id>     ::= <letter> | <letter> <Id>
+    <letter> ::= 'i' | 'f' | 'x' | 'y'
+    <ws>     ::= ' ' | ' ' <ws>
+    ";
+
+fn letters() -> TokenSet {
+    TokenSet::new().range('a', 'z')
+}
+
+fn trees(restrictions: Restrictions, sentence: &str) -> Vec<Tree> {
+    let outcome = EarleyParser::new(STATEMENTS, sentence)
+        .unwrap()
+        .with_layout(Layout::new("ws").lexical("if").lexical("Id"))
+        .unwrap()
+        .with_restrictions(restrictions)
+        .unwrap()
+        .earley_parse(None)
+        .unwrap();
+    match outcome {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap(),
+        EarleyOutcome::Rejected => vec![],
+    }
+}
+
+#[test]
+fn unrestricted_is_ambiguous() {
+    assert!(trees(Restrictions::new(), "ifx").len() > 1);
+}
+
+#[test]
+fn follow_restriction_takes_longest_match() {
+    let restrictions = Restrictions::new()
+        .follow("Id", letters())
+        .follow("if", letters());
+
+    // Neither the keyword nor an identifier stops inside a word.
+    let trees = trees(restrictions.clone(), "ifx");
+    assert_eq!(trees.len(), 1);
+    assert!(trees[0].query("//if").unwrap().is_empty());
+
+    let spaced = self::trees(restrictions, "if x");
+    assert_eq!(spaced.len(), 2);
+}
+
+#[test]
+fn reserved_keyword() {
+    let restrictions = Restrictions::new()
+        .follow("Id", letters())
+        .follow("if", letters())
+        .reserve("Id", "if");
+
+    let trees = trees(restrictions.clone(), "if x");
+    assert_eq!(trees.len(), 1);
+    assert_eq!(trees[0].query("//if").unwrap().len(), 1);
+
+    assert!(self::trees(restrictions.clone(), "if").is_empty());
+    assert_eq!(self::trees(restrictions, "iff").len(), 1);
+}
+
+#[test]
+fn terminal_lookahead() {
+    let grammar = "
+    <E> ::= <E> '-' <T> | <E> '-' '>' <T> | <T>
+    <T> ::= 'x' | 'y'
+    ";
+    let accepts = |restrictions: Restrictions, sentence: &str| match EarleyChart::eval_restricted(
+        grammar,
+        sentence,
+        None,
+        restrictions,
+    )
+    .unwrap()
+    {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap().len(),
+        EarleyOutcome::Rejected => 0,
+    };
+
+    assert_eq!(accepts(Restrictions::new(), "x-y"), 1);
+    assert_eq!(accepts(Restrictions::new(), "x->y"), 1);
+
+    let not_arrow = Restrictions::new().negative_lookahead("-", TokenSet::new().token(">"));
+    assert_eq!(accepts(not_arrow.clone(), "x-y"), 1);
+    assert_eq!(accepts(not_arrow, "x->y"), 0);
+
+    let arrow_only = Restrictions::new().lookahead("-", TokenSet::new().token(">"));
+    assert_eq!(accepts(arrow_only.clone(), "x-y"), 0);
+    assert_eq!(accepts(arrow_only, "x->y"), 1);
+}
+
+#[test]
+fn token_set() {
+    let set = TokenSet::new().range('a', 'c').token("if");
+    assert!(set.contains("a"));
+    assert!(set.contains("c"));
+    assert!(set.contains("if"));
+    assert!(!set.contains("d"));
+    assert!(!set.contains("ab"));
+}
+
+#[test]
+fn unknown_restricted_symbol() {
+    let restrictions = Restrictions::new().follow("Ident", letters());
+    match EarleyChart::eval_restricted(STATEMENTS, "x", None, restrictions) {
+        Err(Error::GrammarError(_)) => {}
+        other => panic!("expected GrammarError, got {:?}", other),
+    }
+}
@@ -0,0 +1,127 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use earley::rewrite::Rewriter;
+use earley::tree::{Branch, Tree};
+
+fn parse(grammar_str: &str, sentence: &str) -> Tree {
+    match EarleyChart::eval(grammar_str, sentence, None).unwrap() {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap().remove(0),
+        EarleyOutcome::Rejected => panic!("{} rejected", sentence),
+    }
+}
+
+fn wiki_tree(sentence: &str) -> Tree {
+    parse(
+        "
+        <P> ::= <S>
+        <S> ::= <S> '+' <M> | <M>
+        <M> ::= <M> '*' <T> | <T>
+        <T> ::= '1' | '2' | '3' | '4'
+        ",
+        sentence,
+    )
+}
+
+fn list_tree(sentence: &str) -> Tree {
+    parse(
+        "
+        <L> ::= <I> | <I> ',' <L>
+        <I> ::= 'a' | 'b'
+        ",
+        sentence,
+    )
+}
+
+/// The tree as an s-expression, to compare shapes at a glance.
+fn shape(tree: &Tree) -> String {
+    let branches: Vec<String> = tree
+        .branches
+        .iter()
+        .map(|branch| match branch {
+            Branch::Nonterminal(t) => shape(t),
+            Branch::Terminal(s) => format!("{:?}", s),
+        })
+        .collect();
+    format!("({} {})", tree.name(), branches.join(" "))
+}
+
+#[test]
+fn collapse_unit_chains() {
+    let rewriter = Rewriter::new().rule("($a ($b @x))", "($a @x)").unwrap();
+    let tree = rewriter.rewrite(&wiki_tree("2+3*4")).unwrap();
+
+    assert_eq!(
+        shape(&tree),
+        "(P (S (S \"2\") \"+\" (M (M \"3\") \"*\" (T \"4\"))))"
+    );
+    assert_eq!(tree.yield_tokens(), vec!["2", "+", "3", "*", "4"]);
+
+    let chain = rewriter.rewrite(&wiki_tree("2")).unwrap();
+    assert_eq!(shape(&chain), "(P \"2\")");
+    assert_eq!(chain.production.to_string(), "<P> ::= \"2\"");
+}
+
+#[test]
+fn remove_punctuation_and_flatten() {
+    let rewriter = Rewriter::new()
+        .rule("','", "")
+        .unwrap()
+        .rule("(L @first (L @rest...))", "(L @first @rest...)")
+        .unwrap();
+    let tree = rewriter.rewrite(&list_tree("a,b,a")).unwrap();
+
+    assert_eq!(shape(&tree), "(L (I \"a\") (I \"b\") (I \"a\"))");
+    assert_eq!(tree.production.to_string(), "<L> ::= <I> <I> <I>");
+}
+
+#[test]
+fn rename_and_repeated_captures() {
+    let rewriter = Rewriter::new()
+        .rule("(I 'a')", "(A)")
+        .unwrap()
+        .rule("(L @x ',' (L @x))", "(Pair @x)")
+        .unwrap();
+
+    let same = rewriter.rewrite(&list_tree("a,a")).unwrap();
+    assert_eq!(shape(&same), "(Pair (A ))");
+
+    let different = rewriter.rewrite(&list_tree("a,b")).unwrap();
+    assert_eq!(shape(&different), "(L (A ) \",\" (L (I \"b\")))");
+}
+
+#[test]
+fn rewrite_errors() {
+    for (pattern, replacement) in &[
+        ("(S @x", "(S)"),
+        ("(S @x)", "(S @y)"),
+        ("(S)", "($n)"),
+        ("(S) (M)", "(S)"),
+        ("(S)", "_"),
+        ("(S)@x", "(T)@y"),
+        ("@xs...", ""),
+    ] {
+        match Rewriter::new().rule(pattern, replacement) {
+            Err(Error::RewriteError(_)) => {}
+            Err(e) => panic!("{:?} -> {:?} gave {:?}", pattern, replacement, e),
+            Ok(_) => panic!("{:?} -> {:?} was accepted", pattern, replacement),
+        }
+    }
+
+    let drop_root = Rewriter::new().rule("($a @x)", "@x").unwrap();
+    match drop_root.rewrite(&wiki_tree("1")) {
+        Err(Error::RewriteError(_)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    let endless = Rewriter::new()
+        .rule("(T @x)", "(T (T @x))")
+        .unwrap()
+        .max_passes(10);
+    match endless.rewrite(&wiki_tree("1")) {
+        Err(Error::RewriteError(msg)) => assert!(msg.contains("10 passes")),
+        other => panic!("{:?}", other),
+    }
+}
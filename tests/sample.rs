@@ -0,0 +1,108 @@
+extern crate bnf;
+extern crate earley;
+extern crate rand;
+
+use bnf::Production;
+use earley::chart::EarleyChart;
+use earley::forest::Forest;
+use earley::outcome::EarleyOutcome;
+use earley::sample::Sampler;
+use earley::tree::Tree;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const AMBIGUOUS_SUM: &str = "
+    <E> ::= <E> '+' <E> | '1'
+    ";
+
+#[test]
+fn sample_is_reproducible_from_seed() {
+    let outcome = EarleyChart::eval(AMBIGUOUS_SUM, "1+1+1+1+1", None).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let draw = |seed: u64| -> Vec<Tree> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| accepted.sample(&mut rng).unwrap())
+                .collect()
+        };
+
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn sample_is_uniform_over_derivations() {
+    let outcome = EarleyChart::eval(AMBIGUOUS_SUM, "1+1+1+1", None).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let forest = Forest::new(&accepted);
+        let sampler = Sampler::uniform(&forest);
+        assert_eq!(sampler.total(), 5.0);
+
+        let trees: Vec<Tree> = accepted
+            .k_best(5, |_| 0.0)
+            .unwrap()
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect();
+        let mut counts = vec![0; trees.len()];
+
+        let mut rng = StdRng::seed_from_u64(2020);
+        for _ in 0..5000 {
+            let tree = sampler.sample(&mut rng).unwrap();
+            let i = trees.iter().position(|t| *t == tree).unwrap();
+            counts[i] += 1;
+        }
+
+        for count in counts {
+            assert!(count > 850 && count < 1150, "{}", count);
+        }
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn sample_weighted_follows_production_weights() {
+    let grammar_str = "
+    <S> ::= <NP> <VP>
+    <VP> ::= <V> <NP> | <V> <NP> <PP>
+    <NP> ::= <N> | <N> <PP>
+    <PP> ::= <P> <NP>
+    <N> ::= 'Garnet' | 'Peridot' | 'binoculars'
+    <V> ::= 'saw'
+    <P> ::= 'with'
+    ";
+    let verb_attached: Production = "<VP> ::= <V> <NP> <PP>".parse().unwrap();
+
+    let outcome =
+        EarleyChart::eval(grammar_str, "Garnet saw Peridot with binoculars", Some(' ')).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let mut rng = StdRng::seed_from_u64(11);
+        let weight = |p: &Production| if *p == verb_attached { 3.0 } else { 1.0 };
+
+        let mut verb = 0;
+        for _ in 0..4000 {
+            let tree = accepted.sample_weighted(&mut rng, weight).unwrap();
+            if tree.to_string().contains("<VP> ::= <V> <NP> <PP>") {
+                verb += 1;
+            }
+        }
+        assert!(verb > 2850 && verb < 3150, "{}", verb);
+
+        let never = |p: &Production| if *p == verb_attached { 0.0 } else { 1.0 };
+        for _ in 0..100 {
+            let tree = accepted.sample_weighted(&mut rng, never).unwrap();
+            assert!(!tree.to_string().contains("<VP> ::= <V> <NP> <PP>"));
+        }
+
+        assert!(accepted.sample_weighted(&mut rng, |_| 0.0).is_err());
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
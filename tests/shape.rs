@@ -0,0 +1,115 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::outcome::{EarleyAccepted, EarleyOutcome};
+use earley::shape::Shape;
+use earley::tree::{Branch, Tree};
+
+fn accept(grammar_str: &str, sentence: &str) -> EarleyAccepted {
+    match EarleyChart::eval(grammar_str, sentence, None).unwrap() {
+        EarleyOutcome::Accepted(accepted) => *accepted,
+        EarleyOutcome::Rejected => panic!("{} rejected", sentence),
+    }
+}
+
+fn wiki(sentence: &str) -> EarleyAccepted {
+    accept(
+        "
+        <P> ::= <S>
+        <S> ::= <S> '+' <M> | <M>
+        <M> ::= <M> '*' <T> | <T>
+        <T> ::= '1' | '2' | '3' | '4'
+        ",
+        sentence,
+    )
+}
+
+/// The tree as an s-expression, to compare shapes at a glance.
+fn shape(tree: &Tree) -> String {
+    let branches: Vec<String> = tree
+        .branches
+        .iter()
+        .map(|branch| match branch {
+            Branch::Nonterminal(t) => shape(t),
+            Branch::Terminal(s) => format!("{:?}", s),
+        })
+        .collect();
+    format!("({} {})", tree.name(), branches.join(" "))
+}
+
+#[test]
+fn default_shape_is_the_parse_forest() {
+    let accepted = wiki("2+3*4");
+    assert_eq!(
+        accepted.shaped_parse_forest(&Shape::new()).unwrap(),
+        accepted.parse_forest().unwrap()
+    );
+}
+
+#[test]
+fn collapse_unit_chains() {
+    let trees = wiki("2+3*4")
+        .shaped_parse_forest(&Shape::new().collapse_units())
+        .unwrap();
+    assert_eq!(trees.len(), 1);
+    assert_eq!(
+        shape(&trees[0]),
+        "(P (S (T \"2\") \"+\" (M (T \"3\") \"*\" (T \"4\"))))"
+    );
+    assert_eq!(trees[0].production.to_string(), "<P> ::= <S>");
+    match &trees[0].branches[0] {
+        Branch::Nonterminal(sum) => {
+            assert_eq!(sum.production.to_string(), "<S> ::= <T> \"+\" <M>")
+        }
+        Branch::Terminal(s) => panic!("{} under <P>", s),
+    }
+
+    let single = wiki("2")
+        .shaped_parse_forest(&Shape::new().collapse_units())
+        .unwrap();
+    assert_eq!(shape(&single[0]), "(P (T \"2\"))");
+}
+
+#[test]
+fn transparent_nonterminals_and_dropped_terminals() {
+    let accepted = accept(
+        "
+        <call> ::= <name> '(' <_args> ')'
+        <_args> ::= <arg> | <arg> ',' <_args>
+        <arg> ::= <name>
+        <name> ::= 'f' | 'x' | 'y'
+        ",
+        "f(x,y,x)",
+    );
+
+    let trees = accepted
+        .shaped_parse_forest(
+            &Shape::new()
+                .transparent_prefix("_")
+                .drop_terminal("(")
+                .drop_terminal(")")
+                .drop_terminal(","),
+        )
+        .unwrap();
+    assert_eq!(
+        shape(&trees[0]),
+        "(call (name \"f\") (arg (name \"x\")) (arg (name \"y\")) (arg (name \"x\")))"
+    );
+    assert_eq!(
+        trees[0].production.to_string(),
+        "<call> ::= <name> <arg> <arg> <arg>"
+    );
+
+    let inlined = accepted
+        .shaped_parse_forest(
+            &Shape::new()
+                .transparent("arg")
+                .transparent("_args")
+                .collapse_units(),
+        )
+        .unwrap();
+    assert_eq!(
+        shape(&inlined[0]),
+        "(call (name \"f\") \"(\" (name \"x\") \",\" (name \"y\") \",\" (name \"x\") \")\")"
+    );
+}
@@ -0,0 +1,8 @@
+use earley::grammar::{grammar, StaticGrammar};
+
+static SUMS: StaticGrammar = grammar! {
+    <S> <S> "+" <M> | <M>
+    <M> ::= "1" | "2"
+};
+
+fn main() {}
@@ -0,0 +1,9 @@
+use earley::grammar::{grammar, StaticGrammar};
+
+static SUMS: StaticGrammar = grammar! {
+    <S> ::= <S> "+" <M> | <M>
+    <M> ::= <M> "*" <T> | <Digit>
+    <T> ::= "1" | "2"
+};
+
+fn main() {}
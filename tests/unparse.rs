@@ -0,0 +1,91 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::outcome::EarleyOutcome;
+use earley::token::{Span, TokenizerInfo};
+use earley::tree::{Branch, Tree};
+
+fn replace_leaf(tree: &mut Tree, from: &str, to: &str) {
+    for branch in tree.branches.iter_mut() {
+        match branch {
+            Branch::Nonterminal(t) => replace_leaf(t, from, to),
+            Branch::Terminal(s) => {
+                if s == from {
+                    *s = to.to_string();
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn yield_tokens_is_the_input() {
+    let grammar_str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+    let outcome = EarleyChart::eval(grammar_str, "2+3*4", None).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        for tree in accepted.parse_forest().unwrap() {
+            assert_eq!(tree.yield_tokens(), accepted.input);
+            let info = accepted.tokenizer_info.as_ref().unwrap();
+            assert_eq!(tree.unparse(info).unwrap(), "2+3*4");
+        }
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn unparse_round_trips_separators() {
+    let grammar_str = "
+    <Block>      ::=  <If>
+    <Block>      ::= '{}'
+    <If>         ::=  'if' <Block>
+    <If>         ::=  'if' <Block> 'else' <Block>
+    ";
+
+    let sentence = "if if {} else {}";
+    let outcome = EarleyChart::eval(grammar_str, sentence, Some(' ')).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        let info = accepted.tokenizer_info.clone().unwrap();
+        assert_eq!(info.spans[1], Span::new(3, 5));
+
+        let mut forest = accepted.parse_forest().unwrap();
+        assert_eq!(forest.len(), 2);
+        for tree in &forest {
+            assert_eq!(tree.unparse(&info).unwrap(), sentence);
+        }
+
+        replace_leaf(&mut forest[0], "else", "otherwise");
+        assert_eq!(forest[0].unparse(&info).unwrap(), "if if {} otherwise {}");
+
+        // <If> ::= "if" <Block> covers every token, the <Block> doesn't
+        if let Branch::Nonterminal(t) = &forest[1].branches[0] {
+            assert!(t.unparse(&info).is_ok());
+            if let Branch::Nonterminal(block) = &t.branches[1] {
+                assert!(block.unparse(&info).is_err());
+            }
+        }
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn tokenizer_info_keeps_empty_tokens() {
+    let info = TokenizerInfo::split("a,,b,", Some(','));
+    assert_eq!(info.tokens(), vec!["a", "", "b", ""]);
+    assert_eq!(info.gap(0), "");
+    assert_eq!(info.gap(2), ",");
+    assert_eq!(info.gap(4), "");
+
+    let info = TokenizerInfo::split("añb", None);
+    assert_eq!(info.tokens(), vec!["a", "ñ", "b"]);
+    assert_eq!(info.spans[2], Span::new(3, 4));
+}
@@ -0,0 +1,133 @@
+extern crate bnf;
+extern crate earley;
+
+use bnf::Grammar;
+use earley::chart::EarleyChart;
+use earley::error::Error;
+use earley::outcome::EarleyOutcome;
+use earley::tree::{Branch, Tree};
+use std::fs;
+
+const WIKI: &str = "
+    <P> ::= <S>
+    <S> ::= <S> \"+\" <M> | <M>
+    <M> ::= <M> \"*\" <T> | <T>
+    <T> ::= \"1\" | \"2\" | \"3\" | \"4\"
+    ";
+
+fn tokens(sentence: &str) -> Vec<String> {
+    sentence.chars().map(|c| c.to_string()).collect()
+}
+
+fn saved_forest(fname: &str) -> Vec<Tree> {
+    let fjson = fs::read_to_string(fname).unwrap();
+    serde_json::from_str(&fjson).unwrap()
+}
+
+fn wiki_tree() -> Tree {
+    saved_forest("tests/res/wiki_pf.json").remove(0)
+}
+
+fn assert_invalid(result: Result<(), Error>) {
+    match result {
+        Err(Error::InvalidTree(_)) => {}
+        other => panic!("expected an InvalidTree error, got {:?}", other),
+    }
+}
+
+#[test]
+fn saved_forests_are_valid() {
+    let cases = vec![
+        (WIKI, "2+3*4", "tests/res/wiki_pf.json"),
+        (
+            "
+            <Sum> ::= <Sum> '+' <Product> | <Sum> '-' <Product> | <Product>
+            <Product> ::= <Product> '*' <Factor> | <Product> '/' <Factor> | <Factor>
+            <Factor> ::= '(' <Sum> ')' | <Number>
+            <Number> ::= '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9'
+            ",
+            "1+(2*3-4)",
+            "tests/res/loup_pf.json",
+        ),
+        (
+            "
+            <Block>      ::=  <If>
+            <Block>      ::= '{' '}'
+            <If>         ::=  'i' 'f' <Block>
+            <If>         ::=  'i' 'f' <Block> 'e' 'l' 's' 'e' <Block>
+            ",
+            "ifif{}else{}",
+            "tests/res/ambiguous_pf.json",
+        ),
+    ];
+
+    for (grammar_str, sentence, fname) in cases {
+        let grammar: Grammar = grammar_str.parse().unwrap();
+        for tree in saved_forest(fname) {
+            assert_eq!(tree.validate(&grammar, &tokens(sentence)), Ok(()));
+        }
+    }
+}
+
+#[test]
+fn parse_forest_trees_are_valid() {
+    let grammar_str = "
+    <S> ::= <N> <VP>
+    <VP> ::= <V> <NP>
+    <V> ::= 'joined' | 'followed' | 'lost' | 'caught'
+    <N> ::= 'Amethyst' | 'Perl' | 'Garnet' | 'Peridot' | 'Stevonnie' | 'Lapis' | 'friend'
+    <NP> ::= <D> <N>
+    <D> ::= 'their' | 'a'
+    ";
+    let grammar: Grammar = grammar_str.parse().unwrap();
+
+    let outcome = EarleyChart::eval(grammar_str, "Amethyst joined a friend", Some(' ')).unwrap();
+
+    if let EarleyOutcome::Accepted(accepted) = outcome {
+        for tree in accepted.parse_forest().unwrap() {
+            assert_eq!(tree.validate(&grammar, &accepted.input), Ok(()));
+        }
+    } else {
+        assert_eq!("EarleyOutcome::Accepted", "EarleyOutcome::Rejected");
+    }
+}
+
+#[test]
+fn wrong_input_is_invalid() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let tree = wiki_tree();
+
+    assert_invalid(tree.validate(&grammar, &tokens("2+3*1")));
+    assert_invalid(tree.validate(&grammar, &tokens("2+3*4*1")));
+    assert_invalid(tree.validate(&grammar, &tokens("2+3*")));
+}
+
+#[test]
+fn wrong_shape_is_invalid() {
+    let grammar: Grammar = WIKI.parse().unwrap();
+    let input = tokens("2+3*4");
+
+    // <P> ::= <S> isn't the start of a subtree rooted at <S>
+    if let Branch::Nonterminal(s) = &wiki_tree().branches[0] {
+        assert_invalid(s.validate(&grammar, &input));
+    }
+
+    // drop the "*" leaf from <M> ::= <M> "*" <T>
+    let mut tree = wiki_tree();
+    if let Branch::Nonterminal(s) = &mut tree.branches[0] {
+        if let Branch::Nonterminal(m) = &mut s.branches[2] {
+            m.branches.remove(1);
+        }
+    }
+    assert_invalid(tree.validate(&grammar, &input));
+
+    // swap a node's production for one the grammar doesn't have
+    let mut tree = wiki_tree();
+    tree.production = "<P> ::= <S> <S>".parse().unwrap();
+    assert_invalid(tree.validate(&grammar, &input));
+
+    // a terminal where the production wants a nonterminal
+    let mut tree = wiki_tree();
+    tree.branches[0] = Branch::Terminal("2".to_string());
+    assert_invalid(tree.validate(&grammar, &input));
+}
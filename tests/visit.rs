@@ -0,0 +1,134 @@
+extern crate earley;
+
+use earley::chart::EarleyChart;
+use earley::outcome::EarleyOutcome;
+use earley::tree::{Branch, Tree};
+use earley::visit::{Hooks, NodeRef, Visitor, VisitorMut};
+
+fn wiki_tree(sentence: &str) -> Tree {
+    let grammar_str = "
+    <P> ::= <S>
+    <S> ::= <S> '+' <M> | <M>
+    <M> ::= <M> '*' <T> | <T>
+    <T> ::= '1' | '2' | '3' | '4'
+    ";
+
+    match EarleyChart::eval(grammar_str, sentence, None).unwrap() {
+        EarleyOutcome::Accepted(accepted) => accepted.parse_forest().unwrap().remove(0),
+        EarleyOutcome::Rejected => panic!("{} rejected", sentence),
+    }
+}
+
+fn evaluate(tree: &Tree) -> i64 {
+    tree.fold(
+        &mut |tree: &Tree, values: Vec<i64>| match tree.branches.get(1) {
+            Some(Branch::Terminal(op)) if op == "+" => values[0] + values[2],
+            Some(Branch::Terminal(op)) if op == "*" => values[0] * values[2],
+            _ => values[0],
+        },
+        &mut |token: &str| token.parse().unwrap_or(0),
+    )
+}
+
+#[test]
+fn fold_evaluates_arithmetic() {
+    assert_eq!(evaluate(&wiki_tree("2+3*4")), 14);
+    assert_eq!(evaluate(&wiki_tree("2*3+4")), 10);
+    assert_eq!(evaluate(&wiki_tree("1+2+3*4*2")), 27);
+}
+
+#[derive(Default)]
+struct Trace {
+    events: Vec<String>,
+}
+
+impl Visitor for Trace {
+    fn enter(&mut self, name: &str, _tree: &Tree) {
+        self.events.push(format!("+{}", name));
+    }
+    fn leave(&mut self, name: &str, _tree: &Tree) {
+        self.events.push(format!("-{}", name));
+    }
+    fn terminal(&mut self, token: &str) {
+        self.events.push(token.to_string());
+    }
+}
+
+#[test]
+fn visitor_enters_and_leaves_in_order() {
+    let mut trace = Trace::default();
+    wiki_tree("2*3").walk(&mut trace);
+
+    assert_eq!(
+        trace.events.join(" "),
+        "+P +S +M +M +T 2 -T -M * +T 3 -T -M -S -P"
+    );
+}
+
+#[test]
+fn hooks_are_keyed_by_name() {
+    let mut entered = 0;
+    let mut left = vec![];
+    {
+        let mut hooks = Hooks::new()
+            .on_enter("T", |_| entered += 1)
+            .on_leave("M", |tree| left.push(tree.yield_tokens().join("")));
+        wiki_tree("2*3+4").walk(&mut hooks);
+    }
+
+    assert_eq!(entered, 3);
+    assert_eq!(left, vec!["2", "2*3", "4"]);
+}
+
+struct Double;
+
+impl VisitorMut for Double {
+    fn terminal(&mut self, token: &mut String) {
+        if let Ok(n) = token.parse::<i64>() {
+            *token = (n * 2).to_string();
+        }
+    }
+}
+
+#[test]
+fn visitor_mut_rewrites_leaves() {
+    let mut tree = wiki_tree("2+3*4");
+    tree.walk_mut(&mut Double);
+    assert_eq!(tree.yield_tokens(), vec!["4", "+", "6", "*", "8"]);
+    assert_eq!(evaluate(&tree), 52);
+}
+
+#[test]
+fn iterators_report_depth_and_path() {
+    let tree = wiki_tree("2*3");
+
+    let preorder: Vec<String> = tree
+        .preorder()
+        .map(|node| match node.node {
+            NodeRef::Nonterminal(t) => format!("{}{}", t.name(), node.depth),
+            NodeRef::Terminal(s) => format!("'{}'{}", s, node.depth),
+        })
+        .collect();
+    assert_eq!(preorder.join(" "), "P0 S1 M2 M3 T4 '2'5 '*'3 T3 '3'4");
+
+    let postorder: Vec<String> = tree
+        .postorder()
+        .map(|node| match node.node {
+            NodeRef::Nonterminal(t) => t.name().to_string(),
+            NodeRef::Terminal(s) => format!("'{}'", s),
+        })
+        .collect();
+    assert_eq!(postorder.join(" "), "'2' T M '*' '3' T M S P");
+
+    let breadth_first: Vec<usize> = tree.breadth_first().map(|node| node.depth).collect();
+    assert_eq!(breadth_first, vec![0, 1, 2, 3, 3, 3, 4, 4, 5]);
+
+    for node in tree.preorder() {
+        assert_eq!(node.depth, node.path.len());
+    }
+    let three = tree
+        .preorder()
+        .find(|node| node.node == NodeRef::Terminal("3"))
+        .unwrap();
+    assert_eq!(three.path, vec![0, 0, 2, 0]);
+}